@@ -0,0 +1,283 @@
+//! Local SQLite backend
+
+use super::{BackendError, HearthBackend};
+use crate::models::{Character, CharacterItem, MessageRevision, ScenarioItem, StoryItem, StoryMessage};
+use crate::entity::Entity;
+use crate::guidance::{MessageGuidance, LOCAL_USER_ID};
+use crate::message_tree::StoryBranch;
+use crate::persona::Persona;
+use crate::{Database, LocalBackendConfig};
+use async_trait::async_trait;
+
+/// Backend that stores everything in the local database
+#[derive(Clone)]
+pub struct LocalBackend {
+    db: Database,
+}
+
+impl LocalBackend {
+    /// Open the configured database, apply migrations and seed it if empty
+    pub fn open(config: &LocalBackendConfig) -> Result<Self, BackendError> {
+        let db = Database::open(config)?;
+        log::info!("Database ready (schema version {})", db.schema_version()?);
+        if db.seed_if_empty()? {
+            log::info!("Seeded database with sample data");
+        }
+        Ok(Self { db })
+    }
+
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+
+    pub fn database(&self) -> &Database {
+        &self.db
+    }
+}
+
+#[async_trait(?Send)]
+impl HearthBackend for LocalBackend {
+    fn name(&self) -> String {
+        "Local (SQLite)".to_string()
+    }
+
+    async fn list_characters(&self) -> Result<Vec<CharacterItem>, BackendError> {
+        Ok(self.db.characters().list()?)
+    }
+
+    async fn get_character(&self, id: &str) -> Result<Option<CharacterItem>, BackendError> {
+        Ok(self.db.characters().get(id)?)
+    }
+
+    async fn create_character(&self, character: &CharacterItem) -> Result<(), BackendError> {
+        Ok(self.db.characters().create(character)?)
+    }
+
+    async fn update_character(&self, character: &CharacterItem) -> Result<(), BackendError> {
+        Ok(self.db.characters().update(character)?)
+    }
+
+    async fn delete_character(&self, id: &str) -> Result<(), BackendError> {
+        Ok(self.db.characters().delete(id)?)
+    }
+
+    async fn get_character_definition(&self, id: &str) -> Result<Option<Character>, BackendError> {
+        Ok(self.db.characters().get_definition(id)?)
+    }
+
+    async fn save_character_definition(&self, character: &Character) -> Result<(), BackendError> {
+        character.validate()?;
+        Ok(self.db.characters().save_definition(character)?)
+    }
+
+    async fn list_scenarios(&self) -> Result<Vec<ScenarioItem>, BackendError> {
+        Ok(self.db.scenarios().list()?)
+    }
+
+    async fn get_scenario(&self, id: &str) -> Result<Option<ScenarioItem>, BackendError> {
+        Ok(self.db.scenarios().get(id)?)
+    }
+
+    async fn create_scenario(&self, scenario: &ScenarioItem) -> Result<(), BackendError> {
+        Ok(self.db.scenarios().create(scenario)?)
+    }
+
+    async fn update_scenario(&self, scenario: &ScenarioItem) -> Result<(), BackendError> {
+        Ok(self.db.scenarios().update(scenario)?)
+    }
+
+    async fn delete_scenario(&self, id: &str) -> Result<(), BackendError> {
+        Ok(self.db.scenarios().delete(id)?)
+    }
+
+    async fn list_stories(&self) -> Result<Vec<StoryItem>, BackendError> {
+        Ok(self.db.stories().list()?)
+    }
+
+    async fn get_story(&self, id: &str) -> Result<Option<StoryItem>, BackendError> {
+        Ok(self.db.stories().get(id)?)
+    }
+
+    async fn create_story(&self, story: &StoryItem) -> Result<(), BackendError> {
+        Ok(self.db.stories().create(story)?)
+    }
+
+    async fn update_story(&self, story: &StoryItem) -> Result<(), BackendError> {
+        Ok(self.db.stories().update(story)?)
+    }
+
+    async fn delete_story(&self, id: &str) -> Result<(), BackendError> {
+        Ok(self.db.stories().delete(id)?)
+    }
+
+    async fn import_story(&self, story: &StoryItem, messages: &[StoryMessage]) -> Result<(), BackendError> {
+        Ok(self.db.stories().create_with_messages(story, messages)?)
+    }
+
+    async fn list_personas(&self) -> Result<Vec<Persona>, BackendError> {
+        Ok(self.db.personas().list()?)
+    }
+
+    async fn get_persona(&self, id: &str) -> Result<Option<Persona>, BackendError> {
+        Ok(self.db.personas().get(id)?)
+    }
+
+    async fn create_persona(&self, persona: &Persona) -> Result<(), BackendError> {
+        persona.validate()?;
+        Ok(self.db.personas().create(persona)?)
+    }
+
+    async fn update_persona(&self, persona: &Persona) -> Result<(), BackendError> {
+        persona.validate()?;
+        Ok(self.db.personas().update(persona)?)
+    }
+
+    async fn delete_persona(&self, id: &str) -> Result<(), BackendError> {
+        Ok(self.db.personas().delete(id)?)
+    }
+
+    async fn list_entities(&self) -> Result<Vec<Entity>, BackendError> {
+        Ok(self.db.entities().list()?)
+    }
+
+    async fn get_entity(&self, id: &str) -> Result<Option<Entity>, BackendError> {
+        Ok(self.db.entities().get(id)?)
+    }
+
+    async fn create_entity(&self, entity: &Entity) -> Result<(), BackendError> {
+        entity.validate()?;
+        Ok(self.db.entities().create(entity)?)
+    }
+
+    async fn update_entity(&self, entity: &Entity) -> Result<(), BackendError> {
+        entity.validate()?;
+        Ok(self.db.entities().update(entity)?)
+    }
+
+    async fn delete_entity(&self, id: &str) -> Result<(), BackendError> {
+        Ok(self.db.entities().delete(id)?)
+    }
+
+    async fn list_messages(&self, story_id: &str) -> Result<Vec<StoryMessage>, BackendError> {
+        Ok(self.db.messages().load_tree(story_id)?.active_path())
+    }
+
+    async fn add_message(&self, story_id: &str, message: &StoryMessage) -> Result<(), BackendError> {
+        Ok(self.db.messages().create(story_id, message)?)
+    }
+
+    async fn update_message(&self, message: &StoryMessage) -> Result<(), BackendError> {
+        Ok(self.db.messages().update(message)?)
+    }
+
+    async fn delete_message(&self, id: &str) -> Result<(), BackendError> {
+        Ok(self.db.messages().delete(id)?)
+    }
+
+    async fn edit_message(&self, id: &str, content: &str) -> Result<StoryMessage, BackendError> {
+        Ok(self.db.messages().edit(id, content)?)
+    }
+
+    async fn list_revisions(&self, message_id: &str) -> Result<Vec<MessageRevision>, BackendError> {
+        Ok(self.db.messages().revisions(message_id)?)
+    }
+
+    async fn restore_revision(&self, message_id: &str, revision_id: &str) -> Result<StoryMessage, BackendError> {
+        Ok(self.db.messages().restore_revision(message_id, revision_id)?)
+    }
+
+    async fn add_guidance(&self, guidance: &MessageGuidance) -> Result<(), BackendError> {
+        Ok(self.db.guidance().create(guidance)?)
+    }
+
+    async fn list_guidance(&self, message_id: &str) -> Result<Vec<MessageGuidance>, BackendError> {
+        Ok(self.db.guidance().list_for_message(message_id, LOCAL_USER_ID)?)
+    }
+
+    async fn list_branches(&self, story_id: &str) -> Result<Vec<StoryBranch>, BackendError> {
+        Ok(self.db.messages().load_tree(story_id)?.branches())
+    }
+
+    async fn fork_at(&self, story_id: &str, message_id: &str) -> Result<(), BackendError> {
+        let mut tree = self.db.messages().load_tree(story_id)?;
+        tree.fork(message_id)?;
+        Ok(self.db.messages().set_cursor(story_id, tree.cursor())?)
+    }
+
+    async fn switch_branch(&self, story_id: &str, leaf_id: &str) -> Result<(), BackendError> {
+        let mut tree = self.db.messages().load_tree(story_id)?;
+        tree.switch_branch(leaf_id)?;
+        Ok(self.db.messages().set_cursor(story_id, tree.cursor())?)
+    }
+
+    async fn prune_branch(&self, story_id: &str, leaf_id: &str) -> Result<(), BackendError> {
+        let mut tree = self.db.messages().load_tree(story_id)?;
+        let removed = tree.prune_branch(leaf_id)?;
+        Ok(self
+            .db
+            .messages()
+            .remove_branch(story_id, &removed, tree.cursor())?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::StoryRole;
+    use crate::{sample, DatabaseError};
+
+    fn backend() -> LocalBackend {
+        LocalBackend::new(Database::open_in_memory().unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_character_crud() {
+        let backend = backend();
+        let mut character = sample::sample_characters().remove(0);
+        backend.create_character(&character).await.unwrap();
+        assert_eq!(backend.get_character(&character.id).await.unwrap().as_ref(), Some(&character));
+
+        character.is_favorite = !character.is_favorite;
+        backend.update_character(&character).await.unwrap();
+        assert_eq!(backend.list_characters().await.unwrap(), vec![character.clone()]);
+
+        backend.delete_character(&character.id).await.unwrap();
+        assert!(backend.get_character(&character.id).await.unwrap().is_none());
+        assert!(matches!(
+            backend.delete_character(&character.id).await,
+            Err(BackendError::Database(DatabaseError::NotFound(_)))
+        ));
+
+        let invalid = Character::new("", "Nameless", "");
+        assert!(matches!(
+            backend.save_character_definition(&invalid).await,
+            Err(BackendError::Validation(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_story_messages_and_revisions() {
+        let backend = backend();
+        let mut story = sample::sample_stories().remove(0);
+        backend.create_story(&story).await.unwrap();
+        story.title = "Renamed".to_string();
+        backend.update_story(&story).await.unwrap();
+        assert_eq!(backend.get_story(&story.id).await.unwrap().unwrap().title, "Renamed");
+
+        let first = StoryMessage::new("m1", StoryRole::Narrator, "It begins.");
+        let second = StoryMessage::new("m2", StoryRole::Narrator, "It goes on.");
+        backend.add_message(&story.id, &first).await.unwrap();
+        backend.add_message(&story.id, &second).await.unwrap();
+        let ids: Vec<String> = backend.list_messages(&story.id).await.unwrap().into_iter().map(|m| m.id).collect();
+        assert_eq!(ids, ["m1", "m2"]);
+
+        backend.edit_message("m1", "It starts.").await.unwrap();
+        let revision = backend.list_revisions("m1").await.unwrap().remove(0);
+        assert!(backend.restore_revision("m2", &revision.id).await.is_err());
+        assert_eq!(backend.restore_revision("m1", &revision.id).await.unwrap().content, "It begins.");
+
+        backend.delete_story(&story.id).await.unwrap();
+        assert!(backend.get_story(&story.id).await.unwrap().is_none());
+        assert!(backend.list_messages(&story.id).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,150 @@
+//! Backend-agnostic data access
+//!
+//! Views talk to a [`HearthBackend`] and never care whether the data lives in the
+//! local SQLite database or on a remote Hearth server. The implementation is picked
+//! from `AppSettings.selected_backend` by [`connect_backend`].
+
+use crate::models::{
+    Character, CharacterItem, MessageRevision, ScenarioItem, StoryItem, StoryMessage, ValidationError,
+};
+use crate::entity::Entity;
+use crate::guidance::MessageGuidance;
+use crate::message_tree::{StoryBranch, TreeError};
+use crate::persona::Persona;
+use crate::AppSettings;
+use async_trait::async_trait;
+use std::rc::Rc;
+use thiserror::Error;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod local;
+mod remote;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use local::*;
+pub use remote::*;
+
+#[derive(Error, Debug)]
+pub enum BackendError {
+    #[cfg(not(target_arch = "wasm32"))]
+    #[error("Database error: {0}")]
+    Database(#[from] crate::DatabaseError),
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("Server error ({status}): {message}")]
+    Server { status: u16, message: String },
+    #[error("Not found: {0}")]
+    NotFound(String),
+    #[error("Validation error: {0}")]
+    Validation(#[from] ValidationError),
+    #[error("Branch error: {0}")]
+    Tree(#[from] TreeError),
+    #[error("Backend not configured: {0}")]
+    NotConfigured(String),
+}
+
+/// Uniform data access for characters, scenarios, stories, personas, entities and messages
+///
+/// Futures are not `Send` so the trait can be implemented on top of browser APIs.
+#[async_trait(?Send)]
+pub trait HearthBackend {
+    /// Human readable name of the backend, e.g. for settings and logs
+    fn name(&self) -> String;
+
+    // Characters
+    async fn list_characters(&self) -> Result<Vec<CharacterItem>, BackendError>;
+    async fn get_character(&self, id: &str) -> Result<Option<CharacterItem>, BackendError>;
+    async fn create_character(&self, character: &CharacterItem) -> Result<(), BackendError>;
+    async fn update_character(&self, character: &CharacterItem) -> Result<(), BackendError>;
+    async fn delete_character(&self, id: &str) -> Result<(), BackendError>;
+    /// Full definition including personality, example dialogue and default histories
+    async fn get_character_definition(&self, id: &str) -> Result<Option<Character>, BackendError>;
+    /// Validate and store a full definition, creating the character if needed
+    async fn save_character_definition(&self, character: &Character) -> Result<(), BackendError>;
+
+    // Scenarios
+    async fn list_scenarios(&self) -> Result<Vec<ScenarioItem>, BackendError>;
+    async fn get_scenario(&self, id: &str) -> Result<Option<ScenarioItem>, BackendError>;
+    async fn create_scenario(&self, scenario: &ScenarioItem) -> Result<(), BackendError>;
+    async fn update_scenario(&self, scenario: &ScenarioItem) -> Result<(), BackendError>;
+    async fn delete_scenario(&self, id: &str) -> Result<(), BackendError>;
+
+    // Stories
+    async fn list_stories(&self) -> Result<Vec<StoryItem>, BackendError>;
+    async fn get_story(&self, id: &str) -> Result<Option<StoryItem>, BackendError>;
+    async fn create_story(&self, story: &StoryItem) -> Result<(), BackendError>;
+    async fn update_story(&self, story: &StoryItem) -> Result<(), BackendError>;
+    async fn delete_story(&self, id: &str) -> Result<(), BackendError>;
+    /// Create a story with its messages in one step, keeping nothing if any part fails
+    async fn import_story(&self, story: &StoryItem, messages: &[StoryMessage]) -> Result<(), BackendError>;
+
+    // Personas
+    async fn list_personas(&self) -> Result<Vec<Persona>, BackendError>;
+    async fn get_persona(&self, id: &str) -> Result<Option<Persona>, BackendError>;
+    async fn create_persona(&self, persona: &Persona) -> Result<(), BackendError>;
+    async fn update_persona(&self, persona: &Persona) -> Result<(), BackendError>;
+    async fn delete_persona(&self, id: &str) -> Result<(), BackendError>;
+
+    // Entity library
+    async fn list_entities(&self) -> Result<Vec<Entity>, BackendError>;
+    async fn get_entity(&self, id: &str) -> Result<Option<Entity>, BackendError>;
+    async fn create_entity(&self, entity: &Entity) -> Result<(), BackendError>;
+    async fn update_entity(&self, entity: &Entity) -> Result<(), BackendError>;
+    async fn delete_entity(&self, id: &str) -> Result<(), BackendError>;
+
+    // Messages
+    /// Messages on the story's active branch in conversation order
+    async fn list_messages(&self, story_id: &str) -> Result<Vec<StoryMessage>, BackendError>;
+    /// Append a message to the active branch
+    async fn add_message(&self, story_id: &str, message: &StoryMessage) -> Result<(), BackendError>;
+    async fn update_message(&self, message: &StoryMessage) -> Result<(), BackendError>;
+    async fn delete_message(&self, id: &str) -> Result<(), BackendError>;
+    /// Change a message's content, keeping the previous content as a revision
+    async fn edit_message(&self, id: &str, content: &str) -> Result<StoryMessage, BackendError>;
+    /// Earlier versions of a message, newest first
+    async fn list_revisions(&self, message_id: &str) -> Result<Vec<MessageRevision>, BackendError>;
+    async fn restore_revision(&self, message_id: &str, revision_id: &str) -> Result<StoryMessage, BackendError>;
+
+    // Guidance
+    /// Attach hidden guidance to a message the current user sent
+    async fn add_guidance(&self, guidance: &MessageGuidance) -> Result<(), BackendError>;
+    /// Guidance on a message that the current user is allowed to see
+    async fn list_guidance(&self, message_id: &str) -> Result<Vec<MessageGuidance>, BackendError>;
+
+    // Branches
+    async fn list_branches(&self, story_id: &str) -> Result<Vec<StoryBranch>, BackendError>;
+    /// Continue the story from `message_id`; the next message starts a new branch
+    async fn fork_at(&self, story_id: &str, message_id: &str) -> Result<(), BackendError>;
+    async fn switch_branch(&self, story_id: &str, leaf_id: &str) -> Result<(), BackendError>;
+    /// Delete the messages that only belong to the branch ending at `leaf_id`
+    async fn prune_branch(&self, story_id: &str, leaf_id: &str) -> Result<(), BackendError>;
+}
+
+/// Shared handle to the active backend
+pub type SharedBackend = Rc<dyn HearthBackend>;
+
+/// Build the backend selected in the settings
+///
+/// `None` selects the local SQLite backend on native platforms and the server
+/// hosting the page on web.
+pub fn connect_backend(settings: &AppSettings) -> Result<SharedBackend, BackendError> {
+    if let Some(backend_id) = &settings.selected_backend {
+        let config = settings
+            .remote_backends
+            .iter()
+            .find(|b| &b.id == backend_id)
+            .ok_or_else(|| BackendError::NotConfigured(format!("remote backend '{backend_id}'")))?;
+        log::info!("Connecting to remote backend: {}", config.name);
+        return Ok(Rc::new(RemoteBackend::new(config)));
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let config = settings.local_backend.clone().unwrap_or_default();
+        Ok(Rc::new(LocalBackend::open(&config)?))
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        Ok(Rc::new(RemoteBackend::same_origin()?))
+    }
+}
@@ -0,0 +1,443 @@
+//! Remote Hearth server backend over HTTP
+
+use super::{BackendError, HearthBackend};
+use crate::models::{Character, CharacterItem, MessageRevision, ScenarioItem, StoryItem, StoryMessage};
+use crate::entity::Entity;
+use crate::guidance::MessageGuidance;
+use crate::message_tree::StoryBranch;
+use crate::persona::Persona;
+use crate::RemoteBackendConfig;
+use async_trait::async_trait;
+use reqwest::{Client, Method, RequestBuilder, Response, StatusCode, Url};
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Backend that forwards every operation to a Hearth server's REST API
+#[derive(Clone)]
+pub struct RemoteBackend {
+    name: String,
+    base_url: String,
+    auth_token: Option<String>,
+    client: Client,
+}
+
+impl RemoteBackend {
+    pub fn new(config: &RemoteBackendConfig) -> Self {
+        Self {
+            name: config.name.clone(),
+            base_url: config.url.trim_end_matches('/').to_string(),
+            // References stay unresolved while the credential store is locked
+            auth_token: config.auth_token.clone().filter(|token| crate::credential_name(token).is_none()),
+            client: Client::new(),
+        }
+    }
+
+    /// Connect to the server that is hosting the web app
+    #[cfg(target_arch = "wasm32")]
+    pub fn same_origin() -> Result<Self, BackendError> {
+        let origin = web_sys::window()
+            .and_then(|window| window.location().origin().ok())
+            .ok_or_else(|| BackendError::NotConfigured("could not determine page origin".to_string()))?;
+        Ok(Self {
+            name: "Remote (Current Server)".to_string(),
+            base_url: origin,
+            auth_token: None,
+            client: Client::new(),
+        })
+    }
+
+    /// Request to `{base}/api/` followed by `segments`, each percent-encoded as one path segment
+    fn request(&self, method: Method, segments: &[&str]) -> Result<RequestBuilder, BackendError> {
+        let invalid = || BackendError::NotConfigured(format!("invalid server URL '{}'", self.base_url));
+        let mut url = Url::parse(&self.base_url).map_err(|_| invalid())?;
+        url.path_segments_mut()
+            .map_err(|_| invalid())?
+            .pop_if_empty()
+            .push("api")
+            .extend(segments);
+        let request = self.client.request(method, url);
+        Ok(match &self.auth_token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        })
+    }
+
+    async fn check(response: Response) -> Result<Response, BackendError> {
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+
+        let message = response.text().await.unwrap_or_default();
+        if status == StatusCode::NOT_FOUND {
+            Err(BackendError::NotFound(message))
+        } else {
+            Err(BackendError::Server {
+                status: status.as_u16(),
+                message,
+            })
+        }
+    }
+
+    async fn get_json<T: DeserializeOwned>(&self, path: &[&str]) -> Result<T, BackendError> {
+        let response = self.request(Method::GET, path)?.send().await?;
+        Ok(Self::check(response).await?.json().await?)
+    }
+
+    async fn get_optional<T: DeserializeOwned>(&self, path: &[&str]) -> Result<Option<T>, BackendError> {
+        match self.get_json(path).await {
+            Ok(value) => Ok(Some(value)),
+            Err(BackendError::NotFound(_)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn send_json<B: Serialize + ?Sized>(
+        &self,
+        method: Method,
+        path: &[&str],
+        body: &B,
+    ) -> Result<(), BackendError> {
+        let response = self.request(method, path)?.json(body).send().await?;
+        Self::check(response).await?;
+        Ok(())
+    }
+
+    /// Like `send_json`, but decode the response body
+    async fn send_json_for<B: Serialize + ?Sized, T: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &[&str],
+        body: &B,
+    ) -> Result<T, BackendError> {
+        let response = self.request(method, path)?.json(body).send().await?;
+        Ok(Self::check(response).await?.json().await?)
+    }
+
+    async fn delete(&self, path: &[&str]) -> Result<(), BackendError> {
+        let response = self.request(Method::DELETE, path)?.send().await?;
+        Self::check(response).await?;
+        Ok(())
+    }
+}
+
+#[async_trait(?Send)]
+impl HearthBackend for RemoteBackend {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    async fn list_characters(&self) -> Result<Vec<CharacterItem>, BackendError> {
+        self.get_json(&["characters"]).await
+    }
+
+    async fn get_character(&self, id: &str) -> Result<Option<CharacterItem>, BackendError> {
+        self.get_optional(&["characters", id]).await
+    }
+
+    async fn create_character(&self, character: &CharacterItem) -> Result<(), BackendError> {
+        self.send_json(Method::POST, &["characters"], character).await
+    }
+
+    async fn update_character(&self, character: &CharacterItem) -> Result<(), BackendError> {
+        self.send_json(Method::PUT, &["characters", &character.id], character)
+            .await
+    }
+
+    async fn delete_character(&self, id: &str) -> Result<(), BackendError> {
+        self.delete(&["characters", id]).await
+    }
+
+    async fn get_character_definition(&self, id: &str) -> Result<Option<Character>, BackendError> {
+        self.get_optional(&["characters", id, "definition"]).await
+    }
+
+    async fn save_character_definition(&self, character: &Character) -> Result<(), BackendError> {
+        character.validate()?;
+        self.send_json(
+            Method::PUT,
+            &["characters", &character.id, "definition"],
+            character,
+        )
+        .await
+    }
+
+    async fn list_scenarios(&self) -> Result<Vec<ScenarioItem>, BackendError> {
+        self.get_json(&["scenarios"]).await
+    }
+
+    async fn get_scenario(&self, id: &str) -> Result<Option<ScenarioItem>, BackendError> {
+        self.get_optional(&["scenarios", id]).await
+    }
+
+    async fn create_scenario(&self, scenario: &ScenarioItem) -> Result<(), BackendError> {
+        self.send_json(Method::POST, &["scenarios"], scenario).await
+    }
+
+    async fn update_scenario(&self, scenario: &ScenarioItem) -> Result<(), BackendError> {
+        self.send_json(Method::PUT, &["scenarios", &scenario.id], scenario)
+            .await
+    }
+
+    async fn delete_scenario(&self, id: &str) -> Result<(), BackendError> {
+        self.delete(&["scenarios", id]).await
+    }
+
+    async fn list_stories(&self) -> Result<Vec<StoryItem>, BackendError> {
+        self.get_json(&["stories"]).await
+    }
+
+    async fn get_story(&self, id: &str) -> Result<Option<StoryItem>, BackendError> {
+        self.get_optional(&["stories", id]).await
+    }
+
+    async fn create_story(&self, story: &StoryItem) -> Result<(), BackendError> {
+        self.send_json(Method::POST, &["stories"], story).await
+    }
+
+    async fn update_story(&self, story: &StoryItem) -> Result<(), BackendError> {
+        self.send_json(Method::PUT, &["stories", &story.id], story)
+            .await
+    }
+
+    async fn delete_story(&self, id: &str) -> Result<(), BackendError> {
+        self.delete(&["stories", id]).await
+    }
+
+    async fn import_story(&self, story: &StoryItem, messages: &[StoryMessage]) -> Result<(), BackendError> {
+        let body = serde_json::json!({ "story": story, "messages": messages });
+        self.send_json(Method::POST, &["stories", "import"], &body).await
+    }
+
+    async fn list_personas(&self) -> Result<Vec<Persona>, BackendError> {
+        self.get_json(&["personas"]).await
+    }
+
+    async fn get_persona(&self, id: &str) -> Result<Option<Persona>, BackendError> {
+        self.get_optional(&["personas", id]).await
+    }
+
+    async fn create_persona(&self, persona: &Persona) -> Result<(), BackendError> {
+        persona.validate()?;
+        self.send_json(Method::POST, &["personas"], persona).await
+    }
+
+    async fn update_persona(&self, persona: &Persona) -> Result<(), BackendError> {
+        persona.validate()?;
+        self.send_json(Method::PUT, &["personas", &persona.id], persona)
+            .await
+    }
+
+    async fn delete_persona(&self, id: &str) -> Result<(), BackendError> {
+        self.delete(&["personas", id]).await
+    }
+
+    async fn list_entities(&self) -> Result<Vec<Entity>, BackendError> {
+        self.get_json(&["entities"]).await
+    }
+
+    async fn get_entity(&self, id: &str) -> Result<Option<Entity>, BackendError> {
+        self.get_optional(&["entities", id]).await
+    }
+
+    async fn create_entity(&self, entity: &Entity) -> Result<(), BackendError> {
+        entity.validate()?;
+        self.send_json(Method::POST, &["entities"], entity).await
+    }
+
+    async fn update_entity(&self, entity: &Entity) -> Result<(), BackendError> {
+        entity.validate()?;
+        self.send_json(Method::PUT, &["entities", &entity.id], entity)
+            .await
+    }
+
+    async fn delete_entity(&self, id: &str) -> Result<(), BackendError> {
+        self.delete(&["entities", id]).await
+    }
+
+    async fn list_messages(&self, story_id: &str) -> Result<Vec<StoryMessage>, BackendError> {
+        self.get_json(&["stories", story_id, "messages"]).await
+    }
+
+    async fn add_message(&self, story_id: &str, message: &StoryMessage) -> Result<(), BackendError> {
+        self.send_json(Method::POST, &["stories", story_id, "messages"], message)
+            .await
+    }
+
+    async fn update_message(&self, message: &StoryMessage) -> Result<(), BackendError> {
+        self.send_json(Method::PUT, &["messages", &message.id], message)
+            .await
+    }
+
+    async fn delete_message(&self, id: &str) -> Result<(), BackendError> {
+        self.delete(&["messages", id]).await
+    }
+
+    async fn edit_message(&self, id: &str, content: &str) -> Result<StoryMessage, BackendError> {
+        let body = serde_json::json!({ "content": content });
+        self.send_json_for(Method::PATCH, &["messages", id], &body)
+            .await
+    }
+
+    async fn list_revisions(&self, message_id: &str) -> Result<Vec<MessageRevision>, BackendError> {
+        self.get_json(&["messages", message_id, "revisions"]).await
+    }
+
+    async fn restore_revision(&self, message_id: &str, revision_id: &str) -> Result<StoryMessage, BackendError> {
+        self.send_json_for(
+            Method::POST,
+            &["messages", message_id, "revisions", revision_id, "restore"],
+            &serde_json::json!({}),
+        )
+        .await
+    }
+
+    async fn add_guidance(&self, guidance: &MessageGuidance) -> Result<(), BackendError> {
+        self.send_json(
+            Method::POST,
+            &["messages", &guidance.message_id, "guidance"],
+            guidance,
+        )
+        .await
+    }
+
+    /// The server only returns guidance written by the authenticated user
+    async fn list_guidance(&self, message_id: &str) -> Result<Vec<MessageGuidance>, BackendError> {
+        self.get_json(&["messages", message_id, "guidance"]).await
+    }
+
+    async fn list_branches(&self, story_id: &str) -> Result<Vec<StoryBranch>, BackendError> {
+        self.get_json(&["stories", story_id, "branches"]).await
+    }
+
+    async fn fork_at(&self, story_id: &str, message_id: &str) -> Result<(), BackendError> {
+        let body = serde_json::json!({ "message_id": message_id });
+        self.send_json(Method::POST, &["stories", story_id, "fork"], &body)
+            .await
+    }
+
+    async fn switch_branch(&self, story_id: &str, leaf_id: &str) -> Result<(), BackendError> {
+        let body = serde_json::json!({ "leaf_id": leaf_id });
+        self.send_json(Method::PUT, &["stories", story_id, "active-branch"], &body)
+            .await
+    }
+
+    async fn prune_branch(&self, story_id: &str, leaf_id: &str) -> Result<(), BackendError> {
+        self.delete(&["stories", story_id, "branches", leaf_id]).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::StoryRole;
+    use crate::sample;
+    use wiremock::matchers::{body_json, header, header_exists, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn backend(server: &MockServer, auth_token: Option<&str>) -> RemoteBackend {
+        RemoteBackend::new(&RemoteBackendConfig {
+            id: "server".to_string(),
+            name: "Server".to_string(),
+            url: format!("{}/", server.uri()),
+            auth_token: auth_token.map(str::to_string),
+            last_connected: None,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_requests_go_to_api_paths_with_the_bearer_token() {
+        let server = MockServer::start().await;
+        let characters = sample::sample_characters();
+        Mock::given(method("GET"))
+            .and(path("/api/characters"))
+            .and(header("Authorization", "Bearer secret"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&characters))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let message = StoryMessage::new("m1", StoryRole::Narrator, "It begins.");
+        Mock::given(method("POST"))
+            .and(path("/api/stories/s1/messages"))
+            .and(body_json(&message))
+            .respond_with(ResponseTemplate::new(201))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/messages/m1/revisions/r1/restore"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&message))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/api/stories/s1"))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let remote = backend(&server, Some("secret"));
+        assert_eq!(remote.list_characters().await.unwrap(), characters);
+        remote.add_message("s1", &message).await.unwrap();
+        assert_eq!(remote.restore_revision("m1", "r1").await.unwrap(), message);
+        remote.delete_story("s1").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_ids_are_encoded_as_one_path_segment() {
+        let server = MockServer::start().await;
+        Mock::given(method("DELETE"))
+            .and(path("/api/characters/a%2Fb%3Fc%23d"))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("DELETE"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+
+        backend(&server, None).delete_character("a/b?c#d").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_unresolved_token_reference_is_not_sent() {
+        let server = MockServer::start().await;
+        Mock::given(header_exists("Authorization"))
+            .respond_with(ResponseTemplate::new(401))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/stories"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(Vec::<StoryItem>::new()))
+            .mount(&server)
+            .await;
+
+        let remote = backend(&server, Some("credential:server"));
+        assert!(remote.list_stories().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_error_statuses_map_to_backend_errors() {
+        let server = MockServer::start().await;
+        Mock::given(path("/api/characters/missing"))
+            .respond_with(ResponseTemplate::new(404).set_body_string("no such character"))
+            .mount(&server)
+            .await;
+        Mock::given(path("/api/stories"))
+            .respond_with(ResponseTemplate::new(500).set_body_string("database is locked"))
+            .mount(&server)
+            .await;
+
+        let remote = backend(&server, None);
+        assert!(remote.get_character("missing").await.unwrap().is_none());
+        assert!(matches!(
+            remote.delete_character("missing").await,
+            Err(BackendError::NotFound(message)) if message == "no such character"
+        ));
+        assert!(matches!(
+            remote.list_stories().await,
+            Err(BackendError::Server { status: 500, message }) if message == "database is locked"
+        ));
+    }
+}
@@ -0,0 +1,856 @@
+//! Character Card import and export
+//!
+//! Reads TavernAI and SillyTavern character cards: spec v1 (bare fields), v2
+//! (`chara_card_v2`) and v3 (`chara_card_v3`), either as JSON, embedded in a
+//! PNG as a base64 `chara` or `ccv3` tEXt chunk, or packed in a CharX zip.
+//! Card fields without a place in [`Character`], such as the lorebook
+//! (`character_book`) and extensions, are kept in [`Character::card_extras`]
+//! and written back on export.
+
+use crate::models::{Character, DefaultHistory, DefaultHistoryKind, ScenarioItem, StoryMessage, StoryRole};
+use crate::story_export::file_stem;
+use crate::SaveFile;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde_json::{json, Map, Value};
+use std::collections::HashSet;
+use std::io::{Cursor, Read, Write};
+use thiserror::Error;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+pub const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+const ZIP_SIGNATURE: [u8; 4] = [b'P', b'K', 0x03, 0x04];
+
+/// A transparent pixel, used as the image of characters without an avatar
+const PLACEHOLDER_AVATAR: &str =
+    "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mNkYPhfDwAChwGA60e6kgAAAABJRU5ErkJggg==";
+
+#[derive(Error, Debug)]
+pub enum CardError {
+    #[error("Not a PNG or JSON character card")]
+    UnknownFormat,
+    #[error("Invalid PNG: {0}")]
+    Png(String),
+    #[error("Invalid CharX archive: {0}")]
+    Archive(String),
+    #[error("The image has no character card data")]
+    NoCardData,
+    #[error("Invalid card data: {0}")]
+    Invalid(String),
+    #[error("Unsupported card spec: {0}")]
+    UnsupportedSpec(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CardSpec {
+    V1,
+    V2,
+    V3,
+}
+
+/// A character read from a card
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedCard {
+    pub spec: CardSpec,
+    pub character: Character,
+    /// The card's scenario, which Hearth keeps apart from the character
+    pub scenario: Option<ScenarioItem>,
+}
+
+/// Import a card from the bytes of a PNG, CharX or JSON file
+///
+/// A PNG card, or the main icon of a CharX card, becomes the character's avatar.
+pub fn import_card(bytes: &[u8]) -> Result<ImportedCard, CardError> {
+    if bytes.starts_with(&PNG_SIGNATURE) {
+        let mut card = parse_card_json(&card_json_from_png(bytes)?)?;
+        card.character.avatar_url = Some(data_url("png", bytes));
+        return Ok(card);
+    }
+    if bytes.starts_with(&ZIP_SIGNATURE) {
+        return import_charx(bytes);
+    }
+    let text = std::str::from_utf8(bytes).map_err(|_| CardError::UnknownFormat)?;
+    if !text.trim_start().starts_with('{') {
+        return Err(CardError::UnknownFormat);
+    }
+    parse_card_json(text)
+}
+
+/// Type and data of a PNG chunk
+type PngChunk<'a> = ([u8; 4], &'a [u8]);
+
+/// Every chunk in a PNG, up to and including `IEND`
+fn png_chunks(bytes: &[u8]) -> Result<Vec<PngChunk<'_>>, CardError> {
+    let mut chunks = Vec::new();
+    let mut rest = bytes
+        .strip_prefix(&PNG_SIGNATURE)
+        .ok_or_else(|| CardError::Png("missing signature".to_string()))?;
+    let truncated = || CardError::Png("truncated chunk".to_string());
+    while rest.len() >= 12 {
+        let length = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+        let kind = [rest[4], rest[5], rest[6], rest[7]];
+        // A crafted length can overflow usize on 32-bit targets
+        let data_end = 8usize.checked_add(length).ok_or_else(truncated)?;
+        let chunk_end = 12usize.checked_add(length).ok_or_else(truncated)?;
+        let data = rest.get(8..data_end).ok_or_else(truncated)?;
+        chunks.push((kind, data));
+        if &kind == b"IEND" {
+            break;
+        }
+        rest = rest.get(chunk_end..).unwrap_or_default();
+    }
+    Ok(chunks)
+}
+
+/// Keyword and text of every tEXt chunk in a PNG
+pub(crate) fn png_text_chunks(bytes: &[u8]) -> Result<Vec<(String, Vec<u8>)>, CardError> {
+    let chunks = png_chunks(bytes)?.into_iter().filter(|(kind, _)| kind == b"tEXt");
+    Ok(chunks
+        .filter_map(|(_, data)| {
+            let split = data.iter().position(|&b| b == 0)?;
+            // Keywords are Latin-1, but card keywords are plain ASCII
+            let keyword = String::from_utf8_lossy(&data[..split]).into_owned();
+            Some((keyword, data[split + 1..].to_vec()))
+        })
+        .collect())
+}
+
+/// Card JSON from a PNG, preferring the v3 chunk when both are present
+fn card_json_from_png(bytes: &[u8]) -> Result<String, CardError> {
+    let chunks = png_text_chunks(bytes)?;
+    let find = |keyword: &str| chunks.iter().find(|(k, _)| k.eq_ignore_ascii_case(keyword));
+    let (_, encoded) = find("ccv3").or_else(|| find("chara")).ok_or(CardError::NoCardData)?;
+    let encoded: Vec<u8> = encoded.iter().copied().filter(|b| !b.is_ascii_whitespace()).collect();
+    let json = BASE64
+        .decode(encoded)
+        .map_err(|e| CardError::Invalid(format!("card data is not base64: {e}")))?;
+    String::from_utf8(json).map_err(|e| CardError::Invalid(e.to_string()))
+}
+
+/// Card from a CharX archive, with its main icon as the avatar
+///
+/// Other bundled files stay in the card's assets as data URLs, which
+/// [`export_charx`] bundles again.
+fn import_charx(bytes: &[u8]) -> Result<ImportedCard, CardError> {
+    let mut archive = ZipArchive::new(Cursor::new(bytes)).map_err(|e| CardError::Archive(e.to_string()))?;
+    let json = String::from_utf8(read_entry(&mut archive, "card.json")?)
+        .map_err(|e| CardError::Invalid(e.to_string()))?;
+    let mut card = parse_card_json(&json)?;
+
+    let extras = &mut card.character.card_extras;
+    let mut assets = match extras.remove("assets") {
+        Some(Value::Array(assets)) => assets,
+        Some(other) => {
+            extras.insert("assets".to_string(), other);
+            return Ok(card);
+        }
+        None => return Ok(card),
+    };
+
+    let is_bundled_icon =
+        |asset: &Value| asset["type"] == "icon" && asset["uri"].as_str().and_then(bundled_path).is_some();
+    let icon = assets
+        .iter()
+        .position(|asset| is_bundled_icon(asset) && asset["name"] == "main")
+        .or_else(|| assets.iter().position(is_bundled_icon));
+    if let Some(index) = icon {
+        let icon = assets.remove(index);
+        let path = icon["uri"].as_str().and_then(bundled_path).unwrap_or_default();
+        let extension = icon["ext"].as_str().unwrap_or("png");
+        card.character.avatar_url = Some(data_url(extension, &read_entry(&mut archive, path)?));
+    }
+    for asset in &mut assets {
+        let Some(path) = asset["uri"].as_str().and_then(bundled_path).map(str::to_string) else {
+            continue;
+        };
+        let extension = asset["ext"].as_str().unwrap_or_default().to_string();
+        asset["uri"] = json!(asset_data_url(&extension, &read_entry(&mut archive, &path)?));
+    }
+    if !assets.is_empty() {
+        card.character.card_extras.insert("assets".to_string(), Value::Array(assets));
+    }
+    Ok(card)
+}
+
+fn read_entry(archive: &mut ZipArchive<Cursor<&[u8]>>, name: &str) -> Result<Vec<u8>, CardError> {
+    let mut file = archive
+        .by_name(name)
+        .map_err(|e| CardError::Archive(format!("{name}: {e}")))?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)
+        .map_err(|e| CardError::Archive(format!("{name}: {e}")))?;
+    Ok(bytes)
+}
+
+/// Path inside the archive of an asset bundled with a CharX card
+fn bundled_path(uri: &str) -> Option<&str> {
+    // The spec spells it "embeded", some writers fix the typo
+    uri.strip_prefix("embeded://").or_else(|| uri.strip_prefix("embedded://"))
+}
+
+fn data_url(extension: &str, bytes: &[u8]) -> String {
+    let subtype = if extension == "jpg" { "jpeg" } else { extension };
+    format!("data:image/{subtype};base64,{}", BASE64.encode(bytes))
+}
+
+/// Data URL for a bundled asset of any kind
+fn asset_data_url(extension: &str, bytes: &[u8]) -> String {
+    match asset_folder(extension) {
+        "images" => data_url(extension, bytes),
+        "audio" => format!("data:audio/{extension};base64,{}", BASE64.encode(bytes)),
+        _ => format!("data:application/octet-stream;base64,{}", BASE64.encode(bytes)),
+    }
+}
+
+/// Bytes of a base64 data URL
+fn data_url_bytes(url: &str) -> Option<Vec<u8>> {
+    let (header, data) = url.strip_prefix("data:")?.split_once(',')?;
+    header.ends_with(";base64").then(|| BASE64.decode(data).ok())?
+}
+
+/// Folder the CharX spec suggests for an asset with this extension
+fn asset_folder(extension: &str) -> &'static str {
+    match extension.to_ascii_lowercase().as_str() {
+        "png" | "jpg" | "jpeg" | "webp" | "gif" | "avif" => "images",
+        "mp3" | "wav" | "ogg" | "flac" => "audio",
+        _ => "other",
+    }
+}
+
+/// Ask the user for a card file, `None` if the dialog was cancelled
+#[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+pub async fn pick_card_file() -> Option<Vec<u8>> {
+    let file = rfd::AsyncFileDialog::new()
+        .set_title("Import Character Card")
+        .add_filter("Character Cards", &["png", "charx", "json"])
+        .add_filter("All Files", &["*"])
+        .pick_file()
+        .await?;
+    Some(file.read().await)
+}
+
+/// Import a card from its JSON
+pub fn parse_card_json(json: &str) -> Result<ImportedCard, CardError> {
+    let Value::Object(mut root) = serde_json::from_str(json).map_err(|e| CardError::Invalid(e.to_string()))? else {
+        return Err(CardError::Invalid("expected a JSON object".to_string()));
+    };
+    let (spec, mut data) = match root.get("spec").and_then(Value::as_str) {
+        Some("chara_card_v3") => (CardSpec::V3, take_object(&mut root, "data")?),
+        Some("chara_card_v2") => (CardSpec::V2, take_object(&mut root, "data")?),
+        Some(other) => return Err(CardError::UnsupportedSpec(other.to_string())),
+        None => (CardSpec::V1, root),
+    };
+
+    let name = take_string(&mut data, "name").trim().to_string();
+    if name.is_empty() {
+        return Err(CardError::Invalid("the card has no name".to_string()));
+    }
+    // Only v1 cards use the old placeholders, later text is kept as written
+    let text = |data: &mut Map<String, Value>, key: &str| match spec {
+        CardSpec::V1 => placeholders(&take_string(data, key)),
+        CardSpec::V2 | CardSpec::V3 => take_string(data, key),
+    };
+    let mut character = Character::new(uuid::Uuid::new_v4().to_string(), name.clone(), text(&mut data, "description"));
+    character.personality = text(&mut data, "personality");
+    character.example_dialogue = text(&mut data, "mes_example");
+    character.tags = clean_tags(take_strings(&mut data, "tags"));
+
+    let first_message = text(&mut data, "first_mes");
+    let mut alternates = take_strings(&mut data, "alternate_greetings");
+    if spec == CardSpec::V1 {
+        alternates = alternates.iter().map(|greeting| placeholders(greeting)).collect();
+    }
+    let greetings = std::iter::once(first_message).chain(alternates);
+    character.default_histories = greetings
+        .filter(|greeting| !greeting.trim().is_empty())
+        .enumerate()
+        .map(|(index, greeting)| greeting_history(index, &name, &greeting))
+        .collect();
+
+    let scenario = Some(text(&mut data, "scenario"))
+        .filter(|scenario| !scenario.trim().is_empty())
+        .map(|scenario| scenario_item(&name, &scenario));
+    character.default_scenario_id = scenario.as_ref().map(|s| s.id.clone());
+
+    // Everything else, including the lorebook and extensions, is kept as it
+    // was; empty spec fields are written back on export anyway
+    let defaults = card_defaults();
+    data.retain(|key, value| defaults.get(key) != Some(value));
+    character.card_extras = data;
+    Ok(ImportedCard {
+        spec,
+        character,
+        scenario,
+    })
+}
+
+fn scenario_item(name: &str, description: &str) -> ScenarioItem {
+    ScenarioItem {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: format!("{name}'s Scenario"),
+        description: description.to_string(),
+        avatar_url: None,
+        tags: Vec::new(),
+        is_favorite: false,
+        story_count: 0,
+        last_used: None,
+    }
+}
+
+fn greeting_history(index: usize, name: &str, greeting: &str) -> DefaultHistory {
+    DefaultHistory {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: match index {
+            0 => "Greeting".to_string(),
+            n => format!("Alternate greeting {n}"),
+        },
+        kind: DefaultHistoryKind::ContextualOpening,
+        messages: vec![StoryMessage::new(
+            uuid::Uuid::new_v4().to_string(),
+            StoryRole::Character { name: name.to_string() },
+            greeting.to_string(),
+        )],
+    }
+}
+
+fn take_object(map: &mut Map<String, Value>, key: &str) -> Result<Map<String, Value>, CardError> {
+    match map.remove(key) {
+        Some(Value::Object(object)) => Ok(object),
+        _ => Err(CardError::Invalid(format!("'{key}' is missing or not an object"))),
+    }
+}
+
+/// Remove a string field; values of any other type stay in the map
+fn take_string(map: &mut Map<String, Value>, key: &str) -> String {
+    if !map.get(key).is_some_and(Value::is_string) {
+        return String::new();
+    }
+    map.remove(key).and_then(|v| v.as_str().map(str::to_string)).unwrap_or_default()
+}
+
+/// Remove a list of strings; lists with other values stay in the map
+fn take_strings(map: &mut Map<String, Value>, key: &str) -> Vec<String> {
+    let all_strings = matches!(map.get(key), Some(Value::Array(items)) if items.iter().all(Value::is_string));
+    if !all_strings {
+        return Vec::new();
+    }
+    let items = map.remove(key).and_then(|v| v.as_array().cloned()).unwrap_or_default();
+    items.iter().filter_map(|v| v.as_str().map(str::to_string)).collect()
+}
+
+/// Trimmed tags without blanks or case-insensitive duplicates
+fn clean_tags(tags: Vec<String>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    tags.into_iter()
+        .map(|tag| tag.trim().to_string())
+        .filter(|tag| !tag.is_empty() && seen.insert(tag.to_lowercase()))
+        .collect()
+}
+
+/// Old cards use `<USER>` and `<BOT>` where newer ones use `{{user}}` and `{{char}}`
+fn placeholders(text: &str) -> String {
+    text.replace("<USER>", "{{user}}").replace("<BOT>", "{{char}}")
+}
+
+/// Fields every v3 card has, left out of [`Character::card_extras`] while empty
+fn card_defaults() -> Map<String, Value> {
+    let text = ["creator_notes", "system_prompt", "post_history_instructions", "creator", "character_version"];
+    text.into_iter()
+        .map(|key| (key.to_string(), json!("")))
+        .chain([
+            ("extensions".to_string(), json!({})),
+            ("group_only_greetings".to_string(), json!([])),
+        ])
+        .collect()
+}
+
+/// A v3 card for a character, with the scenario it starts in if any
+pub fn card_json(character: &Character, scenario: Option<&ScenarioItem>) -> Value {
+    json!({
+        "spec": "chara_card_v3",
+        "spec_version": "3.0",
+        "data": card_data(character, scenario),
+    })
+}
+
+fn card_data(character: &Character, scenario: Option<&ScenarioItem>) -> Map<String, Value> {
+    let mut data = card_defaults();
+    data.extend(character.card_extras.clone());
+    let mut greetings = character.default_histories.iter().filter_map(greeting_text);
+    let fields = [
+        ("name", json!(character.name)),
+        ("description", json!(character.description)),
+        ("personality", json!(character.personality)),
+        ("scenario", json!(scenario.map(|s| s.description.as_str()).unwrap_or_default())),
+        ("first_mes", json!(greetings.next().unwrap_or_default())),
+        ("mes_example", json!(character.example_dialogue)),
+        ("alternate_greetings", json!(greetings.collect::<Vec<_>>())),
+        ("tags", json!(character.tags)),
+    ];
+    data.extend(fields.map(|(key, value)| (key.to_string(), value)));
+    data
+}
+
+/// What the character says first in a default history
+fn greeting_text(history: &DefaultHistory) -> Option<&str> {
+    history
+        .messages
+        .iter()
+        .find(|message| matches!(message.role, StoryRole::Character { .. }))
+        .map(|message| message.content.as_str())
+}
+
+/// Image bytes of an avatar stored as a base64 data URL
+pub fn embedded_avatar(character: &Character) -> Option<Vec<u8>> {
+    let url = character.avatar_url.as_deref()?;
+    url.starts_with("data:image/").then(|| data_url_bytes(url))?
+}
+
+/// Embed a v3 card in a PNG avatar, with a v2 copy for older apps
+///
+/// Card data already in the image is replaced. Without a PNG avatar the card
+/// is written into a transparent pixel.
+pub fn export_png(
+    character: &Character,
+    scenario: Option<&ScenarioItem>,
+    avatar: Option<&[u8]>,
+) -> Result<Vec<u8>, CardError> {
+    let avatar = match avatar {
+        Some(avatar) if avatar.starts_with(&PNG_SIGNATURE) => avatar.to_vec(),
+        other => {
+            if other.is_some() {
+                log::warn!("The avatar of '{}' is not a PNG, exporting without it", character.name);
+            }
+            BASE64.decode(PLACEHOLDER_AVATAR).unwrap_or_default()
+        }
+    };
+    let v3 = card_json(character, scenario);
+    let mut v2 = v3.clone();
+    v2["spec"] = json!("chara_card_v2");
+    v2["spec_version"] = json!("2.0");
+
+    let mut png = PNG_SIGNATURE.to_vec();
+    let mut complete = false;
+    for (kind, data) in png_chunks(&avatar)? {
+        if &kind == b"tEXt" && is_card_chunk(data) {
+            continue;
+        }
+        if &kind == b"IEND" {
+            write_chunk(&mut png, b"tEXt", &text_chunk("chara", &v2));
+            write_chunk(&mut png, b"tEXt", &text_chunk("ccv3", &v3));
+            complete = true;
+        }
+        write_chunk(&mut png, &kind, data);
+    }
+    if !complete {
+        return Err(CardError::Png("missing IEND chunk".to_string()));
+    }
+    Ok(png)
+}
+
+fn is_card_chunk(data: &[u8]) -> bool {
+    let keyword = data.split(|&b| b == 0).next().unwrap_or_default();
+    keyword.eq_ignore_ascii_case(b"chara") || keyword.eq_ignore_ascii_case(b"ccv3")
+}
+
+fn text_chunk(keyword: &str, card: &Value) -> Vec<u8> {
+    [keyword.as_bytes(), &[0], BASE64.encode(card.to_string()).as_bytes()].concat()
+}
+
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8], data: &[u8]) {
+    let mut crc = crc32fast::Hasher::new();
+    crc.update(kind);
+    crc.update(data);
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    png.extend_from_slice(&crc.finalize().to_be_bytes());
+}
+
+/// File types a card can be exported as
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CardFormat {
+    Png,
+    CharX,
+}
+
+impl CardFormat {
+    pub const ALL: [CardFormat; 2] = [Self::Png, Self::CharX];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Png => "PNG Card",
+            Self::CharX => "CharX Archive",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::CharX => "charx",
+        }
+    }
+}
+
+/// A character's card as a file for [`crate::save_with_dialog`]
+///
+/// An avatar stored as a data URL goes into the card; linked avatars are left out.
+pub fn card_file(
+    character: &Character,
+    scenario: Option<&ScenarioItem>,
+    format: CardFormat,
+) -> Result<SaveFile, CardError> {
+    let avatar = embedded_avatar(character);
+    let (content, mime_type) = match format {
+        CardFormat::Png => (export_png(character, scenario, avatar.as_deref())?, "image/png"),
+        CardFormat::CharX => (export_charx(character, scenario, avatar.as_deref())?, "application/zip"),
+    };
+    Ok(SaveFile {
+        title: "Export Character Card".to_string(),
+        file_name: format!("{}.{}", file_stem(&character.name, "character"), format.extension()),
+        kind: format!("{} Files", format.label()),
+        extension: format.extension().to_string(),
+        mime_type: mime_type.to_string(),
+        content,
+    })
+}
+
+/// Pack a v3 card, its avatar and its other assets into a CharX archive
+///
+/// Assets kept as data URLs are bundled as files again.
+pub fn export_charx(
+    character: &Character,
+    scenario: Option<&ScenarioItem>,
+    avatar: Option<&[u8]>,
+) -> Result<Vec<u8>, CardError> {
+    let mut data = card_data(character, scenario);
+    let mut assets = match data.remove("assets") {
+        Some(Value::Array(assets)) => assets,
+        _ => Vec::new(),
+    };
+    let mut files = Vec::new();
+    for (index, asset) in assets.iter_mut().enumerate() {
+        let Some(bytes) = asset["uri"].as_str().and_then(data_url_bytes) else {
+            continue;
+        };
+        let extension = path_segment(asset["ext"].as_str(), "bin");
+        let kind = path_segment(asset["type"].as_str(), "other");
+        let path = format!("assets/{kind}/{}/{index}.{extension}", asset_folder(&extension));
+        asset["uri"] = json!(format!("embeded://{path}"));
+        files.push((path, bytes));
+    }
+    if let Some(avatar) = avatar {
+        let extension = image_extension(avatar);
+        let path = format!("assets/icon/images/main.{extension}");
+        assets.retain(|asset| !(asset["type"] == "icon" && asset["name"] == "main"));
+        assets.insert(
+            0,
+            json!({"type": "icon", "uri": format!("embeded://{path}"), "name": "main", "ext": extension}),
+        );
+        files.push((path, avatar.to_vec()));
+    }
+    if !assets.is_empty() {
+        data.insert("assets".to_string(), Value::Array(assets));
+    }
+    let card = json!({"spec": "chara_card_v3", "spec_version": "3.0", "data": data});
+
+    let archive_error = |e: zip::result::ZipError| CardError::Archive(e.to_string());
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    zip.start_file("card.json", options).map_err(archive_error)?;
+    zip.write_all(card.to_string().as_bytes())
+        .map_err(|e| CardError::Archive(e.to_string()))?;
+    for (path, bytes) in files {
+        zip.start_file(path, options).map_err(archive_error)?;
+        zip.write_all(&bytes).map_err(|e| CardError::Archive(e.to_string()))?;
+    }
+    Ok(zip.finish().map_err(archive_error)?.into_inner())
+}
+
+/// Asset field usable in an archive path, `fallback` when it has nothing usable
+fn path_segment(value: Option<&str>, fallback: &str) -> String {
+    let segment: String = value
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'))
+        .collect();
+    if segment.is_empty() {
+        fallback.to_string()
+    } else {
+        segment
+    }
+}
+
+/// File extension for an image, going by its first bytes
+fn image_extension(bytes: &[u8]) -> &'static str {
+    if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        "jpg"
+    } else if bytes.starts_with(b"GIF8") {
+        "gif"
+    } else if bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WEBP") {
+        "webp"
+    } else {
+        "png"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn png_with_text(chunks: &[(&str, String)]) -> Vec<u8> {
+        let mut png = PNG_SIGNATURE.to_vec();
+        write_chunk(&mut png, b"IHDR", &[0, 0, 0, 1, 0, 0, 0, 1, 8, 6, 0, 0, 0]);
+        for (keyword, text) in chunks {
+            write_chunk(&mut png, b"tEXt", &[keyword.as_bytes(), &[0], text.as_bytes()].concat());
+        }
+        write_chunk(&mut png, b"IEND", &[]);
+        png
+    }
+
+    fn v2_card() -> Value {
+        json!({
+            "spec": "chara_card_v2",
+            "spec_version": "2.0",
+            "data": {
+                "name": "Seraphina",
+                "description": "{{char}} is a guardian of the forest.",
+                "personality": "Kind, protective",
+                "scenario": "{{user}} wakes up in {{char}}'s glade.",
+                "first_mes": "*She smiles.* You're awake.",
+                "mes_example": "<START>\n{{user}}: Who are you?\n{{char}}: A friend.",
+                "alternate_greetings": ["*She hums a tune.*"],
+                "tags": ["Fantasy", "fantasy", " Guardian "],
+                "creator": "someone",
+                "system_prompt": "",
+                "character_book": {"entries": [{"keys": ["glade"], "content": "A hidden clearing."}]},
+                "extensions": {"depth_prompt": {"depth": 4, "prompt": "Stay gentle."}, "fav": true}
+            }
+        })
+    }
+
+    #[test]
+    fn test_v2_card_maps_fields_and_keeps_extras() {
+        let card = parse_card_json(&v2_card().to_string()).unwrap();
+        assert_eq!(card.spec, CardSpec::V2);
+
+        let character = &card.character;
+        assert_eq!(character.name, "Seraphina");
+        assert_eq!(character.personality, "Kind, protective");
+        assert_eq!(character.tags, vec!["Fantasy".to_string(), "Guardian".to_string()]);
+        assert_eq!(character.default_histories.len(), 2);
+        assert_eq!(character.default_histories[1].messages[0].content, "*She hums a tune.*");
+        assert!(character.validate().is_ok());
+
+        let scenario = card.scenario.unwrap();
+        assert_eq!(character.default_scenario_id.as_ref(), Some(&scenario.id));
+        assert_eq!(scenario.description, "{{user}} wakes up in {{char}}'s glade.");
+
+        assert_eq!(character.card_extras["creator"], "someone");
+        assert_eq!(character.card_extras["character_book"]["entries"][0]["keys"][0], "glade");
+        assert_eq!(character.card_extras["extensions"]["depth_prompt"]["depth"], 4);
+        assert!(!character.card_extras.contains_key("first_mes"));
+    }
+
+    #[test]
+    fn test_png_card_prefers_v3_chunk_and_becomes_avatar() {
+        let mut v3 = v2_card();
+        v3["spec"] = json!("chara_card_v3");
+        v3["data"]["name"] = json!("Seraphina V3");
+        let png = png_with_text(&[
+            ("chara", BASE64.encode(v2_card().to_string())),
+            ("ccv3", BASE64.encode(v3.to_string())),
+        ]);
+
+        let card = import_card(&png).unwrap();
+        assert_eq!(card.spec, CardSpec::V3);
+        assert_eq!(card.character.name, "Seraphina V3");
+        assert!(card.character.avatar_url.unwrap().starts_with("data:image/png;base64,iVBORw0KGgo"));
+
+        assert!(matches!(import_card(&png_with_text(&[])), Err(CardError::NoCardData)));
+    }
+
+    #[test]
+    fn test_oversized_chunk_length_is_truncated() {
+        let mut png = PNG_SIGNATURE.to_vec();
+        png.extend_from_slice(&0xFFFF_FFFFu32.to_be_bytes());
+        png.extend_from_slice(b"tEXt");
+        png.extend_from_slice(b"chara\0data");
+        assert!(matches!(import_card(&png), Err(CardError::Png(message)) if message == "truncated chunk"));
+    }
+
+    #[test]
+    fn test_v1_card_uses_old_placeholders() {
+        let v1 = json!({
+            "name": "Bob",
+            "description": "<BOT> helps <USER>.",
+            "first_mes": "",
+            "mes_example": "",
+            "scenario": "",
+            "personality": "",
+            "avatar": "none"
+        });
+        let card = import_card(v1.to_string().as_bytes()).unwrap();
+        assert_eq!(card.spec, CardSpec::V1);
+        assert_eq!(card.character.description, "{{char}} helps {{user}}.");
+        assert!(card.character.default_histories.is_empty());
+        assert!(card.scenario.is_none());
+        assert_eq!(card.character.card_extras["avatar"], "none");
+
+        assert!(matches!(import_card(b"GIF89a"), Err(CardError::UnknownFormat)));
+    }
+
+    fn extra_value() -> impl Strategy<Value = Value> {
+        prop_oneof![
+            any::<bool>().prop_map(Value::from),
+            any::<i64>().prop_map(Value::from),
+            "(.|<USER>){0,12}".prop_map(Value::from),
+            prop::collection::vec("[a-z]{0,6}", 0..3).prop_map(|items| json!({ "entries": items })),
+        ]
+    }
+
+    /// Characters as a card can describe them, with an optional scenario
+    fn card_character() -> impl Strategy<Value = (Character, Option<ScenarioItem>)> {
+        let text = "((?s:.)|<USER>|<BOT>){0,40}";
+        let greeting = "((?s:.)|<USER>|<BOT>){0,20}[a-z]((?s:.)|<USER>|<BOT>){0,20}";
+        (
+            "[A-Za-z][A-Za-z ]{0,14}[A-Za-z]",
+            (text, text, text),
+            prop::collection::btree_set("[a-z]{1,8}", 0..5),
+            prop::collection::vec(greeting, 0..4),
+            prop::option::of(greeting),
+            prop::collection::btree_map("x_[a-z]{1,6}", extra_value(), 0..4),
+        )
+            .prop_map(|(name, (description, personality, example), tags, greetings, scenario, extras)| {
+                let mut character = Character::new("original", name.clone(), description);
+                character.personality = personality;
+                character.example_dialogue = example;
+                character.tags = tags.into_iter().collect();
+                character.default_histories = greetings
+                    .iter()
+                    .enumerate()
+                    .map(|(index, greeting)| greeting_history(index, &name, greeting))
+                    .collect();
+                character.card_extras = extras.into_iter().collect();
+                let scenario = scenario.map(|description| scenario_item(&name, &description));
+                (character, scenario)
+            })
+    }
+
+    fn assert_same_card(character: &Character, scenario: Option<&ScenarioItem>, card: &ImportedCard) {
+        let imported = &card.character;
+        assert_eq!(card.spec, CardSpec::V3);
+        assert_eq!(imported.name, character.name);
+        assert_eq!(imported.description, character.description);
+        assert_eq!(imported.personality, character.personality);
+        assert_eq!(imported.example_dialogue, character.example_dialogue);
+        assert_eq!(imported.tags, character.tags);
+        assert_eq!(imported.card_extras, character.card_extras);
+        let greetings = |c: &Character| {
+            let texts = c.default_histories.iter().filter_map(greeting_text);
+            texts.map(str::to_string).collect::<Vec<_>>()
+        };
+        assert_eq!(greetings(imported), greetings(character));
+        assert_eq!(
+            card.scenario.as_ref().map(|s| &s.description),
+            scenario.map(|s| &s.description)
+        );
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn test_png_export_round_trips((character, scenario) in card_character()) {
+            let png = export_png(&character, scenario.as_ref(), None).unwrap();
+            let card = import_card(&png).unwrap();
+            assert_same_card(&character, scenario.as_ref(), &card);
+
+            // Exporting again replaces the card instead of adding another one
+            let avatar = embedded_avatar(&card.character).unwrap();
+            let again = export_png(&card.character, card.scenario.as_ref(), Some(&avatar)).unwrap();
+            prop_assert_eq!(png_text_chunks(&again).unwrap().len(), 2);
+            assert_same_card(&character, scenario.as_ref(), &import_card(&again).unwrap());
+        }
+
+        #[test]
+        fn test_charx_export_round_trips((character, scenario) in card_character()) {
+            let avatar = BASE64.decode(PLACEHOLDER_AVATAR).unwrap();
+            let charx = export_charx(&character, scenario.as_ref(), Some(&avatar)).unwrap();
+            let card = import_card(&charx).unwrap();
+            assert_same_card(&character, scenario.as_ref(), &card);
+            prop_assert_eq!(embedded_avatar(&card.character), Some(avatar));
+        }
+    }
+
+    #[test]
+    fn test_v2_card_keeps_angle_bracket_text() {
+        let mut card = v2_card();
+        card["data"]["description"] = json!("<BOT> greets <USER>.");
+        let card = parse_card_json(&card.to_string()).unwrap();
+        assert_eq!(card.character.description, "<BOT> greets <USER>.");
+    }
+
+    #[test]
+    fn test_charx_keeps_bundled_assets() {
+        let mut card = v2_card();
+        card["spec"] = json!("chara_card_v3");
+        card["data"]["assets"] = json!([
+            {"type": "icon", "uri": "embeded://assets/icon/images/main.png", "name": "main", "ext": "png"},
+            {"type": "emotion", "uri": "embeded://assets/emotion/images/joy.webp", "name": "joy", "ext": "webp"},
+            {"type": "background", "uri": "https://example.com/glade.png", "name": "glade", "ext": "png"}
+        ]);
+        let icon = BASE64.decode(PLACEHOLDER_AVATAR).unwrap();
+        let joy = b"RIFF\0\0\0\0WEBPjoy".to_vec();
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        for (path, bytes) in [
+            ("card.json", card.to_string().into_bytes()),
+            ("assets/icon/images/main.png", icon.clone()),
+            ("assets/emotion/images/joy.webp", joy.clone()),
+        ] {
+            zip.start_file(path, SimpleFileOptions::default()).unwrap();
+            zip.write_all(&bytes).unwrap();
+        }
+        let charx = zip.finish().unwrap().into_inner();
+
+        let card = import_card(&charx).unwrap();
+        assert_eq!(embedded_avatar(&card.character), Some(icon.clone()));
+        let assets = card.character.card_extras["assets"].as_array().unwrap();
+        assert_eq!(assets.len(), 2);
+        assert_eq!(data_url_bytes(assets[0]["uri"].as_str().unwrap()), Some(joy.clone()));
+        assert_eq!(assets[1]["uri"], "https://example.com/glade.png");
+
+        let again = export_charx(&card.character, None, Some(&icon)).unwrap();
+        let reimported = import_card(&again).unwrap();
+        assert_eq!(reimported.character.card_extras["assets"], card.character.card_extras["assets"]);
+        assert_eq!(embedded_avatar(&reimported.character), Some(icon));
+    }
+
+    #[test]
+    fn test_png_export_replaces_other_image_formats() {
+        let character = parse_card_json(&v2_card().to_string()).unwrap().character;
+        let png = export_png(&character, None, Some(&[0xFF, 0xD8, 0xFF, 0xE0])).unwrap();
+        assert_eq!(import_card(&png).unwrap().character.name, "Seraphina");
+    }
+
+    #[test]
+    fn test_png_export_writes_valid_chunks() {
+        let character = parse_card_json(&v2_card().to_string()).unwrap().character;
+        let png = export_png(&character, None, None).unwrap();
+        let mut rest = &png[PNG_SIGNATURE.len()..];
+        while !rest.is_empty() {
+            let length = u32::from_be_bytes(rest[..4].try_into().unwrap()) as usize;
+            let crc = u32::from_be_bytes(rest[8 + length..12 + length].try_into().unwrap());
+            assert_eq!(crc32fast::hash(&rest[4..8 + length]), crc);
+            rest = &rest[12 + length..];
+        }
+
+        let chara = BASE64.decode(&png_text_chunks(&png).unwrap()[0].1).unwrap();
+        let card: Value = serde_json::from_slice(&chara).unwrap();
+        assert_eq!(card["spec"], "chara_card_v2");
+        assert_eq!(card["data"]["system_prompt"], "");
+        assert_eq!(card["data"]["character_book"]["entries"][0]["content"], "A hidden clearing.");
+    }
+}
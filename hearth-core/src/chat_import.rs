@@ -0,0 +1,351 @@
+//! Chat history import
+//!
+//! Builds a story from a SillyTavern `.jsonl` chat or a TavernAI chat. Both
+//! write one JSON object per message, after an optional header naming the user
+//! and the character; TavernAI chats may also be a single JSON array. Swipes
+//! become message alternates, keeping the one that was selected.
+
+use crate::models::{
+    Character, CharacterItem, GenerationMetadata, MessageAlternate, StoryItem, StoryMessage, StoryParticipant,
+    StoryRole,
+};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use serde::Deserialize;
+use serde_json::Value;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ChatImportError {
+    #[error("The chat has no messages")]
+    Empty,
+    #[error("Invalid chat entry {entry}: {message}")]
+    Invalid { entry: usize, message: String },
+}
+
+/// A story read from a chat file, with its messages in order
+#[derive(Clone)]
+pub struct ImportedChat {
+    pub story: StoryItem,
+    pub messages: Vec<StoryMessage>,
+}
+
+impl ImportedChat {
+    /// Point the story's participants at library characters with the same name
+    pub fn link_characters(&mut self, characters: &[CharacterItem]) {
+        for participant in &mut self.story.characters {
+            if let Some(character) = characters.iter().find(|c| c.name.eq_ignore_ascii_case(&participant.name)) {
+                participant.id = character.id.clone();
+                participant.avatar_url = character.avatar_url.clone();
+            }
+        }
+    }
+
+    /// Library characters for the speakers `link_characters` found no match for
+    ///
+    /// The story's participants are pointed at the new characters, which must be
+    /// saved before the story so that views loading them by id can find them.
+    pub fn unlinked_characters(&mut self) -> Vec<Character> {
+        self.story
+            .characters
+            .iter_mut()
+            .filter(|participant| participant.id.is_empty())
+            .map(|participant| {
+                let character = Character::new(uuid::Uuid::new_v4().to_string(), participant.name.clone(), "");
+                participant.id = character.id.clone();
+                character
+            })
+            .collect()
+    }
+}
+
+/// One line of a chat file, either the header or a message
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ChatEntry {
+    user_name: Option<String>,
+    character_name: Option<String>,
+    name: Option<String>,
+    is_user: bool,
+    is_system: bool,
+    send_date: Option<Value>,
+    mes: Option<String>,
+    swipes: Vec<Option<String>>,
+    swipe_id: Option<usize>,
+    swipe_info: Vec<SwipeInfo>,
+    extra: ChatExtra,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct SwipeInfo {
+    send_date: Option<Value>,
+    gen_started: Option<Value>,
+    gen_finished: Option<Value>,
+    extra: ChatExtra,
+}
+
+/// Where SillyTavern records how a reply was generated
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ChatExtra {
+    api: Option<String>,
+    model: Option<String>,
+}
+
+/// Import a SillyTavern or TavernAI chat
+pub fn import_chat(text: &str) -> Result<ImportedChat, ChatImportError> {
+    let entries = parse_entries(text)?;
+    let (header, entries): (Vec<_>, Vec<_>) = entries.into_iter().partition(|entry| entry.mes.is_none());
+    if entries.is_empty() {
+        return Err(ChatImportError::Empty);
+    }
+
+    let header_name = |field: fn(&ChatEntry) -> Option<&String>| header.iter().find_map(field).cloned();
+    let first_speaker = |is_user: bool| {
+        entries
+            .iter()
+            .filter(|e| e.is_user == is_user && !e.is_system)
+            .find_map(|e| e.name.clone())
+    };
+    let user_name = header_name(|h| h.user_name.as_ref())
+        .or_else(|| first_speaker(true))
+        .unwrap_or_else(|| "You".to_string());
+    let character_name = header_name(|h| h.character_name.as_ref())
+        .or_else(|| first_speaker(false))
+        .unwrap_or_else(|| "Character".to_string());
+
+    let messages: Vec<StoryMessage> = entries
+        .into_iter()
+        .map(|entry| chat_message(entry, &user_name, &character_name))
+        .collect();
+
+    let mut characters: Vec<StoryParticipant> = Vec::new();
+    for message in &messages {
+        if let StoryRole::Character { name } = &message.role {
+            if !characters.iter().any(|c| &c.name == name) {
+                characters.push(participant(name));
+            }
+        }
+    }
+    let last = messages.last().ok_or(ChatImportError::Empty)?;
+    let story = StoryItem {
+        id: uuid::Uuid::new_v4().to_string(),
+        title: format!("Chat with {character_name}"),
+        characters,
+        user_character: Some(participant(&user_name)),
+        last_message: last.content.clone(),
+        last_speaker: speaker_name(&last.role).to_string(),
+        timestamp: last
+            .created_at
+            .unwrap_or_else(Utc::now)
+            .format("%Y-%m-%d %H:%M")
+            .to_string(),
+        scenario_name: None,
+        message_count: messages.len() as u32,
+        sampler: None,
+    };
+    Ok(ImportedChat { story, messages })
+}
+
+/// Ask the user for a chat file, `None` if the dialog was cancelled
+#[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+pub async fn pick_chat_file() -> Option<String> {
+    let file = rfd::AsyncFileDialog::new()
+        .set_title("Import Chat")
+        .add_filter("Chats", &["jsonl", "json"])
+        .add_filter("All Files", &["*"])
+        .pick_file()
+        .await?;
+    String::from_utf8(file.read().await).ok()
+}
+
+/// Entries of a JSON Lines file, or of a JSON array
+fn parse_entries(text: &str) -> Result<Vec<ChatEntry>, ChatImportError> {
+    let invalid = |entry: usize, error: serde_json::Error| ChatImportError::Invalid {
+        entry,
+        message: error.to_string(),
+    };
+    if text.trim_start().starts_with('[') {
+        let values: Vec<Value> = serde_json::from_str(text).map_err(|e| invalid(1, e))?;
+        return values
+            .into_iter()
+            .enumerate()
+            .map(|(index, value)| serde_json::from_value(value).map_err(|e| invalid(index + 1, e)))
+            .collect();
+    }
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| serde_json::from_str(line).map_err(|e| invalid(index + 1, e)))
+        .collect()
+}
+
+fn chat_message(entry: ChatEntry, user_name: &str, character_name: &str) -> StoryMessage {
+    let role = if entry.is_system {
+        StoryRole::Narrator
+    } else if entry.is_user {
+        StoryRole::User {
+            name: entry.name.unwrap_or_else(|| user_name.to_string()),
+        }
+    } else {
+        StoryRole::Character {
+            name: entry.name.unwrap_or_else(|| character_name.to_string()),
+        }
+    };
+    let content = entry.mes.unwrap_or_default();
+    let mut message = StoryMessage::new(uuid::Uuid::new_v4().to_string(), role, content);
+    message.created_at = entry.send_date.as_ref().and_then(parse_date);
+
+    let mut swipe_info = entry.swipe_info.into_iter();
+    let swipes: Vec<MessageAlternate> = entry
+        .swipes
+        .into_iter()
+        .map(|swipe| MessageAlternate {
+            content: swipe.unwrap_or_default(),
+            metadata: swipe_info.next().map(swipe_metadata).unwrap_or_default(),
+        })
+        .collect();
+    if message.is_generated() && !swipes.is_empty() {
+        let selected = entry.swipe_id.unwrap_or_default().min(swipes.len() - 1);
+        message.alternates = swipes;
+        // The shown text wins over the swipe it came from, it may have been edited
+        message.alternates[selected].content = message.content.clone();
+        message.selected_alternate = selected;
+    }
+    message
+}
+
+fn swipe_metadata(info: SwipeInfo) -> GenerationMetadata {
+    let started = info.gen_started.as_ref().and_then(parse_date);
+    let finished = info.gen_finished.as_ref().and_then(parse_date);
+    GenerationMetadata {
+        provider: info.extra.api,
+        model: info.extra.model,
+        created_at: info.send_date.as_ref().and_then(parse_date).or(finished),
+        duration_ms: started
+            .zip(finished)
+            .and_then(|(started, finished)| (finished - started).num_milliseconds().try_into().ok()),
+        ..Default::default()
+    }
+}
+
+/// A speaker from the chat, without an id until it is linked to a library character
+fn participant(name: &str) -> StoryParticipant {
+    StoryParticipant {
+        id: String::new(),
+        name: name.to_string(),
+        avatar_url: None,
+    }
+}
+
+fn speaker_name(role: &StoryRole) -> &str {
+    match role {
+        StoryRole::User { name } | StoryRole::Character { name } => name,
+        StoryRole::Narrator => "Narrator",
+    }
+}
+
+/// Read the dates the chat formats have used over the years
+///
+/// Unix milliseconds, RFC 3339, `2023-5-2 @15h 30m 12s 345ms` and
+/// `May 2, 2023 3:30pm`. Dates without a time zone are taken as UTC.
+fn parse_date(value: &Value) -> Option<DateTime<Utc>> {
+    let text = match value {
+        Value::Number(millis) => return Utc.timestamp_millis_opt(millis.as_i64()?).single(),
+        Value::String(text) => text.trim(),
+        _ => return None,
+    };
+    if let Ok(date) = DateTime::parse_from_rfc3339(text) {
+        return Some(date.with_timezone(&Utc));
+    }
+    if let Some((date, time)) = text.split_once('@') {
+        let date = NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d").ok()?;
+        let parts: Vec<u32> = time
+            .split(|c: char| !c.is_ascii_digit())
+            .filter(|part| !part.is_empty())
+            .map(str::parse)
+            .collect::<Result<_, _>>()
+            .ok()?;
+        let time = match parts[..] {
+            [hour, minute, second, milli] => NaiveTime::from_hms_milli_opt(hour, minute, second, milli)?,
+            [hour, minute, second] => NaiveTime::from_hms_opt(hour, minute, second)?,
+            _ => return None,
+        };
+        return Some(date.and_time(time).and_utc());
+    }
+    let date = NaiveDateTime::parse_from_str(&text.to_lowercase(), "%B %d, %Y %I:%M%p").ok()?;
+    Some(date.and_utc())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SILLY_TAVERN: &str = r#"{"user_name":"Theron","character_name":"Seraphina","create_date":"2023-5-2 @15h 30m 12s 345ms","chat_metadata":{}}
+{"name":"Seraphina","is_user":false,"is_system":false,"send_date":"May 2, 2023 3:30pm","mes":"*She smiles.* You're awake."}
+{"name":"Theron","is_user":true,"is_system":false,"send_date":"2023-05-02T15:31:00.000Z","mes":"Where am I?"}
+{"name":"Seraphina","is_user":false,"send_date":"2023-5-2 @15h 31m 20s 5ms","mes":"In my glade.","swipe_id":1,"swipes":["In the forest.","In my glade.","Safe."],"swipe_info":[{"send_date":"2023-5-2 @15h 31m 10s 0ms","gen_started":"2023-05-02T15:31:05.000Z","gen_finished":"2023-05-02T15:31:10.500Z","extra":{"api":"openai","model":"gpt-4"}},{},{}]}
+
+{"name":"System","is_user":false,"is_system":true,"send_date":1683041500000,"mes":"Theron rests."}
+"#;
+
+    #[test]
+    fn test_imports_silly_tavern_chat() {
+        let mut chat = import_chat(SILLY_TAVERN).unwrap();
+        assert_eq!(chat.story.title, "Chat with Seraphina");
+        assert_eq!(chat.story.characters.len(), 1);
+        assert_eq!(chat.story.user_character.as_ref().unwrap().name, "Theron");
+        assert!(chat.story.user_character.as_ref().unwrap().id.is_empty());
+        assert_eq!(chat.story.message_count, 4);
+        assert_eq!(chat.story.last_speaker, "Narrator");
+
+        let [greeting, question, answer, rest] = &chat.messages[..] else {
+            panic!("expected four messages");
+        };
+        assert_eq!(greeting.role, StoryRole::Character { name: "Seraphina".to_string() });
+        assert_eq!(greeting.created_at.unwrap().to_rfc3339(), "2023-05-02T15:30:00+00:00");
+        assert_eq!(question.role, StoryRole::User { name: "Theron".to_string() });
+        assert_eq!(question.created_at.unwrap().to_rfc3339(), "2023-05-02T15:31:00+00:00");
+        assert_eq!(rest.role, StoryRole::Narrator);
+        assert_eq!(rest.created_at.unwrap().timestamp(), 1683041500);
+
+        assert_eq!(answer.alternate_count(), 3);
+        assert_eq!(answer.selected_alternate, 1);
+        assert_eq!(answer.content, "In my glade.");
+        assert_eq!(answer.created_at.unwrap().to_rfc3339(), "2023-05-02T15:31:20.005+00:00");
+        let first = &answer.alternates[0].metadata;
+        assert_eq!(first.model.as_deref(), Some("gpt-4"));
+        assert_eq!(first.duration_ms, Some(5500));
+        assert_eq!(first.created_at.unwrap().to_rfc3339(), "2023-05-02T15:31:10+00:00");
+
+        chat.link_characters(&[]);
+        let created = chat.unlinked_characters();
+        assert_eq!(created.len(), 1);
+        assert_eq!(created[0].name, "Seraphina");
+        assert!(created[0].validate().is_ok());
+        assert_eq!(chat.story.characters[0].id, created[0].id);
+    }
+
+    #[test]
+    fn test_imports_tavern_ai_array_and_links_characters() {
+        let tavern = r#"[
+            {"user_name":"You","character_name":"Aqua","create_date":1680000000000},
+            {"name":"Aqua","is_user":false,"is_name":true,"send_date":1680000001000,"mes":"Hello!"},
+            {"name":"You","is_user":true,"is_name":true,"send_date":1680000002000,"mes":"Hi."}
+        ]"#;
+        let mut chat = import_chat(tavern).unwrap();
+        assert_eq!(chat.messages.len(), 2);
+        assert!(chat.messages[0].alternates.is_empty());
+
+        let mut aqua = crate::Character::new("c1", "aqua", "A goddess").to_item();
+        aqua.avatar_url = Some("aqua.png".to_string());
+        chat.link_characters(&[aqua]);
+        assert_eq!(chat.story.characters[0].id, "c1");
+        assert_eq!(chat.story.characters[0].avatar_url.as_deref(), Some("aqua.png"));
+        assert!(chat.unlinked_characters().is_empty());
+
+        let error = import_chat("{\"user_name\":\"You\"}\nnot json").err();
+        assert!(matches!(error, Some(ChatImportError::Invalid { entry: 2, .. })));
+        assert!(matches!(import_chat("{\"user_name\":\"You\"}"), Err(ChatImportError::Empty)));
+    }
+}
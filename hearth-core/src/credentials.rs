@@ -0,0 +1,449 @@
+//! Encrypted storage for API keys and auth tokens
+//!
+//! Secrets never reach the settings file. Settings hold a reference such as
+//! `credential:llm/<provider id>` and the secret itself is kept in an AES-GCM
+//! encrypted vault beside them. The vault key is either a random key file
+//! that only the user can read, or derived from a passphrase with Argon2.
+//! On the web both live in localStorage, so only a passphrase adds protection.
+
+use crate::StorageError;
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use argon2::Argon2;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::PathBuf;
+use thiserror::Error;
+
+const VAULT_FILE: &str = "credentials.vault";
+const KEY_FILE: &str = "credentials.key";
+/// A new key waiting for the vault that uses it to be in place
+const NEW_KEY_FILE: &str = "credentials.key.new";
+const VAULT_VERSION: u32 = 1;
+
+/// Marks a settings value as a reference into the credential store
+pub const CREDENTIAL_PREFIX: &str = "credential:";
+
+#[derive(Error, Debug)]
+pub enum CredentialError {
+    #[error("Storage error: {0}")]
+    Storage(#[from] StorageError),
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Credential store is locked")]
+    Locked,
+    #[error("Wrong passphrase or damaged credential store")]
+    Decryption,
+    #[error("Invalid credential store: {0}")]
+    Invalid(String),
+    #[error("Credential store could not be opened: {0}")]
+    Unavailable(String),
+}
+
+/// Settings value that refers to the named secret
+pub fn credential_reference(name: &str) -> String {
+    format!("{CREDENTIAL_PREFIX}{name}")
+}
+
+/// Name of the secret a settings value refers to, if it is a reference
+pub fn credential_name(value: &str) -> Option<&str> {
+    value.strip_prefix(CREDENTIAL_PREFIX)
+}
+
+/// Where the vault key comes from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum KeySource {
+    KeyFile,
+    Passphrase { salt: String },
+}
+
+/// On-disk form of the store
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Vault {
+    version: u32,
+    key: KeySource,
+    nonce: String,
+    ciphertext: String,
+}
+
+pub struct CredentialStore {
+    source: KeySource,
+    /// `None` while a passphrase-protected store is locked
+    key: Option<Key<Aes256Gcm>>,
+    secrets: BTreeMap<String, String>,
+    /// Vault as read, kept so it can be unlocked later
+    sealed: Option<Vault>,
+    /// Why the store could not be opened; such a store stays locked and is never written
+    problem: Option<String>,
+    /// Directory holding the vault, the storage directory when `None`
+    #[cfg(not(target_arch = "wasm32"))]
+    dir: Option<PathBuf>,
+}
+
+impl Default for CredentialStore {
+    /// Empty store with a fresh key file key
+    fn default() -> Self {
+        Self {
+            source: KeySource::KeyFile,
+            key: Some(Aes256Gcm::generate_key(OsRng)),
+            secrets: BTreeMap::new(),
+            sealed: None,
+            problem: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            dir: None,
+        }
+    }
+}
+
+impl CredentialStore {
+    /// Open the store in the app's storage, or start an empty one
+    pub fn open() -> Result<Self, CredentialError> {
+        Self::default().read_vault()
+    }
+
+    /// Open the store kept in `dir`
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn open_in(dir: PathBuf) -> Result<Self, CredentialError> {
+        Self {
+            dir: Some(dir),
+            ..Self::default()
+        }
+        .read_vault()
+    }
+
+    /// Locked store standing in for one that failed to open
+    ///
+    /// It holds no key, so nothing can overwrite the vault on disk.
+    pub fn unavailable(error: &CredentialError) -> Self {
+        Self {
+            key: None,
+            problem: Some(error.to_string()),
+            ..Self::default()
+        }
+    }
+
+    /// Why the store could not be opened, if it could not
+    pub fn problem(&self) -> Option<&str> {
+        self.problem.as_deref()
+    }
+
+    fn read_vault(mut self) -> Result<Self, CredentialError> {
+        let Some(bytes) = self.read_file(VAULT_FILE)? else {
+            return Ok(self);
+        };
+        let vault: Vault = serde_json::from_slice(&bytes).map_err(|e| CredentialError::Invalid(e.to_string()))?;
+        if vault.version != VAULT_VERSION {
+            return Err(CredentialError::Invalid(format!("unsupported version {}", vault.version)));
+        }
+
+        self.key = None;
+        self.source = vault.key.clone();
+        self.sealed = Some(vault);
+        if self.source == KeySource::KeyFile {
+            if let Err(e) = self.open_with_key_file(KEY_FILE) {
+                // A write interrupted before the new key replaced the old one
+                if self.read_file(NEW_KEY_FILE)?.is_none() {
+                    return Err(e);
+                }
+                self.open_with_key_file(NEW_KEY_FILE).map_err(|_| e)?;
+                self.rename_file(NEW_KEY_FILE, KEY_FILE)?;
+            }
+        }
+        Ok(self)
+    }
+
+    fn open_with_key_file(&mut self, name: &str) -> Result<(), CredentialError> {
+        let key = self
+            .read_file(name)?
+            .ok_or_else(|| CredentialError::Invalid("key file is missing".to_string()))?;
+        if key.len() != 32 {
+            return Err(CredentialError::Invalid("key file is damaged".to_string()));
+        }
+        self.key = Some(*Key::<Aes256Gcm>::from_slice(&key));
+        if let Err(e) = self.decrypt() {
+            self.key = None;
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    fn decrypt(&mut self) -> Result<(), CredentialError> {
+        let (Some(key), Some(vault)) = (&self.key, &self.sealed) else {
+            return Err(CredentialError::Locked);
+        };
+        let nonce = decode(&vault.nonce)?;
+        if nonce.len() != 12 {
+            return Err(CredentialError::Invalid("bad nonce".to_string()));
+        }
+        let plaintext = Aes256Gcm::new(key)
+            .decrypt(Nonce::from_slice(&nonce), decode(&vault.ciphertext)?.as_ref())
+            .map_err(|_| CredentialError::Decryption)?;
+        self.secrets = serde_json::from_slice(&plaintext).map_err(|e| CredentialError::Invalid(e.to_string()))?;
+        Ok(())
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.key.is_none()
+    }
+
+    pub fn uses_passphrase(&self) -> bool {
+        matches!(self.source, KeySource::Passphrase { .. })
+    }
+
+    /// Decrypt a passphrase-protected store
+    pub fn unlock(&mut self, passphrase: &str) -> Result<(), CredentialError> {
+        if let Some(problem) = &self.problem {
+            return Err(CredentialError::Unavailable(problem.clone()));
+        }
+        let KeySource::Passphrase { salt } = &self.source else {
+            return Ok(());
+        };
+        self.key = Some(derive_key(passphrase, &decode(salt)?)?);
+        if let Err(e) = self.decrypt() {
+            self.key = None;
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.secrets.get(name).map(String::as_str)
+    }
+
+    /// Protect the store with a passphrase, or with a key file when `None`
+    ///
+    /// Takes effect on the next [`write`](Self::write).
+    pub fn set_passphrase(&mut self, passphrase: Option<&str>) -> Result<(), CredentialError> {
+        if self.is_locked() {
+            return Err(CredentialError::Locked);
+        }
+        match passphrase {
+            Some(passphrase) => {
+                let mut salt = [0u8; 16];
+                OsRng.fill_bytes(&mut salt);
+                self.key = Some(derive_key(passphrase, &salt)?);
+                self.source = KeySource::Passphrase {
+                    salt: BASE64.encode(salt),
+                };
+            }
+            None => {
+                self.key = Some(Aes256Gcm::generate_key(OsRng));
+                self.source = KeySource::KeyFile;
+            }
+        }
+        Ok(())
+    }
+
+    /// Encrypt and store exactly these secrets, replacing the previous ones
+    pub fn write(&self, secrets: &BTreeMap<String, String>) -> Result<(), CredentialError> {
+        let key = self.key.as_ref().ok_or(CredentialError::Locked)?;
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let plaintext = serde_json::to_vec(secrets).map_err(|e| CredentialError::Invalid(e.to_string()))?;
+        let ciphertext = Aes256Gcm::new(key)
+            .encrypt(&nonce, plaintext.as_ref())
+            .map_err(|_| CredentialError::Invalid("encryption failed".to_string()))?;
+        let vault = Vault {
+            version: VAULT_VERSION,
+            key: self.source.clone(),
+            nonce: BASE64.encode(nonce),
+            ciphertext: BASE64.encode(ciphertext),
+        };
+
+        // The old key stays in place until the vault that needs the new one is written
+        let new_key =
+            self.source == KeySource::KeyFile && self.read_file(KEY_FILE)?.as_deref() != Some(key.as_slice());
+        if new_key {
+            self.write_file(NEW_KEY_FILE, key.as_slice(), true)?;
+        }
+        let content = serde_json::to_vec_pretty(&vault).map_err(|e| CredentialError::Invalid(e.to_string()))?;
+        self.write_file(VAULT_FILE, &content, false)?;
+        if new_key {
+            self.rename_file(NEW_KEY_FILE, KEY_FILE)?;
+        }
+        if self.uses_passphrase() {
+            self.remove_file(KEY_FILE)?;
+            self.remove_file(NEW_KEY_FILE)?;
+        }
+        Ok(())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn path(&self, name: &str) -> Result<PathBuf, CredentialError> {
+        match &self.dir {
+            Some(dir) => Ok(dir.join(name)),
+            None => Ok(crate::Storage::new().get_file_path(name)?),
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn read_file(&self, name: &str) -> Result<Option<Vec<u8>>, CredentialError> {
+        match std::fs::read(self.path(name)?) {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Replace a file atomically, readable only by the user when `private`
+    #[cfg(not(target_arch = "wasm32"))]
+    fn write_file(&self, name: &str, bytes: &[u8], private: bool) -> Result<(), CredentialError> {
+        Ok(crate::write_atomic(&self.path(name)?, bytes, private)?)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn rename_file(&self, from: &str, to: &str) -> Result<(), CredentialError> {
+        Ok(std::fs::rename(self.path(from)?, self.path(to)?)?)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn remove_file(&self, name: &str) -> Result<(), CredentialError> {
+        match std::fs::remove_file(self.path(name)?) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn read_file(&self, name: &str) -> Result<Option<Vec<u8>>, CredentialError> {
+        let value = local_storage()?
+            .get_item(&format!("hearth_{name}"))
+            .map_err(|_| web_error("Failed to read from localStorage"))?;
+        value.map(|value| decode(&value)).transpose()
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn write_file(&self, name: &str, bytes: &[u8], _private: bool) -> Result<(), CredentialError> {
+        local_storage()?
+            .set_item(&format!("hearth_{name}"), &BASE64.encode(bytes))
+            .map_err(|_| web_error("Failed to write to localStorage"))
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn rename_file(&self, from: &str, to: &str) -> Result<(), CredentialError> {
+        if let Some(bytes) = self.read_file(from)? {
+            self.write_file(to, &bytes, true)?;
+        }
+        self.remove_file(from)
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn remove_file(&self, name: &str) -> Result<(), CredentialError> {
+        local_storage()?
+            .remove_item(&format!("hearth_{name}"))
+            .map_err(|_| web_error("Failed to write to localStorage"))
+    }
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<Key<Aes256Gcm>, CredentialError> {
+    let mut key = Key::<Aes256Gcm>::default();
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| CredentialError::Invalid(e.to_string()))?;
+    Ok(key)
+}
+
+fn decode(value: &str) -> Result<Vec<u8>, CredentialError> {
+    BASE64.decode(value).map_err(|e| CredentialError::Invalid(e.to_string()))
+}
+
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> Result<web_sys::Storage, CredentialError> {
+    web_sys::window()
+        .and_then(|window| window.local_storage().ok().flatten())
+        .ok_or_else(|| web_error("localStorage not available"))
+}
+
+#[cfg(target_arch = "wasm32")]
+fn web_error(message: &str) -> CredentialError {
+    CredentialError::Io(std::io::Error::other(message.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("hearth-credentials-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn secrets() -> BTreeMap<String, String> {
+        BTreeMap::from([("llm/openai".to_string(), "sk-secret".to_string())])
+    }
+
+    #[test]
+    fn test_key_file_store_round_trip() {
+        let dir = temp_dir();
+        CredentialStore::open_in(dir.clone()).unwrap().write(&secrets()).unwrap();
+
+        let vault = std::fs::read_to_string(dir.join(VAULT_FILE)).unwrap();
+        assert!(!vault.contains("sk-secret"));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(dir.join(KEY_FILE)).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        let store = CredentialStore::open_in(dir.clone()).unwrap();
+        assert!(!store.is_locked());
+        assert_eq!(store.get("llm/openai"), Some("sk-secret"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_passphrase_store_unlocks_with_right_passphrase() {
+        let dir = temp_dir();
+        let mut store = CredentialStore::open_in(dir.clone()).unwrap();
+        store.set_passphrase(Some("hunter2")).unwrap();
+        store.write(&secrets()).unwrap();
+        assert!(!dir.join(KEY_FILE).exists());
+
+        let mut store = CredentialStore::open_in(dir.clone()).unwrap();
+        assert!(store.is_locked());
+        assert_eq!(store.get("llm/openai"), None);
+        assert!(matches!(store.unlock("wrong"), Err(CredentialError::Decryption)));
+        assert!(store.is_locked());
+
+        store.unlock("hunter2").unwrap();
+        assert_eq!(store.get("llm/openai"), Some("sk-secret"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_interrupted_key_change_is_recovered() {
+        let dir = temp_dir();
+        CredentialStore::open_in(dir.clone()).unwrap().write(&secrets()).unwrap();
+        let old_key = std::fs::read(dir.join(KEY_FILE)).unwrap();
+
+        let mut store = CredentialStore::open_in(dir.clone()).unwrap();
+        store.set_passphrase(None).unwrap();
+        store.write(&secrets()).unwrap();
+        let new_key = std::fs::read(dir.join(KEY_FILE)).unwrap();
+        assert_ne!(old_key, new_key);
+
+        // Stop as if the process died after the vault but before the key was swapped in
+        std::fs::rename(dir.join(KEY_FILE), dir.join(NEW_KEY_FILE)).unwrap();
+        std::fs::write(dir.join(KEY_FILE), &old_key).unwrap();
+
+        let store = CredentialStore::open_in(dir.clone()).unwrap();
+        assert_eq!(store.get("llm/openai"), Some("sk-secret"));
+        assert_eq!(std::fs::read(dir.join(KEY_FILE)).unwrap(), new_key);
+        assert!(!dir.join(NEW_KEY_FILE).exists());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_unavailable_store_is_never_written() {
+        let store = CredentialStore::unavailable(&CredentialError::Invalid("key file is missing".to_string()));
+        assert!(store.is_locked());
+        assert!(store.problem().is_some());
+        assert!(matches!(store.write(&secrets()), Err(CredentialError::Locked)));
+    }
+}
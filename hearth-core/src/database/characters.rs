@@ -0,0 +1,183 @@
+//! Character repository
+
+use super::{expect_affected, json_column, to_json, Database, DatabaseError};
+use crate::models::{Character, CharacterItem};
+use rusqlite::{params, OptionalExtension, Row};
+
+const COLUMNS: &str = "id, name, description, avatar_url, tags, is_favorite, last_used, story_count";
+const DEFINITION_COLUMNS: &str = "id, name, description, avatar_url, tags, is_favorite, last_used, \
+                                  story_count, personality, example_dialogue, default_scenario_id, \
+                                  response_style, default_histories, template_id, card_extras";
+
+pub struct CharacterRepository<'a> {
+    db: &'a Database,
+}
+
+impl<'a> CharacterRepository<'a> {
+    pub(crate) fn new(db: &'a Database) -> Self {
+        Self { db }
+    }
+
+    fn from_row(row: &Row) -> rusqlite::Result<CharacterItem> {
+        Ok(CharacterItem {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            description: row.get(2)?,
+            avatar_url: row.get(3)?,
+            tags: json_column(row, 4)?,
+            is_favorite: row.get(5)?,
+            last_used: row.get(6)?,
+            story_count: row.get(7)?,
+        })
+    }
+
+    fn definition_from_row(row: &Row) -> rusqlite::Result<Character> {
+        Ok(Character {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            description: row.get(2)?,
+            avatar_url: row.get(3)?,
+            tags: json_column(row, 4)?,
+            is_favorite: row.get(5)?,
+            last_used: row.get(6)?,
+            story_count: row.get(7)?,
+            personality: row.get(8)?,
+            example_dialogue: row.get(9)?,
+            default_scenario_id: row.get(10)?,
+            response_style: json_column(row, 11)?,
+            default_histories: json_column(row, 12)?,
+            template_id: row.get(13)?,
+            card_extras: json_column(row, 14)?,
+        })
+    }
+
+    pub fn list(&self) -> Result<Vec<CharacterItem>, DatabaseError> {
+        self.db.with_conn(|conn| {
+            let mut stmt =
+                conn.prepare(&format!("SELECT {COLUMNS} FROM characters ORDER BY name"))?;
+            let characters = stmt
+                .query_map([], Self::from_row)?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(characters)
+        })
+    }
+
+    pub fn get(&self, id: &str) -> Result<Option<CharacterItem>, DatabaseError> {
+        self.db.with_conn(|conn| {
+            Ok(conn
+                .query_row(
+                    &format!("SELECT {COLUMNS} FROM characters WHERE id = ?1"),
+                    [id],
+                    Self::from_row,
+                )
+                .optional()?)
+        })
+    }
+
+    /// Load the full character definition
+    pub fn get_definition(&self, id: &str) -> Result<Option<Character>, DatabaseError> {
+        self.db.with_conn(|conn| {
+            Ok(conn
+                .query_row(
+                    &format!("SELECT {DEFINITION_COLUMNS} FROM characters WHERE id = ?1"),
+                    [id],
+                    Self::definition_from_row,
+                )
+                .optional()?)
+        })
+    }
+
+    /// Insert or replace a full character definition
+    pub fn save_definition(&self, character: &Character) -> Result<(), DatabaseError> {
+        let tags = to_json(&character.tags)?;
+        let response_style = to_json(&character.response_style)?;
+        let default_histories = to_json(&character.default_histories)?;
+        let card_extras = to_json(&character.card_extras)?;
+        self.db.with_conn(|conn| {
+            conn.execute(
+                &format!(
+                    "INSERT INTO characters ({DEFINITION_COLUMNS})
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)
+                     ON CONFLICT(id) DO UPDATE SET
+                        name = excluded.name, description = excluded.description,
+                        avatar_url = excluded.avatar_url, tags = excluded.tags,
+                        is_favorite = excluded.is_favorite, last_used = excluded.last_used,
+                        story_count = excluded.story_count, personality = excluded.personality,
+                        example_dialogue = excluded.example_dialogue,
+                        default_scenario_id = excluded.default_scenario_id,
+                        response_style = excluded.response_style,
+                        default_histories = excluded.default_histories,
+                        template_id = excluded.template_id,
+                        card_extras = excluded.card_extras"
+                ),
+                params![
+                    character.id,
+                    character.name,
+                    character.description,
+                    character.avatar_url,
+                    tags,
+                    character.is_favorite,
+                    character.last_used,
+                    character.story_count,
+                    character.personality,
+                    character.example_dialogue,
+                    character.default_scenario_id,
+                    response_style,
+                    default_histories,
+                    character.template_id,
+                    card_extras,
+                ],
+            )?;
+            Ok(())
+        })
+    }
+
+    pub fn create(&self, character: &CharacterItem) -> Result<(), DatabaseError> {
+        let tags = to_json(&character.tags)?;
+        self.db.with_conn(|conn| {
+            conn.execute(
+                &format!("INSERT INTO characters ({COLUMNS}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)"),
+                params![
+                    character.id,
+                    character.name,
+                    character.description,
+                    character.avatar_url,
+                    tags,
+                    character.is_favorite,
+                    character.last_used,
+                    character.story_count,
+                ],
+            )?;
+            Ok(())
+        })
+    }
+
+    pub fn update(&self, character: &CharacterItem) -> Result<(), DatabaseError> {
+        let tags = to_json(&character.tags)?;
+        self.db.with_conn(|conn| {
+            let affected = conn.execute(
+                "UPDATE characters SET name = ?2, description = ?3, avatar_url = ?4, tags = ?5,
+                    is_favorite = ?6, last_used = ?7, story_count = ?8
+                 WHERE id = ?1",
+                params![
+                    character.id,
+                    character.name,
+                    character.description,
+                    character.avatar_url,
+                    tags,
+                    character.is_favorite,
+                    character.last_used,
+                    character.story_count,
+                ],
+            )?;
+            expect_affected(affected, "Character", &character.id)
+        })
+    }
+
+    pub fn delete(&self, id: &str) -> Result<(), DatabaseError> {
+        self.db.with_conn(|conn| {
+            let affected = conn.execute("DELETE FROM characters WHERE id = ?1", [id])?;
+            expect_affected(affected, "Character", id)
+        })
+    }
+}
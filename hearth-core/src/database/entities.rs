@@ -0,0 +1,102 @@
+//! Entity library repository
+
+use super::{expect_affected, json_column, to_json, Database, DatabaseError};
+use crate::entity::Entity;
+use rusqlite::{params, OptionalExtension, Row};
+
+const COLUMNS: &str = "id, name, description, personality, avatar_url, tags, format";
+
+pub struct EntityRepository<'a> {
+    db: &'a Database,
+}
+
+impl<'a> EntityRepository<'a> {
+    pub(crate) fn new(db: &'a Database) -> Self {
+        Self { db }
+    }
+
+    fn from_row(row: &Row) -> rusqlite::Result<Entity> {
+        Ok(Entity {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            description: row.get(2)?,
+            personality: row.get(3)?,
+            avatar_url: row.get(4)?,
+            tags: json_column(row, 5)?,
+            format: json_column(row, 6)?,
+        })
+    }
+
+    pub fn list(&self) -> Result<Vec<Entity>, DatabaseError> {
+        self.db.with_conn(|conn| {
+            let mut stmt =
+                conn.prepare(&format!("SELECT {COLUMNS} FROM entities ORDER BY name"))?;
+            let entities = stmt
+                .query_map([], Self::from_row)?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(entities)
+        })
+    }
+
+    pub fn get(&self, id: &str) -> Result<Option<Entity>, DatabaseError> {
+        self.db.with_conn(|conn| {
+            Ok(conn
+                .query_row(
+                    &format!("SELECT {COLUMNS} FROM entities WHERE id = ?1"),
+                    [id],
+                    Self::from_row,
+                )
+                .optional()?)
+        })
+    }
+
+    pub fn create(&self, entity: &Entity) -> Result<(), DatabaseError> {
+        let tags = to_json(&entity.tags)?;
+        let format = to_json(&entity.format)?;
+        self.db.with_conn(|conn| {
+            conn.execute(
+                &format!("INSERT INTO entities ({COLUMNS}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)"),
+                params![
+                    entity.id,
+                    entity.name,
+                    entity.description,
+                    entity.personality,
+                    entity.avatar_url,
+                    tags,
+                    format,
+                ],
+            )?;
+            Ok(())
+        })
+    }
+
+    pub fn update(&self, entity: &Entity) -> Result<(), DatabaseError> {
+        let tags = to_json(&entity.tags)?;
+        let format = to_json(&entity.format)?;
+        self.db.with_conn(|conn| {
+            let affected = conn.execute(
+                "UPDATE entities SET name = ?2, description = ?3, personality = ?4,
+                    avatar_url = ?5, tags = ?6, format = ?7
+                 WHERE id = ?1",
+                params![
+                    entity.id,
+                    entity.name,
+                    entity.description,
+                    entity.personality,
+                    entity.avatar_url,
+                    tags,
+                    format,
+                ],
+            )?;
+            expect_affected(affected, "Entity", &entity.id)
+        })
+    }
+
+    /// Delete a template; characters and personas made from it keep their data
+    pub fn delete(&self, id: &str) -> Result<(), DatabaseError> {
+        self.db.with_conn(|conn| {
+            let affected = conn.execute("DELETE FROM entities WHERE id = ?1", [id])?;
+            expect_affected(affected, "Entity", id)
+        })
+    }
+}
@@ -0,0 +1,63 @@
+//! Message guidance repository
+
+use super::{Database, DatabaseError};
+use crate::guidance::MessageGuidance;
+use rusqlite::{params, Row};
+
+const COLUMNS: &str = "id, message_id, author_id, character, text, created_at";
+
+pub struct GuidanceRepository<'a> {
+    db: &'a Database,
+}
+
+impl<'a> GuidanceRepository<'a> {
+    pub(crate) fn new(db: &'a Database) -> Self {
+        Self { db }
+    }
+
+    fn from_row(row: &Row) -> rusqlite::Result<MessageGuidance> {
+        Ok(MessageGuidance {
+            id: row.get(0)?,
+            message_id: row.get(1)?,
+            author_id: row.get(2)?,
+            character: row.get(3)?,
+            text: row.get(4)?,
+            created_at: row.get(5)?,
+        })
+    }
+
+    /// Guidance attached to a message, limited to what `author_id` wrote
+    pub fn list_for_message(
+        &self,
+        message_id: &str,
+        author_id: &str,
+    ) -> Result<Vec<MessageGuidance>, DatabaseError> {
+        self.db.with_conn(|conn| {
+            let mut stmt = conn.prepare(&format!(
+                "SELECT {COLUMNS} FROM message_guidance
+                 WHERE message_id = ?1 AND author_id = ?2 ORDER BY created_at"
+            ))?;
+            let guidance = stmt
+                .query_map(params![message_id, author_id], Self::from_row)?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(guidance)
+        })
+    }
+
+    pub fn create(&self, guidance: &MessageGuidance) -> Result<(), DatabaseError> {
+        self.db.with_conn(|conn| {
+            conn.execute(
+                &format!("INSERT INTO message_guidance ({COLUMNS}) VALUES (?1, ?2, ?3, ?4, ?5, ?6)"),
+                params![
+                    guidance.id,
+                    guidance.message_id,
+                    guidance.author_id,
+                    guidance.character,
+                    guidance.text,
+                    guidance.created_at,
+                ],
+            )?;
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,292 @@
+//! Story message repository
+
+use super::{expect_affected, json_column, to_json, Database, DatabaseError};
+use crate::message_tree::{MessageNode, MessageTree};
+use crate::models::{MessageRevision, StoryMessage, StoryRole};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+
+const COLUMNS: &str = "id, role, speaker, content, alternates, selected_alternate, created_at";
+
+pub struct MessageRepository<'a> {
+    db: &'a Database,
+}
+
+impl<'a> MessageRepository<'a> {
+    pub(crate) fn new(db: &'a Database) -> Self {
+        Self { db }
+    }
+
+    fn from_row(row: &Row) -> rusqlite::Result<StoryMessage> {
+        let role: String = row.get(1)?;
+        let speaker: Option<String> = row.get(2)?;
+        Ok(StoryMessage {
+            id: row.get(0)?,
+            role: role_from_columns(&role, speaker),
+            content: row.get(3)?,
+            alternates: json_column(row, 4)?,
+            selected_alternate: row.get::<_, i64>(5)? as usize,
+            created_at: row.get(6)?,
+        })
+    }
+
+    /// List the messages of a story in conversation order
+    pub fn list_for_story(&self, story_id: &str) -> Result<Vec<StoryMessage>, DatabaseError> {
+        self.db.with_conn(|conn| {
+            let mut stmt = conn.prepare(&format!(
+                "SELECT {COLUMNS} FROM messages WHERE story_id = ?1 ORDER BY position"
+            ))?;
+            let messages = stmt
+                .query_map([story_id], Self::from_row)?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(messages)
+        })
+    }
+
+    pub fn get(&self, id: &str) -> Result<Option<StoryMessage>, DatabaseError> {
+        self.db.with_conn(|conn| {
+            Ok(conn
+                .query_row(
+                    &format!("SELECT {COLUMNS} FROM messages WHERE id = ?1"),
+                    [id],
+                    Self::from_row,
+                )
+                .optional()?)
+        })
+    }
+
+    /// Load every message of a story with its parent links and the active cursor
+    pub fn load_tree(&self, story_id: &str) -> Result<MessageTree, DatabaseError> {
+        self.db.with_conn(|conn| {
+            let mut stmt = conn.prepare(&format!(
+                "SELECT {COLUMNS}, parent_id FROM messages WHERE story_id = ?1 ORDER BY position"
+            ))?;
+            let nodes = stmt
+                .query_map([story_id], |row| {
+                    Ok(MessageNode {
+                        message: Self::from_row(row)?,
+                        parent_id: row.get(7)?,
+                    })
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            let cursor = active_message_id(conn, story_id)?;
+            Ok(MessageTree::new(nodes, cursor))
+        })
+    }
+
+    /// Append a message after the story's cursor and make it the new cursor
+    pub fn create(&self, story_id: &str, message: &StoryMessage) -> Result<(), DatabaseError> {
+        self.db.with_conn(|conn| {
+            let tx = conn.transaction()?;
+            insert_message(&tx, story_id, message)?;
+            tx.commit()?;
+            Ok(())
+        })
+    }
+
+    /// Store the story's active-path cursor
+    pub fn set_cursor(&self, story_id: &str, message_id: Option<&str>) -> Result<(), DatabaseError> {
+        self.db
+            .with_conn(|conn| set_active_message_id(conn, story_id, message_id))
+    }
+
+    /// Delete the messages of a pruned branch and store the new cursor
+    pub fn remove_branch(
+        &self,
+        story_id: &str,
+        removed: &[String],
+        cursor: Option<&str>,
+    ) -> Result<(), DatabaseError> {
+        self.db.with_conn(|conn| {
+            let tx = conn.transaction()?;
+            // Leaf first, so no remaining message points at a deleted parent
+            for id in removed {
+                tx.execute(
+                    "DELETE FROM messages WHERE id = ?1 AND story_id = ?2",
+                    params![id, story_id],
+                )?;
+            }
+            set_active_message_id(&tx, story_id, cursor)?;
+            tx.commit()?;
+            Ok(())
+        })
+    }
+
+    pub fn update(&self, message: &StoryMessage) -> Result<(), DatabaseError> {
+        let (role, speaker) = role_to_columns(&message.role);
+        let alternates = to_json(&message.alternates)?;
+        self.db.with_conn(|conn| {
+            let affected = conn.execute(
+                "UPDATE messages SET role = ?2, speaker = ?3, content = ?4, alternates = ?5,
+                    selected_alternate = ?6
+                 WHERE id = ?1",
+                params![
+                    message.id,
+                    role,
+                    speaker,
+                    message.content,
+                    alternates,
+                    message.selected_alternate as i64,
+                ],
+            )?;
+            expect_affected(affected, "Message", &message.id)
+        })
+    }
+
+    pub fn delete(&self, id: &str) -> Result<(), DatabaseError> {
+        self.db.with_conn(|conn| {
+            let affected = conn.execute("DELETE FROM messages WHERE id = ?1", [id])?;
+            expect_affected(affected, "Message", id)
+        })
+    }
+
+    /// Change a message's content, saving the previous content as a revision
+    ///
+    /// Edits that do not change the content are not recorded.
+    pub fn edit(&self, id: &str, content: &str) -> Result<StoryMessage, DatabaseError> {
+        let mut message = self
+            .get(id)?
+            .ok_or_else(|| DatabaseError::NotFound(format!("Message '{id}'")))?;
+        if message.content == content {
+            return Ok(message);
+        }
+
+        let revision = MessageRevision {
+            id: uuid::Uuid::new_v4().to_string(),
+            message_id: id.to_string(),
+            content: message.content.clone(),
+            edited_at: chrono::Utc::now(),
+        };
+        message.set_content(content);
+        let alternates = to_json(&message.alternates)?;
+
+        self.db.with_conn(|conn| {
+            let tx = conn.transaction()?;
+            tx.execute(
+                "INSERT INTO message_revisions (id, message_id, content, edited_at)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    revision.id,
+                    revision.message_id,
+                    revision.content,
+                    revision.edited_at,
+                ],
+            )?;
+            tx.execute(
+                "UPDATE messages SET content = ?2, alternates = ?3 WHERE id = ?1",
+                params![id, message.content, alternates],
+            )?;
+            tx.commit()?;
+            Ok(())
+        })?;
+        Ok(message)
+    }
+
+    /// Earlier versions of a message, newest first
+    pub fn revisions(&self, message_id: &str) -> Result<Vec<MessageRevision>, DatabaseError> {
+        self.db.with_conn(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT id, message_id, content, edited_at FROM message_revisions
+                 WHERE message_id = ?1 ORDER BY edited_at DESC, rowid DESC",
+            )?;
+            let revisions = stmt
+                .query_map([message_id], |row| {
+                    Ok(MessageRevision {
+                        id: row.get(0)?,
+                        message_id: row.get(1)?,
+                        content: row.get(2)?,
+                        edited_at: row.get(3)?,
+                    })
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(revisions)
+        })
+    }
+
+    /// Bring back the content of a revision
+    ///
+    /// This is an edit itself, so the content being replaced becomes a new revision.
+    pub fn restore_revision(&self, message_id: &str, revision_id: &str) -> Result<StoryMessage, DatabaseError> {
+        let content: String = self
+            .db
+            .with_conn(|conn| {
+                Ok(conn
+                    .query_row(
+                        "SELECT content FROM message_revisions WHERE id = ?1 AND message_id = ?2",
+                        [revision_id, message_id],
+                        |row| row.get(0),
+                    )
+                    .optional()?)
+            })?
+            .ok_or_else(|| DatabaseError::NotFound(format!("Revision '{revision_id}' of message '{message_id}'")))?;
+        self.edit(message_id, &content)
+    }
+}
+
+/// Insert a message after the story's cursor and move the cursor to it
+pub(super) fn insert_message(conn: &Connection, story_id: &str, message: &StoryMessage) -> Result<(), DatabaseError> {
+    let (role, speaker) = role_to_columns(&message.role);
+    let alternates = to_json(&message.alternates)?;
+    let parent_id = active_message_id(conn, story_id)?;
+    conn.execute(
+        "INSERT INTO messages (id, story_id, position, role, speaker, content,
+            alternates, selected_alternate, parent_id, created_at)
+         VALUES (?1, ?2,
+            (SELECT COALESCE(MAX(position), -1) + 1 FROM messages WHERE story_id = ?2),
+            ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            message.id,
+            story_id,
+            role,
+            speaker,
+            message.content,
+            alternates,
+            message.selected_alternate as i64,
+            parent_id,
+            message.created_at,
+        ],
+    )?;
+    set_active_message_id(conn, story_id, Some(&message.id))
+}
+
+fn active_message_id(conn: &Connection, story_id: &str) -> Result<Option<String>, DatabaseError> {
+    Ok(conn
+        .query_row(
+            "SELECT active_message_id FROM stories WHERE id = ?1",
+            [story_id],
+            |row| row.get(0),
+        )
+        .optional()?
+        .flatten())
+}
+
+fn set_active_message_id(
+    conn: &Connection,
+    story_id: &str,
+    message_id: Option<&str>,
+) -> Result<(), DatabaseError> {
+    let affected = conn.execute(
+        "UPDATE stories SET active_message_id = ?2 WHERE id = ?1",
+        params![story_id, message_id],
+    )?;
+    expect_affected(affected, "Story", story_id)
+}
+
+fn role_to_columns(role: &StoryRole) -> (&'static str, Option<&str>) {
+    match role {
+        StoryRole::User { name } => ("user", Some(name.as_str())),
+        StoryRole::Narrator => ("narrator", None),
+        StoryRole::Character { name } => ("character", Some(name.as_str())),
+    }
+}
+
+fn role_from_columns(role: &str, speaker: Option<String>) -> StoryRole {
+    match role {
+        "user" => StoryRole::User {
+            name: speaker.unwrap_or_default(),
+        },
+        "character" => StoryRole::Character {
+            name: speaker.unwrap_or_default(),
+        },
+        _ => StoryRole::Narrator,
+    }
+}
@@ -0,0 +1,375 @@
+//! Versioned schema migrations
+//!
+//! Migrations are embedded in the binary and applied in order. Each applied
+//! version is recorded in the `schema_migrations` table so upgrades only run
+//! what is still pending.
+
+use super::DatabaseError;
+use rusqlite::{params, Connection};
+
+/// A single embedded schema migration
+pub struct Migration {
+    pub version: u32,
+    pub name: &'static str,
+    pub sql: &'static str,
+}
+
+/// All known migrations, in the order they must be applied
+///
+/// Never edit a migration that has shipped; add a new one instead.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "initial_schema",
+        // `IF NOT EXISTS` lets databases created before migrations existed adopt this version
+        sql: "
+            CREATE TABLE IF NOT EXISTS characters (
+                id TEXT PRIMARY KEY NOT NULL,
+                name TEXT NOT NULL,
+                description TEXT NOT NULL,
+                avatar_url TEXT,
+                tags TEXT NOT NULL,
+                is_favorite INTEGER NOT NULL DEFAULT 0,
+                last_used TEXT,
+                story_count INTEGER NOT NULL DEFAULT 0
+            );
+
+            CREATE TABLE IF NOT EXISTS scenarios (
+                id TEXT PRIMARY KEY NOT NULL,
+                name TEXT NOT NULL,
+                description TEXT NOT NULL,
+                avatar_url TEXT,
+                tags TEXT NOT NULL,
+                is_favorite INTEGER NOT NULL DEFAULT 0,
+                last_used TEXT,
+                story_count INTEGER NOT NULL DEFAULT 0
+            );
+
+            CREATE TABLE IF NOT EXISTS stories (
+                id TEXT PRIMARY KEY NOT NULL,
+                title TEXT NOT NULL,
+                characters TEXT NOT NULL,
+                user_character TEXT,
+                last_message TEXT NOT NULL,
+                last_speaker TEXT NOT NULL,
+                timestamp TEXT NOT NULL,
+                scenario_name TEXT,
+                message_count INTEGER NOT NULL DEFAULT 0
+            );
+
+            CREATE TABLE IF NOT EXISTS messages (
+                id TEXT PRIMARY KEY NOT NULL,
+                story_id TEXT NOT NULL REFERENCES stories(id) ON DELETE CASCADE,
+                position INTEGER NOT NULL,
+                role TEXT NOT NULL,
+                speaker TEXT,
+                content TEXT NOT NULL
+            );
+        ",
+    },
+    Migration {
+        version: 2,
+        name: "message_story_index",
+        sql: "CREATE INDEX IF NOT EXISTS idx_messages_story ON messages(story_id, position);",
+    },
+    Migration {
+        version: 3,
+        name: "character_definitions",
+        sql: "
+            ALTER TABLE characters ADD COLUMN personality TEXT NOT NULL DEFAULT '';
+            ALTER TABLE characters ADD COLUMN example_dialogue TEXT NOT NULL DEFAULT '';
+            ALTER TABLE characters ADD COLUMN default_scenario_id TEXT;
+            ALTER TABLE characters ADD COLUMN response_style TEXT NOT NULL DEFAULT '{}';
+            ALTER TABLE characters ADD COLUMN default_histories TEXT NOT NULL DEFAULT '[]';
+        ",
+    },
+    Migration {
+        version: 4,
+        name: "personas",
+        sql: "
+            CREATE TABLE IF NOT EXISTS personas (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                description TEXT NOT NULL DEFAULT '',
+                avatar_url TEXT,
+                tags TEXT NOT NULL DEFAULT '[]',
+                injection TEXT NOT NULL DEFAULT '{}',
+                locks TEXT NOT NULL DEFAULT '[]'
+            );
+        ",
+    },
+    Migration {
+        version: 5,
+        name: "entity_library",
+        sql: "
+            CREATE TABLE IF NOT EXISTS entities (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                description TEXT NOT NULL DEFAULT '',
+                personality TEXT NOT NULL DEFAULT '',
+                avatar_url TEXT,
+                tags TEXT NOT NULL DEFAULT '[]',
+                format TEXT NOT NULL DEFAULT '\"PlainText\"'
+            );
+            ALTER TABLE characters ADD COLUMN template_id TEXT;
+            ALTER TABLE personas ADD COLUMN template_id TEXT;
+        ",
+    },
+    Migration {
+        version: 6,
+        name: "message_tree",
+        // Existing stories become a single branch: each message replies to the one before it
+        sql: "
+            ALTER TABLE messages ADD COLUMN parent_id TEXT REFERENCES messages(id) ON DELETE CASCADE;
+            UPDATE messages SET parent_id = (
+                SELECT p.id FROM messages p
+                WHERE p.story_id = messages.story_id AND p.position < messages.position
+                ORDER BY p.position DESC LIMIT 1
+            );
+            CREATE INDEX IF NOT EXISTS idx_messages_parent ON messages(parent_id);
+
+            ALTER TABLE stories ADD COLUMN active_message_id TEXT;
+            UPDATE stories SET active_message_id = (
+                SELECT id FROM messages WHERE story_id = stories.id
+                ORDER BY position DESC LIMIT 1
+            );
+        ",
+    },
+    Migration {
+        version: 7,
+        name: "message_alternates",
+        sql: "
+            ALTER TABLE messages ADD COLUMN alternates TEXT NOT NULL DEFAULT '[]';
+            ALTER TABLE messages ADD COLUMN selected_alternate INTEGER NOT NULL DEFAULT 0;
+        ",
+    },
+    Migration {
+        version: 8,
+        name: "message_revisions",
+        sql: "
+            CREATE TABLE IF NOT EXISTS message_revisions (
+                id TEXT PRIMARY KEY NOT NULL,
+                message_id TEXT NOT NULL REFERENCES messages(id) ON DELETE CASCADE,
+                content TEXT NOT NULL,
+                edited_at TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_message_revisions_message
+                ON message_revisions(message_id, edited_at);
+        ",
+    },
+    Migration {
+        version: 9,
+        name: "message_guidance",
+        // Kept out of the messages table so guidance never enters the conversation
+        sql: "
+            CREATE TABLE IF NOT EXISTS message_guidance (
+                id TEXT PRIMARY KEY NOT NULL,
+                message_id TEXT NOT NULL REFERENCES messages(id) ON DELETE CASCADE,
+                author_id TEXT NOT NULL,
+                character TEXT,
+                text TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_message_guidance_message
+                ON message_guidance(message_id, author_id);
+        ",
+    },
+    Migration {
+        version: 10,
+        name: "story_sampler",
+        sql: "ALTER TABLE stories ADD COLUMN sampler TEXT;",
+    },
+    Migration {
+        version: 11,
+        name: "character_card_extras",
+        sql: "ALTER TABLE characters ADD COLUMN card_extras TEXT NOT NULL DEFAULT '{}';",
+    },
+    Migration {
+        version: 12,
+        name: "message_timestamps",
+        sql: "ALTER TABLE messages ADD COLUMN created_at TEXT;",
+    },
+];
+
+/// Version of the newest embedded migration
+pub fn latest_version() -> u32 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+/// Highest migration version recorded in the database (0 if none)
+pub fn current_version(conn: &Connection) -> Result<u32, DatabaseError> {
+    ensure_migrations_table(conn)?;
+    let version: Option<u32> =
+        conn.query_row("SELECT MAX(version) FROM schema_migrations", [], |row| {
+            row.get(0)
+        })?;
+    Ok(version.unwrap_or(0))
+}
+
+/// Apply every pending migration, returning the versions that were applied
+pub fn run_migrations(conn: &mut Connection) -> Result<Vec<u32>, DatabaseError> {
+    let current = current_version(conn)?;
+    let latest = latest_version();
+    if current > latest {
+        return Err(DatabaseError::Migration(format!(
+            "database schema version {current} is newer than this build supports ({latest})"
+        )));
+    }
+
+    let mut applied = Vec::new();
+    for migration in MIGRATIONS.iter().filter(|m| m.version > current) {
+        log::info!(
+            "Applying database migration {} ({})",
+            migration.version,
+            migration.name
+        );
+
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration.sql).map_err(|e| {
+            DatabaseError::Migration(format!(
+                "migration {} ({}) failed: {e}",
+                migration.version, migration.name
+            ))
+        })?;
+        tx.execute(
+            "INSERT INTO schema_migrations (version, name, applied_at) VALUES (?1, ?2, ?3)",
+            params![
+                migration.version,
+                migration.name,
+                chrono::Utc::now().to_rfc3339()
+            ],
+        )?;
+        tx.commit()?;
+
+        applied.push(migration.version);
+    }
+
+    if applied.is_empty() {
+        log::trace!("Database schema is up to date (version {current})");
+    }
+    Ok(applied)
+}
+
+fn ensure_migrations_table(conn: &Connection) -> Result<(), DatabaseError> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS schema_migrations (
+            version INTEGER PRIMARY KEY NOT NULL,
+            name TEXT NOT NULL,
+            applied_at TEXT NOT NULL
+        );",
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Database;
+
+    /// Schema and data as written by builds that predate versioned migrations
+    const LEGACY_FIXTURE: &str = "
+        CREATE TABLE characters (
+            id TEXT PRIMARY KEY NOT NULL,
+            name TEXT NOT NULL,
+            description TEXT NOT NULL,
+            avatar_url TEXT,
+            tags TEXT NOT NULL,
+            is_favorite INTEGER NOT NULL DEFAULT 0,
+            last_used TEXT,
+            story_count INTEGER NOT NULL DEFAULT 0
+        );
+        CREATE TABLE scenarios (
+            id TEXT PRIMARY KEY NOT NULL,
+            name TEXT NOT NULL,
+            description TEXT NOT NULL,
+            avatar_url TEXT,
+            tags TEXT NOT NULL,
+            is_favorite INTEGER NOT NULL DEFAULT 0,
+            last_used TEXT,
+            story_count INTEGER NOT NULL DEFAULT 0
+        );
+        CREATE TABLE stories (
+            id TEXT PRIMARY KEY NOT NULL,
+            title TEXT NOT NULL,
+            characters TEXT NOT NULL,
+            user_character TEXT,
+            last_message TEXT NOT NULL,
+            last_speaker TEXT NOT NULL,
+            timestamp TEXT NOT NULL,
+            scenario_name TEXT,
+            message_count INTEGER NOT NULL DEFAULT 0
+        );
+        CREATE TABLE messages (
+            id TEXT PRIMARY KEY NOT NULL,
+            story_id TEXT NOT NULL REFERENCES stories(id) ON DELETE CASCADE,
+            position INTEGER NOT NULL,
+            role TEXT NOT NULL,
+            speaker TEXT,
+            content TEXT NOT NULL
+        );
+
+        INSERT INTO characters VALUES ('c1', 'Alice', 'A tavern keeper', NULL, '[\"Fantasy\"]', 1, 'Today', 1);
+        INSERT INTO stories VALUES ('s1', 'Tavern Tales', '[{\"id\":\"c1\",\"name\":\"Alice\",\"avatar_url\":null}]',
+            NULL, 'Welcome!', 'Alice', 'Today', NULL, 2);
+        INSERT INTO messages VALUES ('m1', 's1', 0, 'user', 'Theron', 'Hello there.');
+        INSERT INTO messages VALUES ('m2', 's1', 1, 'character', 'Alice', 'Welcome!');
+    ";
+
+    #[test]
+    fn test_fresh_database_is_at_latest_version() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let applied = run_migrations(&mut conn).unwrap();
+        assert_eq!(applied.len(), MIGRATIONS.len());
+        assert_eq!(current_version(&conn).unwrap(), latest_version());
+
+        // Running again is a no-op
+        assert!(run_migrations(&mut conn).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_migrates_legacy_fixture_to_latest_schema() {
+        let path = std::env::temp_dir().join(format!("hearth-migration-{}.db", uuid::Uuid::new_v4()));
+        {
+            let conn = Connection::open(&path).unwrap();
+            conn.execute_batch(LEGACY_FIXTURE).unwrap();
+        }
+
+        let db = Database::open_at(&path).unwrap();
+        assert_eq!(db.schema_version().unwrap(), latest_version());
+
+        let alice = db.characters().get("c1").unwrap().unwrap();
+        assert_eq!(alice.name, "Alice");
+        assert_eq!(alice.tags, vec!["Fantasy".to_string()]);
+
+        let story = db.stories().get("s1").unwrap().unwrap();
+        assert_eq!(story.characters.len(), 1);
+
+        let messages = db.messages().list_for_story("s1").unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[1].content, "Welcome!");
+
+        // The flat history became a single branch ending at the last message
+        let tree = db.messages().load_tree("s1").unwrap();
+        assert_eq!(tree.cursor(), Some("m2"));
+        assert_eq!(tree.nodes()[1].parent_id.as_deref(), Some("m1"));
+
+        drop(db);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_rejects_newer_schema() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        run_migrations(&mut conn).unwrap();
+        conn.execute(
+            "INSERT INTO schema_migrations (version, name, applied_at) VALUES (?1, 'future', '')",
+            [latest_version() + 1],
+        )
+        .unwrap();
+
+        assert!(matches!(
+            run_migrations(&mut conn),
+            Err(DatabaseError::Migration(_))
+        ));
+    }
+}
@@ -0,0 +1,421 @@
+//! Local SQLite persistence layer
+//!
+//! Opens the database at `LocalBackendConfig.database_path` (or `hearth.db` in the
+//! storage directory), applies pending migrations and exposes typed repositories
+//! for the core models.
+
+use crate::{sample, LocalBackendConfig, Storage, StorageError};
+use rusqlite::{types::Type, Connection, Row};
+use serde::{de::DeserializeOwned, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use thiserror::Error;
+
+mod characters;
+mod entities;
+mod guidance;
+mod messages;
+pub mod migrations;
+mod personas;
+mod scenarios;
+mod stories;
+
+pub use characters::*;
+pub use entities::*;
+pub use guidance::*;
+pub use messages::*;
+pub use personas::*;
+pub use scenarios::*;
+pub use stories::*;
+
+/// Default database file name inside the storage directory
+pub const DEFAULT_DATABASE_FILE: &str = "hearth.db";
+
+#[derive(Error, Debug)]
+pub enum DatabaseError {
+    #[error("Storage error: {0}")]
+    Storage(#[from] StorageError),
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("SQLite error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("Serialization error: {0}")]
+    Serialization(String),
+    #[error("Lock error: {0}")]
+    Lock(String),
+    #[error("Not found: {0}")]
+    NotFound(String),
+    #[error("Migration error: {0}")]
+    Migration(String),
+}
+
+/// Handle to the local SQLite database
+///
+/// Cheap to clone; all clones share the same connection.
+#[derive(Clone)]
+pub struct Database {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl Database {
+    /// Open the database configured for the local backend
+    pub fn open(config: &LocalBackendConfig) -> Result<Self, DatabaseError> {
+        let path = match &config.database_path {
+            Some(path) => path.clone(),
+            None => Self::default_path()?,
+        };
+        Self::open_at(path)
+    }
+
+    /// Open (or create) a database file at the given path
+    pub fn open_at(path: impl AsRef<Path>) -> Result<Self, DatabaseError> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() && !parent.exists() {
+                std::fs::create_dir_all(parent)?;
+            }
+        }
+
+        log::debug!("Opening database at: {path:?}");
+        let conn = Connection::open(path)?;
+        Self::from_connection(conn)
+    }
+
+    /// Open a throwaway in-memory database
+    pub fn open_in_memory() -> Result<Self, DatabaseError> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    /// Default database location inside the storage directory
+    pub fn default_path() -> Result<PathBuf, DatabaseError> {
+        Ok(Storage::new().get_file_path(DEFAULT_DATABASE_FILE)?)
+    }
+
+    /// Prepare a connection and bring its schema up to date
+    fn from_connection(mut conn: Connection) -> Result<Self, DatabaseError> {
+        conn.pragma_update(None, "foreign_keys", "ON")?;
+        migrations::run_migrations(&mut conn)?;
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    /// Run a closure against the underlying connection
+    pub(crate) fn with_conn<T>(
+        &self,
+        f: impl FnOnce(&mut Connection) -> Result<T, DatabaseError>,
+    ) -> Result<T, DatabaseError> {
+        let mut conn = self
+            .conn
+            .lock()
+            .map_err(|e| DatabaseError::Lock(e.to_string()))?;
+        f(&mut conn)
+    }
+
+    /// Schema version currently applied to this database
+    pub fn schema_version(&self) -> Result<u32, DatabaseError> {
+        self.with_conn(|conn| migrations::current_version(conn))
+    }
+
+    pub fn characters(&self) -> CharacterRepository<'_> {
+        CharacterRepository::new(self)
+    }
+
+    pub fn scenarios(&self) -> ScenarioRepository<'_> {
+        ScenarioRepository::new(self)
+    }
+
+    pub fn stories(&self) -> StoryRepository<'_> {
+        StoryRepository::new(self)
+    }
+
+    pub fn messages(&self) -> MessageRepository<'_> {
+        MessageRepository::new(self)
+    }
+
+    pub fn personas(&self) -> PersonaRepository<'_> {
+        PersonaRepository::new(self)
+    }
+
+    pub fn entities(&self) -> EntityRepository<'_> {
+        EntityRepository::new(self)
+    }
+
+    pub fn guidance(&self) -> GuidanceRepository<'_> {
+        GuidanceRepository::new(self)
+    }
+
+    /// Check whether the database holds no user data yet
+    pub fn is_empty(&self) -> Result<bool, DatabaseError> {
+        self.with_conn(|conn| {
+            let count: i64 = conn.query_row(
+                "SELECT (SELECT COUNT(*) FROM characters)
+                      + (SELECT COUNT(*) FROM scenarios)
+                      + (SELECT COUNT(*) FROM stories)",
+                [],
+                |row| row.get(0),
+            )?;
+            Ok(count == 0)
+        })
+    }
+
+    /// Fill an empty database with the sample fixture
+    ///
+    /// Returns `true` if the fixture was written, `false` if data already existed.
+    pub fn seed_if_empty(&self) -> Result<bool, DatabaseError> {
+        if !self.is_empty()? {
+            log::trace!("Database already contains data, skipping seed");
+            return Ok(false);
+        }
+
+        log::info!("Seeding empty database with sample data");
+        for character in sample::sample_characters() {
+            self.characters().create(&character)?;
+        }
+        for scenario in sample::sample_scenarios() {
+            self.scenarios().create(&scenario)?;
+        }
+        for persona in sample::sample_personas() {
+            self.personas().create(&persona)?;
+        }
+        for story in sample::sample_stories() {
+            self.stories().create(&story)?;
+
+            let user_name = story
+                .user_character
+                .as_ref()
+                .map(|uc| uc.name.clone())
+                .unwrap_or_else(|| "You".to_string());
+            for message in sample::sample_story_messages(&user_name) {
+                let message = crate::StoryMessage {
+                    id: format!("{}_{}", story.id, message.id),
+                    ..message
+                };
+                self.messages().create(&story.id, &message)?;
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// Serialize a value into a JSON text column
+pub(crate) fn to_json<T: Serialize>(value: &T) -> Result<String, DatabaseError> {
+    serde_json::to_string(value).map_err(|e| DatabaseError::Serialization(e.to_string()))
+}
+
+/// Read a JSON text column into a value
+pub(crate) fn json_column<T: DeserializeOwned>(row: &Row, idx: usize) -> rusqlite::Result<T> {
+    let text: String = row.get(idx)?;
+    serde_json::from_str(&text)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(idx, Type::Text, Box::new(e)))
+}
+
+/// Read an optional JSON text column into a value
+pub(crate) fn optional_json_column<T: DeserializeOwned>(
+    row: &Row,
+    idx: usize,
+) -> rusqlite::Result<Option<T>> {
+    let text: Option<String> = row.get(idx)?;
+    text.map(|text| {
+        serde_json::from_str(&text)
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(idx, Type::Text, Box::new(e)))
+    })
+    .transpose()
+}
+
+/// Turn an affected-row count of zero into a `NotFound` error
+pub(crate) fn expect_affected(
+    affected: usize,
+    kind: &str,
+    id: &str,
+) -> Result<(), DatabaseError> {
+    if affected == 0 {
+        Err(DatabaseError::NotFound(format!("{kind} '{id}'")))
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seed_if_empty_only_seeds_once() {
+        let db = Database::open_in_memory().unwrap();
+        assert!(db.is_empty().unwrap());
+        assert!(db.seed_if_empty().unwrap());
+        assert!(!db.seed_if_empty().unwrap());
+
+        assert_eq!(
+            db.characters().list().unwrap().len(),
+            sample::sample_characters().len()
+        );
+        assert_eq!(
+            db.stories().list().unwrap().len(),
+            sample::sample_stories().len()
+        );
+        assert_eq!(db.messages().list_for_story("1").unwrap().len(), 7);
+    }
+
+    #[test]
+    fn test_deleting_story_removes_messages() {
+        let db = Database::open_in_memory().unwrap();
+        db.seed_if_empty().unwrap();
+
+        db.stories().delete("1").unwrap();
+        assert!(db.stories().get("1").unwrap().is_none());
+        assert!(db.messages().list_for_story("1").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_story_sampler_round_trip() {
+        let db = Database::open_in_memory().unwrap();
+        db.seed_if_empty().unwrap();
+
+        let mut story = db.stories().get("1").unwrap().unwrap();
+        assert_eq!(story.sampler, None);
+        story.sampler = Some(crate::SamplerSettings {
+            min_p: Some(0.1),
+            stop: vec!["\n\n".to_string()],
+            ..Default::default()
+        });
+        db.stories().update(&story).unwrap();
+        assert_eq!(db.stories().get("1").unwrap().unwrap().sampler, story.sampler);
+    }
+
+    #[test]
+    fn test_character_definition_round_trip() {
+        use crate::models::{Character, ResponseLength};
+
+        let db = Database::open_in_memory().unwrap();
+        db.seed_if_empty().unwrap();
+
+        // Seeded rows get defaults for the definition columns
+        let mut character = db.characters().get_definition("1").unwrap().unwrap();
+        assert!(character.personality.is_empty());
+        assert!(character.default_histories.is_empty());
+
+        character.personality = "Curious and patient".to_string();
+        character.response_style.length = ResponseLength::Long;
+        character.card_extras.insert("creator".to_string(), serde_json::json!("someone"));
+        db.characters().save_definition(&character).unwrap();
+
+        let loaded = db.characters().get_definition("1").unwrap().unwrap();
+        assert_eq!(loaded.personality, "Curious and patient");
+        assert_eq!(loaded.response_style.length, ResponseLength::Long);
+        assert_eq!(loaded.card_extras, character.card_extras);
+
+        let created = Character::new("new", "Newcomer", "Just arrived");
+        db.characters().save_definition(&created).unwrap();
+        assert_eq!(db.characters().get("new").unwrap().unwrap().name, "Newcomer");
+    }
+
+    #[test]
+    fn test_fork_and_prune_are_persisted() {
+        let db = Database::open_in_memory().unwrap();
+        db.seed_if_empty().unwrap();
+        let messages = db.messages();
+
+        // Fork after the third message and continue on a new branch
+        messages.set_cursor("1", Some("1_3")).unwrap();
+        let mut reply = crate::StoryMessage::new("alt", crate::StoryRole::Narrator, "Another path");
+        reply.created_at = Some(chrono::Utc::now());
+        messages.create("1", &reply).unwrap();
+        assert_eq!(messages.get("alt").unwrap().unwrap().created_at, reply.created_at);
+
+        let mut tree = messages.load_tree("1").unwrap();
+        assert_eq!(tree.branches().len(), 2);
+        assert_eq!(tree.active_path().len(), 4);
+
+        let removed = tree.prune_branch("alt").unwrap();
+        messages.remove_branch("1", &removed, tree.cursor()).unwrap();
+
+        let tree = messages.load_tree("1").unwrap();
+        assert_eq!(tree.branches().len(), 1);
+        assert_eq!(tree.active_path().len(), 7);
+    }
+
+    #[test]
+    fn test_story_import_is_all_or_nothing() {
+        use crate::{StoryMessage, StoryRole};
+
+        let db = Database::open_in_memory().unwrap();
+        let mut story = sample::sample_stories().remove(0);
+        story.id = "imported".to_string();
+        let first = StoryMessage::new("m1", StoryRole::Narrator, "It begins.");
+        let second = StoryMessage::new("m2", StoryRole::Narrator, "It goes on.");
+
+        // The repeated id fails the second insert, which must undo the first ones
+        let result = db.stories().create_with_messages(&story, &[first.clone(), second.clone(), first.clone()]);
+        assert!(result.is_err());
+        assert!(db.stories().get("imported").unwrap().is_none());
+        assert!(db.messages().get("m1").unwrap().is_none());
+
+        db.stories().create_with_messages(&story, &[first, second]).unwrap();
+        let tree = db.messages().load_tree("imported").unwrap();
+        assert_eq!(tree.active_path().len(), 2);
+        assert_eq!(tree.active_path()[1].content, "It goes on.");
+    }
+
+    #[test]
+    fn test_edits_are_kept_as_revisions() {
+        let db = Database::open_in_memory().unwrap();
+        db.seed_if_empty().unwrap();
+        let messages = db.messages();
+        let original = messages.get("1_1").unwrap().unwrap().content;
+
+        messages.edit("1_1", "First edit").unwrap();
+        messages.edit("1_1", "First edit").unwrap();
+        messages.edit("1_1", "Second edit").unwrap();
+
+        let revisions = messages.revisions("1_1").unwrap();
+        assert_eq!(revisions.len(), 2);
+        assert_eq!(revisions[0].content, "First edit");
+        assert_eq!(revisions[1].content, original);
+
+        // A revision can only be restored onto the message it belongs to
+        let wrong_message = messages.restore_revision("1_2", &revisions[1].id);
+        assert!(matches!(wrong_message, Err(DatabaseError::NotFound(_))));
+        assert_eq!(messages.get("1_1").unwrap().unwrap().content, "Second edit");
+
+        let restored = messages.restore_revision("1_1", &revisions[1].id).unwrap();
+        assert_eq!(restored.content, original);
+        assert_eq!(messages.get("1_1").unwrap().unwrap().content, original);
+        assert_eq!(messages.revisions("1_1").unwrap()[0].content, "Second edit");
+    }
+
+    #[test]
+    fn test_guidance_is_stored_apart_from_messages() {
+        use crate::guidance::{MessageGuidance, LOCAL_USER_ID};
+
+        let db = Database::open_in_memory().unwrap();
+        db.seed_if_empty().unwrap();
+
+        let mine = MessageGuidance::new("1_7", LOCAL_USER_ID, None, "Build suspense");
+        let theirs = MessageGuidance::new("1_7", "someone-else", None, "Make it funny");
+        db.guidance().create(&mine).unwrap();
+        db.guidance().create(&theirs).unwrap();
+
+        assert_eq!(db.guidance().list_for_message("1_7", LOCAL_USER_ID).unwrap(), vec![mine]);
+        assert_eq!(db.messages().list_for_story("1").unwrap().len(), 7);
+    }
+
+    #[test]
+    fn test_instances_keep_their_template() {
+        use crate::entity::Entity;
+
+        let db = Database::open_in_memory().unwrap();
+        let entity = Entity::new("e1", "Alice", "A tavern keeper");
+        db.entities().create(&entity).unwrap();
+
+        db.characters().save_definition(&entity.to_character("c1")).unwrap();
+        db.personas().create(&entity.to_persona("p1")).unwrap();
+
+        let character = db.characters().get_definition("c1").unwrap().unwrap();
+        let persona = db.personas().get("p1").unwrap().unwrap();
+        assert_eq!(character.template_id.as_deref(), Some("e1"));
+        assert_eq!(persona.template_id.as_deref(), Some("e1"));
+        assert_eq!(db.entities().get("e1").unwrap(), Some(entity));
+    }
+}
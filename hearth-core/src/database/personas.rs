@@ -0,0 +1,106 @@
+//! Persona repository
+
+use super::{expect_affected, json_column, to_json, Database, DatabaseError};
+use crate::persona::Persona;
+use rusqlite::{params, OptionalExtension, Row};
+
+const COLUMNS: &str = "id, name, description, avatar_url, tags, injection, locks, template_id";
+
+pub struct PersonaRepository<'a> {
+    db: &'a Database,
+}
+
+impl<'a> PersonaRepository<'a> {
+    pub(crate) fn new(db: &'a Database) -> Self {
+        Self { db }
+    }
+
+    fn from_row(row: &Row) -> rusqlite::Result<Persona> {
+        Ok(Persona {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            description: row.get(2)?,
+            avatar_url: row.get(3)?,
+            tags: json_column(row, 4)?,
+            injection: json_column(row, 5)?,
+            locks: json_column(row, 6)?,
+            template_id: row.get(7)?,
+        })
+    }
+
+    pub fn list(&self) -> Result<Vec<Persona>, DatabaseError> {
+        self.db.with_conn(|conn| {
+            let mut stmt =
+                conn.prepare(&format!("SELECT {COLUMNS} FROM personas ORDER BY name"))?;
+            let personas = stmt
+                .query_map([], Self::from_row)?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(personas)
+        })
+    }
+
+    pub fn get(&self, id: &str) -> Result<Option<Persona>, DatabaseError> {
+        self.db.with_conn(|conn| {
+            Ok(conn
+                .query_row(
+                    &format!("SELECT {COLUMNS} FROM personas WHERE id = ?1"),
+                    [id],
+                    Self::from_row,
+                )
+                .optional()?)
+        })
+    }
+
+    pub fn create(&self, persona: &Persona) -> Result<(), DatabaseError> {
+        let tags = to_json(&persona.tags)?;
+        let injection = to_json(&persona.injection)?;
+        let locks = to_json(&persona.locks)?;
+        self.db.with_conn(|conn| {
+            conn.execute(
+                &format!("INSERT INTO personas ({COLUMNS}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)"),
+                params![
+                    persona.id,
+                    persona.name,
+                    persona.description,
+                    persona.avatar_url,
+                    tags,
+                    injection,
+                    locks,
+                    persona.template_id,
+                ],
+            )?;
+            Ok(())
+        })
+    }
+
+    pub fn update(&self, persona: &Persona) -> Result<(), DatabaseError> {
+        let tags = to_json(&persona.tags)?;
+        let injection = to_json(&persona.injection)?;
+        let locks = to_json(&persona.locks)?;
+        self.db.with_conn(|conn| {
+            let affected = conn.execute(
+                "UPDATE personas SET name = ?2, description = ?3, avatar_url = ?4, tags = ?5,
+                    injection = ?6, locks = ?7, template_id = ?8
+                 WHERE id = ?1",
+                params![
+                    persona.id,
+                    persona.name,
+                    persona.description,
+                    persona.avatar_url,
+                    tags,
+                    injection,
+                    locks,
+                    persona.template_id,
+                ],
+            )?;
+            expect_affected(affected, "Persona", &persona.id)
+        })
+    }
+
+    pub fn delete(&self, id: &str) -> Result<(), DatabaseError> {
+        self.db.with_conn(|conn| {
+            let affected = conn.execute("DELETE FROM personas WHERE id = ?1", [id])?;
+            expect_affected(affected, "Persona", id)
+        })
+    }
+}
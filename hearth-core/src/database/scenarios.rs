@@ -0,0 +1,102 @@
+//! Scenario repository
+
+use super::{expect_affected, json_column, to_json, Database, DatabaseError};
+use crate::models::ScenarioItem;
+use rusqlite::{params, OptionalExtension, Row};
+
+const COLUMNS: &str = "id, name, description, avatar_url, tags, is_favorite, last_used, story_count";
+
+pub struct ScenarioRepository<'a> {
+    db: &'a Database,
+}
+
+impl<'a> ScenarioRepository<'a> {
+    pub(crate) fn new(db: &'a Database) -> Self {
+        Self { db }
+    }
+
+    fn from_row(row: &Row) -> rusqlite::Result<ScenarioItem> {
+        Ok(ScenarioItem {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            description: row.get(2)?,
+            avatar_url: row.get(3)?,
+            tags: json_column(row, 4)?,
+            is_favorite: row.get(5)?,
+            last_used: row.get(6)?,
+            story_count: row.get(7)?,
+        })
+    }
+
+    pub fn list(&self) -> Result<Vec<ScenarioItem>, DatabaseError> {
+        self.db.with_conn(|conn| {
+            let mut stmt =
+                conn.prepare(&format!("SELECT {COLUMNS} FROM scenarios ORDER BY name"))?;
+            let scenarios = stmt
+                .query_map([], Self::from_row)?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(scenarios)
+        })
+    }
+
+    pub fn get(&self, id: &str) -> Result<Option<ScenarioItem>, DatabaseError> {
+        self.db.with_conn(|conn| {
+            Ok(conn
+                .query_row(
+                    &format!("SELECT {COLUMNS} FROM scenarios WHERE id = ?1"),
+                    [id],
+                    Self::from_row,
+                )
+                .optional()?)
+        })
+    }
+
+    pub fn create(&self, scenario: &ScenarioItem) -> Result<(), DatabaseError> {
+        let tags = to_json(&scenario.tags)?;
+        self.db.with_conn(|conn| {
+            conn.execute(
+                &format!("INSERT INTO scenarios ({COLUMNS}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)"),
+                params![
+                    scenario.id,
+                    scenario.name,
+                    scenario.description,
+                    scenario.avatar_url,
+                    tags,
+                    scenario.is_favorite,
+                    scenario.last_used,
+                    scenario.story_count,
+                ],
+            )?;
+            Ok(())
+        })
+    }
+
+    pub fn update(&self, scenario: &ScenarioItem) -> Result<(), DatabaseError> {
+        let tags = to_json(&scenario.tags)?;
+        self.db.with_conn(|conn| {
+            let affected = conn.execute(
+                "UPDATE scenarios SET name = ?2, description = ?3, avatar_url = ?4, tags = ?5,
+                    is_favorite = ?6, last_used = ?7, story_count = ?8
+                 WHERE id = ?1",
+                params![
+                    scenario.id,
+                    scenario.name,
+                    scenario.description,
+                    scenario.avatar_url,
+                    tags,
+                    scenario.is_favorite,
+                    scenario.last_used,
+                    scenario.story_count,
+                ],
+            )?;
+            expect_affected(affected, "Scenario", &scenario.id)
+        })
+    }
+
+    pub fn delete(&self, id: &str) -> Result<(), DatabaseError> {
+        self.db.with_conn(|conn| {
+            let affected = conn.execute("DELETE FROM scenarios WHERE id = ?1", [id])?;
+            expect_affected(affected, "Scenario", id)
+        })
+    }
+}
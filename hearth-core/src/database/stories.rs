@@ -0,0 +1,130 @@
+//! Story repository
+
+use super::messages::insert_message;
+use super::{expect_affected, json_column, optional_json_column, to_json, Database, DatabaseError};
+use crate::models::{StoryItem, StoryMessage};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+
+const COLUMNS: &str = "id, title, characters, user_character, last_message, last_speaker, \
+                       timestamp, scenario_name, message_count, sampler";
+
+pub struct StoryRepository<'a> {
+    db: &'a Database,
+}
+
+impl<'a> StoryRepository<'a> {
+    pub(crate) fn new(db: &'a Database) -> Self {
+        Self { db }
+    }
+
+    fn from_row(row: &Row) -> rusqlite::Result<StoryItem> {
+        Ok(StoryItem {
+            id: row.get(0)?,
+            title: row.get(1)?,
+            characters: json_column(row, 2)?,
+            user_character: optional_json_column(row, 3)?,
+            last_message: row.get(4)?,
+            last_speaker: row.get(5)?,
+            timestamp: row.get(6)?,
+            scenario_name: row.get(7)?,
+            message_count: row.get(8)?,
+            sampler: optional_json_column(row, 9)?,
+        })
+    }
+
+    pub fn list(&self) -> Result<Vec<StoryItem>, DatabaseError> {
+        self.db.with_conn(|conn| {
+            let mut stmt = conn.prepare(&format!("SELECT {COLUMNS} FROM stories ORDER BY rowid"))?;
+            let stories = stmt
+                .query_map([], Self::from_row)?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(stories)
+        })
+    }
+
+    pub fn get(&self, id: &str) -> Result<Option<StoryItem>, DatabaseError> {
+        self.db.with_conn(|conn| {
+            Ok(conn
+                .query_row(
+                    &format!("SELECT {COLUMNS} FROM stories WHERE id = ?1"),
+                    [id],
+                    Self::from_row,
+                )
+                .optional()?)
+        })
+    }
+
+    pub fn create(&self, story: &StoryItem) -> Result<(), DatabaseError> {
+        self.db.with_conn(|conn| insert_story(conn, story))
+    }
+
+    /// Create a story together with its messages, or nothing at all if any of them fails
+    pub fn create_with_messages(&self, story: &StoryItem, messages: &[StoryMessage]) -> Result<(), DatabaseError> {
+        self.db.with_conn(|conn| {
+            let tx = conn.transaction()?;
+            insert_story(&tx, story)?;
+            for message in messages {
+                insert_message(&tx, &story.id, message)?;
+            }
+            tx.commit()?;
+            Ok(())
+        })
+    }
+
+    pub fn update(&self, story: &StoryItem) -> Result<(), DatabaseError> {
+        let characters = to_json(&story.characters)?;
+        let user_character = story.user_character.as_ref().map(to_json).transpose()?;
+        let sampler = story.sampler.as_ref().map(to_json).transpose()?;
+        self.db.with_conn(|conn| {
+            let affected = conn.execute(
+                "UPDATE stories SET title = ?2, characters = ?3, user_character = ?4,
+                    last_message = ?5, last_speaker = ?6, timestamp = ?7, scenario_name = ?8,
+                    message_count = ?9, sampler = ?10
+                 WHERE id = ?1",
+                params![
+                    story.id,
+                    story.title,
+                    characters,
+                    user_character,
+                    story.last_message,
+                    story.last_speaker,
+                    story.timestamp,
+                    story.scenario_name,
+                    story.message_count,
+                    sampler,
+                ],
+            )?;
+            expect_affected(affected, "Story", &story.id)
+        })
+    }
+
+    /// Delete a story together with all of its messages
+    pub fn delete(&self, id: &str) -> Result<(), DatabaseError> {
+        self.db.with_conn(|conn| {
+            let affected = conn.execute("DELETE FROM stories WHERE id = ?1", [id])?;
+            expect_affected(affected, "Story", id)
+        })
+    }
+}
+
+fn insert_story(conn: &Connection, story: &StoryItem) -> Result<(), DatabaseError> {
+    let characters = to_json(&story.characters)?;
+    let user_character = story.user_character.as_ref().map(to_json).transpose()?;
+    let sampler = story.sampler.as_ref().map(to_json).transpose()?;
+    conn.execute(
+        &format!("INSERT INTO stories ({COLUMNS}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)"),
+        params![
+            story.id,
+            story.title,
+            characters,
+            user_character,
+            story.last_message,
+            story.last_speaker,
+            story.timestamp,
+            story.scenario_name,
+            story.message_count,
+            sampler,
+        ],
+    )?;
+    Ok(())
+}
@@ -0,0 +1,394 @@
+//! Structured character definition formats
+//!
+//! W++ writes traits as `[Character("Alice") { Species("Human") Likes("Tea" + "Rain") }]`
+//! blocks; Ali:Chat writes example dialogue as `<START>` separated exchanges of
+//! `{{user}}:` and `{{char}}:` lines. Both parse into structures the character
+//! editor can show as fields, and print back to text.
+
+use std::fmt;
+use thiserror::Error;
+
+/// How a definition field is written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DefinitionFormat {
+    PlainText,
+    WPlusPlus,
+    AliChat,
+}
+
+impl DefinitionFormat {
+    /// The format of `text`, falling back to plain text unless it parses as a structured one
+    pub fn detect(text: &str) -> Self {
+        if text.trim_start().starts_with('[') && parse_wpp(text).is_ok_and(|blocks| !blocks.is_empty()) {
+            Self::WPlusPlus
+        } else if parse_ali_chat(text).is_some() {
+            Self::AliChat
+        } else {
+            Self::PlainText
+        }
+    }
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("{message} at line {line}, column {column}")]
+pub struct WppError {
+    pub message: String,
+    /// Byte offset into the definition
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl WppError {
+    fn at(text: &str, offset: usize, message: impl Into<String>) -> Self {
+        let before = &text[..offset];
+        Self {
+            message: message.into(),
+            offset,
+            line: before.matches('\n').count() + 1,
+            column: before.rsplit('\n').next().unwrap_or_default().chars().count() + 1,
+        }
+    }
+}
+
+/// One `[Kind("Name") { ... }]` block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WppBlock {
+    /// What the block describes, usually `Character`
+    pub kind: String,
+    pub name: String,
+    pub attributes: Vec<WppAttribute>,
+}
+
+/// A `Name("value" + "value")` attribute
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WppAttribute {
+    pub name: String,
+    pub values: Vec<String>,
+}
+
+impl WppBlock {
+    pub fn new(kind: impl Into<String>, name: impl Into<String>) -> Self {
+        Self {
+            kind: kind.into(),
+            name: name.into(),
+            attributes: Vec::new(),
+        }
+    }
+
+    /// Values of an attribute, matching its name case-insensitively
+    pub fn get(&self, name: &str) -> Option<&[String]> {
+        self.attributes
+            .iter()
+            .find(|attribute| attribute.name.eq_ignore_ascii_case(name))
+            .map(|attribute| attribute.values.as_slice())
+    }
+
+    /// Replace the values of an attribute, adding it at the end if it is new
+    pub fn set(&mut self, name: &str, values: Vec<String>) {
+        match self.attributes.iter_mut().find(|a| a.name.eq_ignore_ascii_case(name)) {
+            Some(attribute) => attribute.values = values,
+            None => self.attributes.push(WppAttribute {
+                name: name.to_string(),
+                values,
+            }),
+        }
+    }
+}
+
+impl fmt::Display for WppBlock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "[{}({}) {{", self.kind, quote(&self.name))?;
+        for attribute in &self.attributes {
+            let values: Vec<String> = attribute.values.iter().map(|v| quote(v)).collect();
+            writeln!(f, "  {}({})", attribute.name, values.join(" + "))?;
+        }
+        write!(f, "}}]")
+    }
+}
+
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Parse the W++ blocks of a definition
+pub fn parse_wpp(text: &str) -> Result<Vec<WppBlock>, WppError> {
+    let mut parser = Parser { text, pos: 0 };
+    let mut blocks = Vec::new();
+    parser.skip_whitespace();
+    while parser.peek().is_some() {
+        blocks.push(parser.block()?);
+        parser.skip_whitespace();
+    }
+    Ok(blocks)
+}
+
+/// W++ text for blocks, each starting on a new line
+pub fn print_wpp(blocks: &[WppBlock]) -> String {
+    blocks.iter().map(WppBlock::to_string).collect::<Vec<_>>().join("\n")
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.text[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.bump();
+        }
+    }
+
+    fn error(&self, expected: &str) -> WppError {
+        let found = match self.peek() {
+            Some(c) => format!("'{c}'"),
+            None => "the end".to_string(),
+        };
+        WppError::at(self.text, self.pos, format!("Expected {expected} but found {found}"))
+    }
+
+    /// Skip whitespace and consume `c` if it comes next
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        let found = self.peek() == Some(c);
+        if found {
+            self.bump();
+        }
+        found
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), WppError> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("'{c}'")))
+        }
+    }
+
+    fn block(&mut self) -> Result<WppBlock, WppError> {
+        self.expect('[')?;
+        let kind = self.identifier()?;
+        self.expect('(')?;
+        let name = self.string()?;
+        self.expect(')')?;
+        self.expect('{')?;
+        let mut block = WppBlock::new(kind, name);
+        while !self.eat('}') {
+            let name = self.identifier()?;
+            let values = self.values()?;
+            block.attributes.push(WppAttribute { name, values });
+        }
+        self.expect(']')?;
+        Ok(block)
+    }
+
+    fn identifier(&mut self) -> Result<String, WppError> {
+        self.skip_whitespace();
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '-') {
+            self.bump();
+        }
+        if self.pos == start {
+            return Err(self.error("a name"));
+        }
+        Ok(self.text[start..self.pos].to_string())
+    }
+
+    fn values(&mut self) -> Result<Vec<String>, WppError> {
+        self.expect('(')?;
+        let mut values = Vec::new();
+        if self.eat(')') {
+            return Ok(values);
+        }
+        loop {
+            values.push(self.string()?);
+            if !self.eat('+') {
+                self.expect(')')?;
+                return Ok(values);
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, WppError> {
+        self.skip_whitespace();
+        let start = self.pos;
+        if !self.eat('"') {
+            return Err(self.error("'\"'"));
+        }
+        let mut value = String::new();
+        while let Some(c) = self.bump() {
+            match c {
+                '"' => return Ok(value),
+                '\\' => value.extend(self.bump()),
+                c => value.push(c),
+            }
+        }
+        Err(WppError::at(self.text, start, "Unterminated string"))
+    }
+}
+
+/// Who speaks an Ali:Chat line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AliChatSpeaker {
+    User,
+    Character,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AliChatLine {
+    pub speaker: AliChatSpeaker,
+    pub text: String,
+}
+
+/// One `<START>` separated example exchange
+pub type AliChatBlock = Vec<AliChatLine>;
+
+/// Split Ali:Chat example dialogue into exchanges
+///
+/// Returns `None` when the text has lines outside any `{{user}}:` or
+/// `{{char}}:` message, or when the character never speaks.
+pub fn parse_ali_chat(text: &str) -> Option<Vec<AliChatBlock>> {
+    let mut blocks: Vec<AliChatBlock> = Vec::new();
+    let mut current: Option<AliChatBlock> = None;
+    for line in text.lines() {
+        if line.trim().eq_ignore_ascii_case("<START>") {
+            blocks.extend(current.replace(Vec::new()));
+            continue;
+        }
+        if let Some((speaker, rest)) = speaker_prefix(line) {
+            let block = current.get_or_insert_with(Vec::new);
+            block.push(AliChatLine {
+                speaker,
+                text: rest.trim_start().to_string(),
+            });
+            continue;
+        }
+        match current.as_mut().and_then(|block| block.last_mut()) {
+            // Messages can run over several lines
+            Some(last) => {
+                last.text.push('\n');
+                last.text.push_str(line);
+            }
+            None if line.trim().is_empty() => {}
+            None => return None,
+        }
+    }
+    blocks.extend(current);
+    for line in blocks.iter_mut().flatten() {
+        line.text.truncate(line.text.trim_end().len());
+    }
+    blocks.retain(|block| !block.is_empty());
+    let character_speaks = blocks.iter().flatten().any(|line| line.speaker == AliChatSpeaker::Character);
+    character_speaks.then_some(blocks)
+}
+
+fn speaker_prefix(line: &str) -> Option<(AliChatSpeaker, &str)> {
+    let (speaker, rest) = line.split_once(':')?;
+    match speaker.trim() {
+        "{{user}}" | "<USER>" => Some((AliChatSpeaker::User, rest)),
+        "{{char}}" | "<BOT>" => Some((AliChatSpeaker::Character, rest)),
+        _ => None,
+    }
+}
+
+/// Ali:Chat text for example exchanges
+pub fn print_ali_chat(blocks: &[AliChatBlock]) -> String {
+    let mut text = String::new();
+    for block in blocks {
+        text.push_str("<START>\n");
+        for line in block {
+            let speaker = match line.speaker {
+                AliChatSpeaker::User => "{{user}}",
+                AliChatSpeaker::Character => "{{char}}",
+            };
+            text.push_str(&format!("{speaker}: {}\n", line.text));
+        }
+    }
+    text.truncate(text.trim_end().len());
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    const ALICE: &str = r#"[Character("Alice") {
+    Species("Human")
+    Age("25")
+    Personality("Cheerful" + "Curious" + "Intelligent")
+    Likes("Reading" + "Coffee" + "Rainy days")
+    Secrets()
+}]"#;
+
+    #[test]
+    fn test_parses_design_example() {
+        let blocks = parse_wpp(ALICE).unwrap();
+        assert_eq!(blocks.len(), 1);
+        let alice = &blocks[0];
+        assert_eq!((alice.kind.as_str(), alice.name.as_str()), ("Character", "Alice"));
+        assert_eq!(alice.get("personality").unwrap(), ["Cheerful", "Curious", "Intelligent"]);
+        assert_eq!(alice.get("Secrets").unwrap(), [] as [String; 0]);
+        assert_eq!(parse_wpp(&print_wpp(&blocks)).unwrap(), blocks);
+        assert_eq!(DefinitionFormat::detect(ALICE), DefinitionFormat::WPlusPlus);
+    }
+
+    #[test]
+    fn test_reports_error_positions() {
+        let error = parse_wpp("[Character(\"Alice\") {\n  Species(\"Human\" \"Elf\")\n}]").unwrap_err();
+        assert_eq!((error.line, error.column), (2, 19));
+        assert_eq!(error.to_string(), "Expected ')' but found '\"' at line 2, column 19");
+
+        let error = parse_wpp("[Character(\"Alice) {}]").unwrap_err();
+        assert_eq!((error.line, error.column, error.message.as_str()), (1, 12, "Unterminated string"));
+
+        let error = parse_wpp("[Character(\"Alice\") {").unwrap_err();
+        assert_eq!(error.message, "Expected a name but found the end");
+        assert_eq!(DefinitionFormat::detect("[Character(\"Alice\") {"), DefinitionFormat::PlainText);
+    }
+
+    #[test]
+    fn test_detects_ali_chat_examples() {
+        let examples = "<START>\n{{user}}: Who are you?\n{{char}}: *She bows.* A friend.\nNothing more.\n\n\
+            <START>\n{{char}}: Hello again.";
+        let blocks = parse_ali_chat(examples).unwrap();
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0][1].text, "*She bows.* A friend.\nNothing more.");
+        assert_eq!(blocks[1][0].speaker, AliChatSpeaker::Character);
+        assert_eq!(parse_ali_chat(&print_ali_chat(&blocks)).unwrap(), blocks);
+        assert_eq!(DefinitionFormat::detect(examples), DefinitionFormat::AliChat);
+
+        assert_eq!(DefinitionFormat::detect("A guardian of the forest.\n{{char}}: Hi"), DefinitionFormat::PlainText);
+        assert!(parse_ali_chat("{{user}}: Anyone there?").is_none());
+    }
+
+    fn wpp_block() -> impl Strategy<Value = WppBlock> {
+        let attribute = ("[A-Za-z_][A-Za-z0-9_-]{0,10}", prop::collection::vec(".{0,12}", 0..4))
+            .prop_map(|(name, values)| WppAttribute { name, values });
+        ("[A-Z][a-z]{0,8}", ".{0,12}", prop::collection::vec(attribute, 0..5)).prop_map(|(kind, name, attributes)| {
+            WppBlock {
+                kind,
+                name,
+                attributes,
+            }
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn test_printed_wpp_parses_back(blocks in prop::collection::vec(wpp_block(), 0..3)) {
+            prop_assert_eq!(parse_wpp(&print_wpp(&blocks)).unwrap(), blocks);
+        }
+    }
+}
@@ -0,0 +1,211 @@
+//! Shared entity library
+//!
+//! An entity is a roleplay identity that is not yet bound to a role. It can be
+//! instantiated as a character or a persona; the copy remembers which entity it
+//! came from so it can be compared against the template later.
+
+use crate::models::{validate_name, validate_tags, Character, ValidationError};
+use crate::persona::Persona;
+use serde::{Deserialize, Serialize};
+
+// Entity - a reusable template for characters and personas
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Entity {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    #[serde(default)]
+    pub personality: String,
+    pub avatar_url: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// How the description is written
+    #[serde(default)]
+    pub format: EntityFormat,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EntityFormat {
+    #[default]
+    PlainText,
+    /// `[Character("Name") { Trait("Value") }]` style descriptions
+    WPlusPlus,
+    Structured,
+}
+
+/// A single field that differs between an entity and a copy made from it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldChange {
+    pub field: &'static str,
+    pub template: String,
+    pub current: String,
+}
+
+impl Entity {
+    pub fn new(id: impl Into<String>, name: impl Into<String>, description: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            name: name.into(),
+            description: description.into(),
+            personality: String::new(),
+            avatar_url: None,
+            tags: Vec::new(),
+            format: EntityFormat::default(),
+        }
+    }
+
+    /// Check the entity before it is saved
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        if self.id.trim().is_empty() {
+            return Err(ValidationError::Empty("Entity id"));
+        }
+        validate_name("Entity name", &self.name)?;
+        validate_tags(&self.tags)
+    }
+
+    /// Case-insensitive match against name, description and tags
+    pub fn matches(&self, query: &str) -> bool {
+        let query = query.trim().to_lowercase();
+        query.is_empty()
+            || self.name.to_lowercase().contains(&query)
+            || self.description.to_lowercase().contains(&query)
+            || self.tags.iter().any(|tag| tag.to_lowercase().contains(&query))
+    }
+
+    /// Create a character from this template
+    pub fn to_character(&self, id: impl Into<String>) -> Character {
+        Character {
+            personality: self.personality.clone(),
+            avatar_url: self.avatar_url.clone(),
+            tags: self.tags.clone(),
+            template_id: Some(self.id.clone()),
+            ..Character::new(id, self.name.clone(), self.description.clone())
+        }
+    }
+
+    /// Create a persona from this template
+    ///
+    /// Personas have no separate personality field, so it is appended to the description.
+    pub fn to_persona(&self, id: impl Into<String>) -> Persona {
+        Persona {
+            avatar_url: self.avatar_url.clone(),
+            tags: self.tags.clone(),
+            template_id: Some(self.id.clone()),
+            ..Persona::new(id, self.name.clone(), self.persona_description())
+        }
+    }
+
+    /// Turn an existing character into a template
+    pub fn from_character(id: impl Into<String>, character: &Character) -> Self {
+        Self {
+            personality: character.personality.clone(),
+            avatar_url: character.avatar_url.clone(),
+            tags: character.tags.clone(),
+            ..Self::new(id, character.name.clone(), character.description.clone())
+        }
+    }
+
+    /// Turn an existing persona into a template
+    pub fn from_persona(id: impl Into<String>, persona: &Persona) -> Self {
+        Self {
+            avatar_url: persona.avatar_url.clone(),
+            tags: persona.tags.clone(),
+            ..Self::new(id, persona.name.clone(), persona.description.clone())
+        }
+    }
+
+    /// Fields of a character that no longer match this template
+    pub fn diff_character(&self, character: &Character) -> Vec<FieldChange> {
+        let mut changes = self.diff_common(&character.name, &character.avatar_url, &character.tags);
+        push_change(&mut changes, "description", &self.description, &character.description);
+        push_change(&mut changes, "personality", &self.personality, &character.personality);
+        changes
+    }
+
+    /// Fields of a persona that no longer match this template
+    pub fn diff_persona(&self, persona: &Persona) -> Vec<FieldChange> {
+        let mut changes = self.diff_common(&persona.name, &persona.avatar_url, &persona.tags);
+        push_change(&mut changes, "description", &self.persona_description(), &persona.description);
+        changes
+    }
+
+    fn diff_common(&self, name: &str, avatar_url: &Option<String>, tags: &[String]) -> Vec<FieldChange> {
+        let mut changes = Vec::new();
+        push_change(&mut changes, "name", &self.name, name);
+        push_change(
+            &mut changes,
+            "avatar_url",
+            self.avatar_url.as_deref().unwrap_or_default(),
+            avatar_url.as_deref().unwrap_or_default(),
+        );
+        push_change(&mut changes, "tags", &self.tags.join(", "), &tags.join(", "));
+        changes
+    }
+
+    fn persona_description(&self) -> String {
+        if self.personality.trim().is_empty() {
+            self.description.clone()
+        } else {
+            format!("{}\n\nPersonality: {}", self.description, self.personality)
+        }
+    }
+}
+
+fn push_change(changes: &mut Vec<FieldChange>, field: &'static str, template: &str, current: &str) {
+    if template != current {
+        changes.push(FieldChange {
+            field,
+            template: template.to_string(),
+            current: current.to_string(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template() -> Entity {
+        Entity {
+            personality: "Cheerful".to_string(),
+            tags: vec!["Fantasy".to_string()],
+            ..Entity::new("e1", "Alice", "A tavern keeper")
+        }
+    }
+
+    #[test]
+    fn test_instances_track_template_and_start_without_changes() {
+        let entity = template();
+
+        let character = entity.to_character("c1");
+        assert_eq!(character.template_id.as_deref(), Some("e1"));
+        assert!(entity.diff_character(&character).is_empty());
+
+        let persona = entity.to_persona("p1");
+        assert_eq!(persona.template_id.as_deref(), Some("e1"));
+        assert!(entity.diff_persona(&persona).is_empty());
+    }
+
+    #[test]
+    fn test_diff_reports_changed_fields() {
+        let entity = template();
+        let mut character = entity.to_character("c1");
+        character.name = "Alicia".to_string();
+        character.tags.push("Mysterious".to_string());
+
+        let changes = entity.diff_character(&character);
+        let fields: Vec<_> = changes.iter().map(|c| c.field).collect();
+        assert_eq!(fields, vec!["name", "tags"]);
+        assert_eq!(changes[0].template, "Alice");
+        assert_eq!(changes[0].current, "Alicia");
+    }
+
+    #[test]
+    fn test_character_can_be_reused_as_persona() {
+        let character = template().to_character("c1");
+        let entity = Entity::from_character("e2", &character);
+        let persona = entity.to_persona("p1");
+        assert_eq!(persona.name, "Alice");
+        assert!(persona.description.contains("Cheerful"));
+    }
+}
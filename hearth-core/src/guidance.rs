@@ -0,0 +1,112 @@
+//! Per-message story guidance
+//!
+//! Guidance is a hidden instruction a user attaches to a message they send, e.g.
+//! "the guide should notice something is wrong". It is stored apart from the
+//! conversation, so it never shows up in the story or in exports, and it only
+//! steers the reply to the message it is attached to.
+
+use crate::models::StoryMessage;
+use serde::{Deserialize, Serialize};
+
+/// Author id used for guidance written on this device
+pub const LOCAL_USER_ID: &str = "local";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MessageGuidance {
+    pub id: String,
+    /// Message the guidance was sent with
+    pub message_id: String,
+    /// User who wrote the guidance; nobody else gets to see it
+    pub author_id: String,
+    /// Character the guidance is about, or `None` for the story as a whole
+    pub character: Option<String>,
+    pub text: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl MessageGuidance {
+    pub fn new(
+        message_id: impl Into<String>,
+        author_id: impl Into<String>,
+        character: Option<String>,
+        text: impl Into<String>,
+    ) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            message_id: message_id.into(),
+            author_id: author_id.into(),
+            character,
+            text: text.into(),
+            created_at: chrono::Utc::now(),
+        }
+    }
+
+    pub fn is_visible_to(&self, user_id: &str) -> bool {
+        self.author_id == user_id
+    }
+
+    /// System instruction injected before the next reply
+    pub fn to_system_instruction(&self) -> String {
+        match &self.character {
+            Some(character) => format!("[Guidance for {character}: {}]", self.text.trim()),
+            None => format!("[Story guidance: {}]", self.text.trim()),
+        }
+    }
+}
+
+/// Guidance that applies to the reply after the last message of `path`
+///
+/// Only guidance attached to that message counts, so every piece of guidance
+/// steers exactly one generation and is ignored once the story moves on.
+pub fn guidance_for_next_generation<'a>(
+    path: &[StoryMessage],
+    guidance: &'a [MessageGuidance],
+) -> Vec<&'a MessageGuidance> {
+    let Some(last) = path.last() else {
+        return Vec::new();
+    };
+    guidance
+        .iter()
+        .filter(|g| g.message_id == last.id && !g.text.trim().is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::StoryRole;
+
+    #[test]
+    fn test_guidance_only_applies_to_the_next_reply() {
+        let user = StoryRole::User {
+            name: "Alex".to_string(),
+        };
+        let mut path = vec![
+            StoryMessage::new("1", user.clone(), "Hello"),
+            StoryMessage::new("2", StoryRole::Narrator, "The wind howls"),
+            StoryMessage::new("3", user, "I look around"),
+        ];
+        let guidance = vec![
+            MessageGuidance::new("1", LOCAL_USER_ID, None, "Old guidance"),
+            MessageGuidance::new("3", LOCAL_USER_ID, Some("Guide".to_string()), "Notice the stranger"),
+            MessageGuidance::new("3", LOCAL_USER_ID, None, "   "),
+        ];
+
+        let next = guidance_for_next_generation(&path, &guidance);
+        assert_eq!(next.len(), 1);
+        assert_eq!(
+            next[0].to_system_instruction(),
+            "[Guidance for Guide: Notice the stranger]"
+        );
+
+        path.push(StoryMessage::new("4", StoryRole::Narrator, "A stranger appears"));
+        assert!(guidance_for_next_generation(&path, &guidance).is_empty());
+    }
+
+    #[test]
+    fn test_guidance_is_private_to_its_author() {
+        let guidance = MessageGuidance::new("1", "alice", None, "Keep it tense");
+        assert!(guidance.is_visible_to("alice"));
+        assert!(!guidance.is_visible_to("bob"));
+    }
+}
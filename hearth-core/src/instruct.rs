@@ -0,0 +1,340 @@
+//! Instruct templates for text completion backends
+//!
+//! Chat APIs format the conversation themselves, but raw completion endpoints
+//! (Ollama in raw mode, llama.cpp or KoboldCpp servers) expect the prompt as one
+//! string in the format the model was tuned on. A template wraps each message in
+//! the model's role markers and lists the sequences that end a reply.
+
+use crate::llm::{ChatMessage, ChatRole};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum InstructError {
+    #[error("Invalid template: {0}")]
+    Invalid(String),
+    #[error("Template has no name")]
+    MissingName,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InstructTemplate {
+    pub name: String,
+    pub system_prefix: String,
+    pub system_suffix: String,
+    pub user_prefix: String,
+    pub user_suffix: String,
+    pub assistant_prefix: String,
+    pub assistant_suffix: String,
+    /// Generation stops at any of these
+    pub stop_sequences: Vec<String>,
+}
+
+/// Instruct preset as exported by SillyTavern
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+struct SillyTavernTemplate {
+    name: String,
+    system_sequence: String,
+    system_suffix: String,
+    input_sequence: String,
+    input_suffix: String,
+    output_sequence: String,
+    output_suffix: String,
+    stop_sequence: String,
+    /// Puts a newline between each sequence and the message
+    wrap: bool,
+}
+
+impl Default for SillyTavernTemplate {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            system_sequence: String::new(),
+            system_suffix: String::new(),
+            input_sequence: String::new(),
+            input_suffix: String::new(),
+            output_sequence: String::new(),
+            output_suffix: String::new(),
+            stop_sequence: String::new(),
+            wrap: true,
+        }
+    }
+}
+
+impl From<SillyTavernTemplate> for InstructTemplate {
+    fn from(preset: SillyTavernTemplate) -> Self {
+        let prefix = |sequence: String| {
+            if preset.wrap && !sequence.is_empty() {
+                format!("{sequence}\n")
+            } else {
+                sequence
+            }
+        };
+        Self {
+            name: preset.name.clone(),
+            system_prefix: prefix(preset.system_sequence),
+            system_suffix: preset.system_suffix,
+            user_prefix: prefix(preset.input_sequence),
+            user_suffix: preset.input_suffix,
+            assistant_prefix: prefix(preset.output_sequence),
+            assistant_suffix: preset.output_suffix,
+            stop_sequences: preset
+                .stop_sequence
+                .split(',')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(str::to_string)
+                .collect(),
+        }
+    }
+}
+
+impl InstructTemplate {
+    /// Render the conversation as one prompt, ending where the reply should begin
+    pub fn format(&self, messages: &[ChatMessage]) -> String {
+        let mut prompt = String::new();
+        for message in messages {
+            let (prefix, suffix) = self.wrappers(message.role);
+            prompt.push_str(prefix);
+            prompt.push_str(&message.content);
+            prompt.push_str(suffix);
+        }
+        prompt.push_str(&self.assistant_prefix);
+        prompt
+    }
+
+    fn wrappers(&self, role: ChatRole) -> (&str, &str) {
+        match role {
+            ChatRole::System => (&self.system_prefix, &self.system_suffix),
+            ChatRole::User => (&self.user_prefix, &self.user_suffix),
+            ChatRole::Assistant => (&self.assistant_prefix, &self.assistant_suffix),
+        }
+    }
+
+    /// Parse a template exported from Hearth or a SillyTavern instruct preset
+    pub fn import(json: &str) -> Result<Self, InstructError> {
+        let value: serde_json::Value =
+            serde_json::from_str(json).map_err(|e| InstructError::Invalid(e.to_string()))?;
+        let is_sillytavern = value.get("input_sequence").is_some();
+        let template: Self = if is_sillytavern {
+            serde_json::from_value::<SillyTavernTemplate>(value).map(Self::from)
+        } else {
+            serde_json::from_value(value)
+        }
+        .map_err(|e| InstructError::Invalid(e.to_string()))?;
+
+        if template.name.trim().is_empty() {
+            return Err(InstructError::MissingName);
+        }
+        Ok(template)
+    }
+
+    pub fn export(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    pub fn chatml() -> Self {
+        Self {
+            name: "ChatML".to_string(),
+            system_prefix: "<|im_start|>system\n".to_string(),
+            system_suffix: "<|im_end|>\n".to_string(),
+            user_prefix: "<|im_start|>user\n".to_string(),
+            user_suffix: "<|im_end|>\n".to_string(),
+            assistant_prefix: "<|im_start|>assistant\n".to_string(),
+            assistant_suffix: "<|im_end|>\n".to_string(),
+            stop_sequences: vec!["<|im_end|>".to_string()],
+        }
+    }
+
+    pub fn llama3() -> Self {
+        let header = |role: &str| format!("<|start_header_id|>{role}<|end_header_id|>\n\n");
+        Self {
+            name: "Llama 3".to_string(),
+            system_prefix: header("system"),
+            system_suffix: "<|eot_id|>".to_string(),
+            user_prefix: header("user"),
+            user_suffix: "<|eot_id|>".to_string(),
+            assistant_prefix: header("assistant"),
+            assistant_suffix: "<|eot_id|>".to_string(),
+            stop_sequences: vec!["<|eot_id|>".to_string()],
+        }
+    }
+
+    /// Mistral has no system role, so system messages become instructions
+    pub fn mistral() -> Self {
+        Self {
+            name: "Mistral".to_string(),
+            system_prefix: "[INST] ".to_string(),
+            system_suffix: " [/INST]".to_string(),
+            user_prefix: "[INST] ".to_string(),
+            user_suffix: " [/INST]".to_string(),
+            assistant_prefix: String::new(),
+            assistant_suffix: "</s>".to_string(),
+            stop_sequences: vec!["</s>".to_string(), "[INST]".to_string()],
+        }
+    }
+
+    pub fn alpaca() -> Self {
+        Self {
+            name: "Alpaca".to_string(),
+            system_prefix: String::new(),
+            system_suffix: "\n\n".to_string(),
+            user_prefix: "### Instruction:\n".to_string(),
+            user_suffix: "\n\n".to_string(),
+            assistant_prefix: "### Response:\n".to_string(),
+            assistant_suffix: "\n\n".to_string(),
+            stop_sequences: vec!["### Instruction:".to_string()],
+        }
+    }
+
+    pub fn vicuna() -> Self {
+        Self {
+            name: "Vicuna".to_string(),
+            system_prefix: String::new(),
+            system_suffix: "\n\n".to_string(),
+            user_prefix: "USER: ".to_string(),
+            user_suffix: "\n".to_string(),
+            assistant_prefix: "ASSISTANT: ".to_string(),
+            assistant_suffix: "</s>\n".to_string(),
+            stop_sequences: vec!["USER:".to_string(), "</s>".to_string()],
+        }
+    }
+}
+
+/// Templates that ship with Hearth
+pub fn builtin_templates() -> Vec<InstructTemplate> {
+    vec![
+        InstructTemplate::chatml(),
+        InstructTemplate::llama3(),
+        InstructTemplate::mistral(),
+        InstructTemplate::alpaca(),
+        InstructTemplate::vicuna(),
+    ]
+}
+
+/// Built-in templates together with the user's own, looked up by name
+///
+/// Only user templates are stored. Saving a template with a built-in name
+/// overrides the built-in until it is removed again.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct TemplateRegistry {
+    custom: Vec<InstructTemplate>,
+}
+
+impl TemplateRegistry {
+    pub fn get(&self, name: &str) -> Option<InstructTemplate> {
+        self.custom
+            .iter()
+            .find(|t| t.name == name)
+            .cloned()
+            .or_else(|| builtin_templates().into_iter().find(|t| t.name == name))
+    }
+
+    /// Every available template, built-ins first
+    pub fn all(&self) -> Vec<InstructTemplate> {
+        let mut templates: Vec<InstructTemplate> = builtin_templates()
+            .into_iter()
+            .map(|builtin| self.get(&builtin.name).unwrap_or(builtin))
+            .collect();
+        templates.extend(
+            self.custom
+                .iter()
+                .filter(|t| !Self::is_builtin(&t.name))
+                .cloned(),
+        );
+        templates
+    }
+
+    /// Add a template, replacing any user template with the same name
+    pub fn save(&mut self, template: InstructTemplate) {
+        match self.custom.iter_mut().find(|t| t.name == template.name) {
+            Some(existing) => *existing = template,
+            None => self.custom.push(template),
+        }
+    }
+
+    /// Remove a user template, bringing back the built-in it overrode
+    pub fn remove(&mut self, name: &str) -> bool {
+        let before = self.custom.len();
+        self.custom.retain(|t| t.name != name);
+        self.custom.len() != before
+    }
+
+    pub fn is_builtin(name: &str) -> bool {
+        builtin_templates().iter().any(|t| t.name == name)
+    }
+
+    /// Whether the user has their own version of this template
+    pub fn is_custom(&self, name: &str) -> bool {
+        self.custom.iter().any(|t| t.name == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chatml_leaves_reply_open() {
+        let prompt = InstructTemplate::chatml().format(&[
+            ChatMessage::new(ChatRole::System, "You narrate."),
+            ChatMessage::new(ChatRole::User, "Alex: Hello"),
+        ]);
+        assert_eq!(
+            prompt,
+            "<|im_start|>system\nYou narrate.<|im_end|>\n<|im_start|>user\nAlex: Hello<|im_end|>\n<|im_start|>assistant\n"
+        );
+    }
+
+    #[test]
+    fn test_import_sillytavern_preset() {
+        let template = InstructTemplate::import(
+            r#"{
+                "name": "ChatML (ST)",
+                "system_sequence": "<|im_start|>system",
+                "system_suffix": "<|im_end|>\n",
+                "input_sequence": "<|im_start|>user",
+                "input_suffix": "<|im_end|>\n",
+                "output_sequence": "<|im_start|>assistant",
+                "output_suffix": "<|im_end|>\n",
+                "stop_sequence": "<|im_end|>",
+                "wrap": true,
+                "macro": true
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            InstructTemplate { name: "ChatML".to_string(), ..template.clone() },
+            InstructTemplate::chatml()
+        );
+
+        let exported = InstructTemplate::import(&template.export()).unwrap();
+        assert_eq!(exported, template);
+        assert!(matches!(InstructTemplate::import("{}"), Err(InstructError::MissingName)));
+    }
+
+    #[test]
+    fn test_registry_overrides_builtins() {
+        let mut registry = TemplateRegistry::default();
+        assert_eq!(registry.all().len(), 5);
+
+        let alpaca = InstructTemplate {
+            stop_sequences: vec!["###".to_string()],
+            ..InstructTemplate::alpaca()
+        };
+        registry.save(alpaca.clone());
+        registry.save(InstructTemplate {
+            name: "Pygmalion".to_string(),
+            ..InstructTemplate::default()
+        });
+        assert_eq!(registry.get("Alpaca"), Some(alpaca));
+        assert_eq!(registry.all().len(), 6);
+
+        assert!(registry.remove("Alpaca"));
+        assert_eq!(registry.get("Alpaca"), Some(InstructTemplate::alpaca()));
+        assert!(registry.get("Unknown").is_none());
+    }
+}
@@ -1,15 +1,51 @@
 //! Core models and data for the Hearth application
 
+pub mod backend;
+pub mod card;
+pub mod chat_import;
+pub mod credentials;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod database;
+pub mod definition;
+pub mod entity;
+pub mod guidance;
+pub mod instruct;
+pub mod llm;
 pub mod logging;
 pub mod markdown;
+pub mod message_tree;
 pub mod models;
+pub mod persona;
+pub mod prompt;
+pub mod save_dialog;
+pub mod sampler;
 pub mod sample;
 pub mod settings;
 pub mod storage;
+pub mod story_export;
+pub mod tokens;
 
+pub use backend::*;
+pub use card::*;
+pub use chat_import::*;
+pub use credentials::*;
+#[cfg(not(target_arch = "wasm32"))]
+pub use database::*;
+pub use definition::*;
+pub use entity::*;
+pub use guidance::*;
+pub use instruct::*;
+pub use llm::*;
 pub use logging::*;
 pub use markdown::*;
+pub use message_tree::*;
 pub use models::*;
+pub use persona::*;
+pub use prompt::*;
+pub use save_dialog::*;
+pub use sampler::*;
 pub use sample::*;
 pub use settings::*;
 pub use storage::*;
+pub use story_export::*;
+pub use tokens::*;
@@ -0,0 +1,333 @@
+//! Anthropic messages API client
+
+use super::stream::{read_stream, sse_data, StreamEvent};
+use super::{
+    check, metadata, ChatRole, Completion, CompletionRequest, LlmError, LlmProvider, ModelInfo, OnToken, StopSignal,
+};
+use crate::LlmProviderConfig;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+pub const ANTHROPIC_DEFAULT_URL: &str = "https://api.anthropic.com";
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+/// The messages API requires a limit, unlike the other providers
+const DEFAULT_MAX_TOKENS: u32 = 1024;
+
+#[derive(Clone)]
+pub struct AnthropicProvider {
+    name: String,
+    base_url: String,
+    api_key: String,
+    model: String,
+    max_tokens: Option<u32>,
+    temperature: Option<f32>,
+    client: Client,
+}
+
+#[derive(Debug, Serialize)]
+struct MessagesRequest<'a> {
+    model: &'a str,
+    max_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<String>,
+    messages: Vec<Message>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_k: Option<u32>,
+    /// Penalties, min-p and seeds are not supported by the messages API
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    stop_sequences: &'a [String],
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct Message {
+    role: ChatRole,
+    content: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct MessagesResponse {
+    content: Vec<ContentBlock>,
+    usage: Option<Usage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ContentBlock {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    text: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelsResponse {
+    data: Vec<Model>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Model {
+    id: String,
+    display_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Usage {
+    output_tokens: Option<u32>,
+}
+
+/// Payload of one streamed `data:` event; the event name is repeated in `type`
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum StreamPayload {
+    ContentBlockDelta { delta: TextDelta },
+    MessageDelta { usage: Option<Usage> },
+    MessageStop,
+    Error { error: ApiError },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+struct TextDelta {
+    #[serde(default)]
+    text: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiError {
+    message: String,
+}
+
+impl AnthropicProvider {
+    pub fn new(config: &LlmProviderConfig) -> Result<Self, LlmError> {
+        let api_key = config
+            .config
+            .api_key
+            .clone()
+            .filter(|key| !key.is_empty())
+            .ok_or_else(|| LlmError::NotConfigured(format!("no API key for '{}'", config.name)))?;
+        Ok(Self {
+            name: config.name.clone(),
+            base_url: config
+                .config
+                .base_url
+                .as_deref()
+                .unwrap_or(ANTHROPIC_DEFAULT_URL)
+                .trim_end_matches('/')
+                .to_string(),
+            api_key,
+            model: config.config.model.clone(),
+            max_tokens: config.config.max_tokens,
+            temperature: config.config.temperature,
+            client: Client::new(),
+        })
+    }
+
+    /// System messages move to the top-level `system` field, and the conversation
+    /// must alternate between user and assistant starting with the user.
+    fn messages_request<'a>(&'a self, request: &'a CompletionRequest, stream: bool) -> MessagesRequest<'a> {
+        let system: Vec<&str> = request
+            .messages
+            .iter()
+            .filter(|m| m.role == ChatRole::System)
+            .map(|m| m.content.as_str())
+            .collect();
+
+        let mut messages: Vec<Message> = Vec::new();
+        for message in request.messages.iter().filter(|m| m.role != ChatRole::System) {
+            match messages.last_mut() {
+                Some(last) if last.role == message.role => {
+                    last.content.push_str("\n\n");
+                    last.content.push_str(&message.content);
+                }
+                _ => messages.push(Message {
+                    role: message.role,
+                    content: message.content.clone(),
+                }),
+            }
+        }
+        if messages.first().is_none_or(|m| m.role != ChatRole::User) {
+            messages.insert(
+                0,
+                Message {
+                    role: ChatRole::User,
+                    content: "Begin.".to_string(),
+                },
+            );
+        }
+
+        MessagesRequest {
+            model: &self.model,
+            max_tokens: request
+                .max_tokens
+                .or(self.max_tokens)
+                .unwrap_or(DEFAULT_MAX_TOKENS),
+            system: (!system.is_empty()).then(|| system.join("\n\n")),
+            messages,
+            temperature: request.sampler.temperature.or(self.temperature),
+            top_p: request.sampler.top_p,
+            top_k: request.sampler.top_k,
+            stop_sequences: &request.sampler.stop,
+            stream,
+        }
+    }
+
+    fn post(&self, body: &MessagesRequest<'_>) -> reqwest::RequestBuilder {
+        self.authorize(self.client.post(format!("{}/v1/messages", self.base_url)))
+            .json(body)
+    }
+
+    fn authorize(&self, builder: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        builder
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+    }
+}
+
+#[async_trait(?Send)]
+impl LlmProvider for AnthropicProvider {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn model(&self) -> String {
+        self.model.clone()
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, LlmError> {
+        // One page holds every model Anthropic currently offers
+        let request = self
+            .client
+            .get(format!("{}/v1/models", self.base_url))
+            .query(&[("limit", "1000")]);
+        let response = self.authorize(request).send().await?;
+        let body: ModelsResponse = check(response).await?.json().await?;
+        Ok(body
+            .data
+            .into_iter()
+            .map(|model| ModelInfo {
+                id: model.id,
+                display_name: model.display_name,
+            })
+            .collect())
+    }
+
+    async fn complete(&self, request: &CompletionRequest) -> Result<Completion, LlmError> {
+        let started_at = chrono::Utc::now();
+        let response = self.post(&self.messages_request(request, false)).send().await?;
+        let body: MessagesResponse = check(response).await?.json().await?;
+
+        let content: String = body
+            .content
+            .iter()
+            .filter(|block| block.kind == "text")
+            .map(|block| block.text.as_str())
+            .collect();
+        let token_count = body.usage.and_then(|u| u.output_tokens);
+        Ok(Completion {
+            content,
+            metadata: metadata(&self.name, &self.model, started_at, token_count),
+        })
+    }
+
+    async fn stream(
+        &self,
+        request: &CompletionRequest,
+        on_token: &mut OnToken<'_>,
+        stop: &StopSignal,
+    ) -> Result<Completion, LlmError> {
+        let started_at = chrono::Utc::now();
+        let response = self.post(&self.messages_request(request, true)).send().await?;
+        let (content, token_count) =
+            read_stream(check(response).await?, stop, on_token, parse_stream_line).await?;
+        Ok(Completion {
+            content,
+            metadata: metadata(&self.name, &self.model, started_at, token_count),
+        })
+    }
+}
+
+/// Anthropic streams named server-sent events; only the `data:` lines matter
+fn parse_stream_line(line: &str) -> Result<StreamEvent, LlmError> {
+    let Some(data) = sse_data(line) else {
+        return Ok(StreamEvent::Ignore);
+    };
+    let payload: StreamPayload =
+        serde_json::from_str(data).map_err(|e| LlmError::InvalidResponse(e.to_string()))?;
+    Ok(match payload {
+        StreamPayload::ContentBlockDelta { delta } if !delta.text.is_empty() => {
+            StreamEvent::Text(delta.text)
+        }
+        StreamPayload::MessageDelta { usage } => usage
+            .and_then(|u| u.output_tokens)
+            .map_or(StreamEvent::Ignore, StreamEvent::Usage),
+        StreamPayload::MessageStop => StreamEvent::Done,
+        StreamPayload::Error { error } => return Err(LlmError::Stream(error.message)),
+        _ => StreamEvent::Ignore,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::ChatMessage;
+    use crate::{LlmProviderSettings, LlmProviderType};
+
+    #[test]
+    fn test_request_moves_system_and_merges_turns() {
+        let provider = AnthropicProvider::new(&LlmProviderConfig {
+            id: "anthropic".to_string(),
+            name: "Anthropic".to_string(),
+            provider_type: LlmProviderType::Anthropic,
+            config: LlmProviderSettings {
+                base_url: None,
+                api_key: Some("key".to_string()),
+                model: "claude".to_string(),
+                max_tokens: None,
+                temperature: None,
+                context_size: None,
+                instruct_template: None,
+            },
+        })
+        .unwrap();
+        let request = CompletionRequest::new(vec![
+            ChatMessage::new(ChatRole::System, "Narrate."),
+            ChatMessage::new(ChatRole::Assistant, "The wind howls."),
+            ChatMessage::new(ChatRole::Assistant, "A door opens."),
+            ChatMessage::new(ChatRole::User, "I step inside."),
+            ChatMessage::new(ChatRole::System, "[Story guidance: Keep it tense]"),
+        ]);
+
+        let body = provider.messages_request(&request, false);
+        assert_eq!(body.max_tokens, DEFAULT_MAX_TOKENS);
+        assert_eq!(body.system.as_deref(), Some("Narrate.\n\n[Story guidance: Keep it tense]"));
+        let roles: Vec<ChatRole> = body.messages.iter().map(|m| m.role).collect();
+        assert_eq!(roles, vec![ChatRole::User, ChatRole::Assistant, ChatRole::User]);
+        assert_eq!(body.messages[1].content, "The wind howls.\n\nA door opens.");
+    }
+
+    #[test]
+    fn test_stream_lines() {
+        assert_eq!(parse_stream_line("event: content_block_delta").unwrap(), StreamEvent::Ignore);
+        assert_eq!(
+            parse_stream_line(r#"data: {"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"The door"}}"#).unwrap(),
+            StreamEvent::Text("The door".to_string())
+        );
+        assert_eq!(
+            parse_stream_line(r#"data: {"type":"message_delta","delta":{"stop_reason":"end_turn"},"usage":{"output_tokens":15}}"#).unwrap(),
+            StreamEvent::Usage(15)
+        );
+        assert_eq!(parse_stream_line(r#"data: {"type":"ping"}"#).unwrap(), StreamEvent::Ignore);
+        assert_eq!(parse_stream_line(r#"data: {"type":"message_stop"}"#).unwrap(), StreamEvent::Done);
+        assert!(matches!(
+            parse_stream_line(r#"data: {"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#),
+            Err(LlmError::Stream(_))
+        ));
+    }
+}
@@ -0,0 +1,474 @@
+//! Text generation through language model providers
+//!
+//! Every [`LlmProviderType`] has a client implementing [`LlmProvider`]. Clients are
+//! built from the user's [`LlmProviderConfig`] by [`connect_provider`], so the story
+//! view never has to know which API it is talking to. Ollama and custom servers
+//! switch from their chat API to raw text completion when the provider has an
+//! [`InstructTemplate`] selected.
+
+use crate::models::GenerationMetadata;
+use crate::{
+    InstructTemplate, LlmProviderConfig, LlmProviderType, LocalBackendConfig, SamplerSettings, TemplateRegistry,
+};
+use async_trait::async_trait;
+use reqwest::Response;
+use serde::{Deserialize, Serialize};
+use std::rc::Rc;
+use std::time::Duration;
+use thiserror::Error;
+
+mod anthropic;
+mod ollama;
+mod openai;
+mod retry;
+mod stream;
+
+pub use anthropic::*;
+pub use ollama::*;
+pub use openai::*;
+pub use retry::*;
+pub use stream::{OnToken, StopSignal};
+
+#[derive(Error, Debug)]
+pub enum LlmError {
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("Provider error ({status}): {message}")]
+    Server {
+        status: u16,
+        message: String,
+        /// How long the provider asked us to wait before retrying
+        retry_after: Option<Duration>,
+    },
+    #[error("Stream error: {0}")]
+    Stream(String),
+    #[error("Invalid response: {0}")]
+    InvalidResponse(String),
+    #[error("Provider not configured: {0}")]
+    NotConfigured(String),
+    #[error("Authentication failed: {0}")]
+    Unauthorized(String),
+    #[error("No answer within {} seconds", .0.as_secs())]
+    Timeout(Duration),
+}
+
+impl LlmError {
+    /// Whether the same request may succeed if sent again
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::Server { status, .. } => *status == 429 || *status >= 500,
+            Self::Http(e) => e.is_timeout() || e.is_request(),
+            Self::Stream(_) | Self::Timeout(_) => true,
+            _ => false,
+        }
+    }
+
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Self::Server { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChatRole {
+    System,
+    User,
+    Assistant,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: ChatRole,
+    pub content: String,
+}
+
+impl ChatMessage {
+    pub fn new(role: ChatRole, content: impl Into<String>) -> Self {
+        Self {
+            role,
+            content: content.into(),
+        }
+    }
+}
+
+/// Prompt and sampling options for a single generation
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CompletionRequest {
+    pub messages: Vec<ChatMessage>,
+    /// Overrides the provider's configured limit
+    pub max_tokens: Option<u32>,
+    /// Temperature here overrides the provider's configured one; options the
+    /// provider does not support are dropped
+    pub sampler: SamplerSettings,
+}
+
+impl CompletionRequest {
+    pub fn new(messages: Vec<ChatMessage>) -> Self {
+        Self {
+            messages,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_sampler(mut self, sampler: SamplerSettings) -> Self {
+        self.sampler = sampler;
+        self
+    }
+}
+
+/// Stop sequences of the template followed by the sampler's own
+pub(crate) fn stop_sequences(template: Option<&InstructTemplate>, sampler: &SamplerSettings) -> Vec<String> {
+    let mut stop: Vec<String> = template.map(|t| t.stop_sequences.clone()).unwrap_or_default();
+    for sequence in &sampler.stop {
+        if !stop.contains(sequence) {
+            stop.push(sequence.clone());
+        }
+    }
+    stop
+}
+
+/// A model offered by a provider
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelInfo {
+    /// Name to put in the provider's `model` setting
+    pub id: String,
+    pub display_name: Option<String>,
+}
+
+impl ModelInfo {
+    pub fn new(id: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            display_name: None,
+        }
+    }
+
+    pub fn label(&self) -> &str {
+        self.display_name.as_deref().unwrap_or(&self.id)
+    }
+}
+
+/// Outcome of a successful [`test_connection`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConnectionTest {
+    /// Round trip time of the model listing request
+    pub latency_ms: u64,
+    pub models: Vec<ModelInfo>,
+    /// Whether the configured model is among the listed ones
+    pub model_available: bool,
+}
+
+/// Generated text together with how it was generated
+#[derive(Debug, Clone, PartialEq)]
+pub struct Completion {
+    pub content: String,
+    pub metadata: GenerationMetadata,
+}
+
+/// A language model API that can continue a conversation
+///
+/// Futures are not `Send` so clients also work in the browser.
+#[async_trait(?Send)]
+pub trait LlmProvider {
+    /// Name of the provider as configured by the user
+    fn name(&self) -> String;
+    /// Model used for generations
+    fn model(&self) -> String;
+    /// Models the provider can generate with
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, LlmError>;
+    async fn complete(&self, request: &CompletionRequest) -> Result<Completion, LlmError>;
+    /// Generate a reply, passing each piece of text to `on_token` as it arrives
+    ///
+    /// Once `stop` is triggered the request is cancelled and the text received so
+    /// far is returned as the completion.
+    async fn stream(
+        &self,
+        request: &CompletionRequest,
+        on_token: &mut OnToken<'_>,
+        stop: &StopSignal,
+    ) -> Result<Completion, LlmError>;
+}
+
+pub type SharedProvider = Rc<dyn LlmProvider>;
+
+/// Build the client for a configured provider, looking its template up in `templates`
+pub fn connect_provider(
+    config: &LlmProviderConfig,
+    templates: &TemplateRegistry,
+) -> Result<SharedProvider, LlmError> {
+    if config.config.model.trim().is_empty() {
+        return Err(LlmError::NotConfigured(format!("no model selected for '{}'", config.name)));
+    }
+    connect_for_discovery(config, templates)
+}
+
+/// Build a client that may have no model yet, for [`LlmProvider::list_models`] and [`test_connection`]
+pub fn connect_for_discovery(
+    config: &LlmProviderConfig,
+    templates: &TemplateRegistry,
+) -> Result<SharedProvider, LlmError> {
+    if config.config.api_key.as_deref().and_then(crate::credential_name).is_some() {
+        return Err(LlmError::NotConfigured(format!(
+            "the API key for '{}' is in the locked credential store",
+            config.name
+        )));
+    }
+    let template = instruct_template(config, templates)?;
+    Ok(match config.provider_type {
+        LlmProviderType::Ollama => Rc::new(OllamaProvider::new(config).with_template(template)),
+        LlmProviderType::OpenAI => Rc::new(OpenAiProvider::new(config)),
+        LlmProviderType::Anthropic => Rc::new(AnthropicProvider::new(config)?),
+        LlmProviderType::Custom => Rc::new(OpenAiProvider::custom(config)?.with_template(template)),
+    })
+}
+
+/// Client for the selected provider that falls back to the configured fallbacks
+///
+/// Providers that cannot be connected are skipped, so a fallback also covers
+/// a primary with a locked API key.
+pub fn connect_with_fallbacks(
+    local: &LocalBackendConfig,
+    templates: &TemplateRegistry,
+    sleep: Sleep,
+) -> Result<FailoverProvider, LlmError> {
+    let mut providers = Vec::new();
+    let mut first_error = None;
+    for config in local.provider_chain() {
+        match connect_provider(config, templates) {
+            Ok(provider) => providers.push((config.id.clone(), provider)),
+            Err(e) => {
+                log::warn!("Skipping provider '{}': {e}", config.name);
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+    match first_error {
+        Some(e) if providers.is_empty() => Err(e),
+        _ => FailoverProvider::new(providers, local.retry_policy.clone(), sleep),
+    }
+}
+
+fn instruct_template(
+    config: &LlmProviderConfig,
+    templates: &TemplateRegistry,
+) -> Result<Option<InstructTemplate>, LlmError> {
+    match &config.config.instruct_template {
+        Some(name) => templates.get(name).map(Some).ok_or_else(|| {
+            LlmError::NotConfigured(format!("unknown instruct template '{name}' for '{}'", config.name))
+        }),
+        None => Ok(None),
+    }
+}
+
+/// Check that a provider is reachable and accepts its credentials
+///
+/// Listing models is cheap and needs the same authentication as generating.
+pub async fn test_connection(provider: &dyn LlmProvider) -> Result<ConnectionTest, LlmError> {
+    let started_at = chrono::Utc::now();
+    let models = provider.list_models().await?;
+    let latency_ms = u64::try_from((chrono::Utc::now() - started_at).num_milliseconds()).unwrap_or(0);
+    let model = provider.model();
+    Ok(ConnectionTest {
+        latency_ms,
+        model_available: models.iter().any(|m| m.id == model),
+        models,
+    })
+}
+
+/// Turn an unsuccessful response into an [`LlmError::Server`] or [`LlmError::Unauthorized`]
+pub(crate) async fn check(response: Response) -> Result<Response, LlmError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let retry_after = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(parse_retry_after);
+    let message = response.text().await.unwrap_or_default();
+    if matches!(status.as_u16(), 401 | 403) {
+        return Err(LlmError::Unauthorized(message));
+    }
+    Err(LlmError::Server {
+        status: status.as_u16(),
+        message,
+        retry_after,
+    })
+}
+
+/// `Retry-After` is either a number of seconds or an HTTP date
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    if let Ok(seconds) = value.trim().parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value.trim()).ok()?;
+    Some((date.with_timezone(&chrono::Utc) - chrono::Utc::now()).to_std().unwrap_or_default())
+}
+
+/// Metadata for a generation that started at `started_at`
+pub(crate) fn metadata(
+    provider: &str,
+    model: &str,
+    started_at: chrono::DateTime<chrono::Utc>,
+    token_count: Option<u32>,
+) -> GenerationMetadata {
+    let finished_at = chrono::Utc::now();
+    GenerationMetadata {
+        provider: Some(provider.to_string()),
+        provider_id: None,
+        model: Some(model.to_string()),
+        created_at: Some(finished_at),
+        duration_ms: u64::try_from((finished_at - started_at).num_milliseconds()).ok(),
+        token_count,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LlmProviderSettings;
+    use serde_json::json;
+    use wiremock::matchers::{header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn config(provider_type: LlmProviderType, base_url: Option<&str>) -> LlmProviderConfig {
+        LlmProviderConfig {
+            id: "test".to_string(),
+            name: "Test".to_string(),
+            provider_type,
+            config: LlmProviderSettings {
+                base_url: base_url.map(str::to_string),
+                api_key: None,
+                model: "model".to_string(),
+                max_tokens: None,
+                temperature: None,
+                context_size: None,
+                instruct_template: None,
+            },
+        }
+    }
+
+    #[test]
+    fn test_connect_requires_custom_base_url() {
+        let templates = TemplateRegistry::default();
+        assert!(connect_provider(&config(LlmProviderType::Ollama, None), &templates).is_ok());
+        assert!(matches!(
+            connect_provider(&config(LlmProviderType::Custom, None), &templates),
+            Err(LlmError::NotConfigured(_))
+        ));
+        assert!(connect_provider(&config(LlmProviderType::Custom, Some("http://localhost:5000")), &templates).is_ok());
+    }
+
+    #[test]
+    fn test_connect_requires_known_template() {
+        let templates = TemplateRegistry::default();
+        let mut ollama = config(LlmProviderType::Ollama, None);
+        ollama.config.instruct_template = Some("ChatML".to_string());
+        assert!(connect_provider(&ollama, &templates).is_ok());
+        ollama.config.instruct_template = Some("Missing".to_string());
+        assert!(matches!(connect_provider(&ollama, &templates), Err(LlmError::NotConfigured(_))));
+    }
+
+    #[tokio::test]
+    async fn test_provider_without_model_can_list_models() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/tags"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "models": [{"name": "mistral:7b", "model": "mistral:7b"}]
+            })))
+            .mount(&server)
+            .await;
+
+        let mut config = config(LlmProviderType::Ollama, Some(&server.uri()));
+        config.config.model = String::new();
+        let templates = TemplateRegistry::default();
+        assert!(matches!(connect_provider(&config, &templates), Err(LlmError::NotConfigured(_))));
+
+        let provider = connect_for_discovery(&config, &templates).unwrap();
+        let test = test_connection(provider.as_ref()).await.unwrap();
+        assert_eq!(test.models, vec![ModelInfo::new("mistral:7b")]);
+        assert!(!test.model_available);
+    }
+
+    fn connect(provider_type: LlmProviderType, server: &MockServer, api_key: Option<&str>) -> SharedProvider {
+        let mut config = config(provider_type, Some(&server.uri()));
+        config.config.api_key = api_key.map(str::to_string);
+        connect_provider(&config, &TemplateRegistry::default()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_ollama_lists_installed_models() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/tags"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "models": [
+                    {"name": "model", "model": "model", "size": 4_000_000_000u64},
+                    {"name": "mistral:7b", "model": "mistral:7b", "size": 4_100_000_000u64}
+                ]
+            })))
+            .mount(&server)
+            .await;
+
+        let provider = connect(LlmProviderType::Ollama, &server, None);
+        let test = test_connection(provider.as_ref()).await.unwrap();
+        assert_eq!(test.models, vec![ModelInfo::new("model"), ModelInfo::new("mistral:7b")]);
+        assert!(test.model_available);
+    }
+
+    #[tokio::test]
+    async fn test_openai_compatible_models_and_auth_errors() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/models"))
+            .and(header("authorization", "Bearer good"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "object": "list",
+                "data": [{"id": "qwen2.5-7b-instruct", "object": "model", "owned_by": "local"}]
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/models"))
+            .respond_with(ResponseTemplate::new(401).set_body_string("invalid api key"))
+            .mount(&server)
+            .await;
+
+        let provider = connect(LlmProviderType::Custom, &server, Some("good"));
+        let test = test_connection(provider.as_ref()).await.unwrap();
+        assert_eq!(test.models[0].id, "qwen2.5-7b-instruct");
+        assert!(!test.model_available);
+
+        let provider = connect(LlmProviderType::Custom, &server, Some("bad"));
+        assert!(matches!(
+            test_connection(provider.as_ref()).await,
+            Err(LlmError::Unauthorized(message)) if message == "invalid api key"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_anthropic_lists_models_with_display_names() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/models"))
+            .and(query_param("limit", "1000"))
+            .and(header("x-api-key", "key"))
+            .and(header("anthropic-version", "2023-06-01"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": [{"type": "model", "id": "claude-sonnet-4-5", "display_name": "Claude Sonnet 4.5"}],
+                "has_more": false
+            })))
+            .mount(&server)
+            .await;
+
+        let provider = connect(LlmProviderType::Anthropic, &server, Some("key"));
+        let models = provider.list_models().await.unwrap();
+        assert_eq!(models[0].id, "claude-sonnet-4-5");
+        assert_eq!(models[0].label(), "Claude Sonnet 4.5");
+    }
+}
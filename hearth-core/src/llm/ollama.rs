@@ -0,0 +1,365 @@
+//! Ollama chat API client, with raw generation for instruct templates
+
+use super::stream::{read_stream, StreamEvent};
+use super::{
+    check, metadata, stop_sequences, ChatMessage, Completion, CompletionRequest, LlmError, LlmProvider, ModelInfo,
+    OnToken, StopSignal,
+};
+use crate::{InstructTemplate, LlmProviderConfig};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+pub const OLLAMA_DEFAULT_URL: &str = "http://localhost:11434";
+
+/// Client for a local or remote Ollama server
+#[derive(Clone)]
+pub struct OllamaProvider {
+    name: String,
+    base_url: String,
+    model: String,
+    max_tokens: Option<u32>,
+    temperature: Option<f32>,
+    context_size: Option<u32>,
+    /// Generate from a formatted prompt instead of using the chat API
+    template: Option<InstructTemplate>,
+    client: Client,
+}
+
+#[derive(Debug, Serialize)]
+struct ChatRequest<'a> {
+    model: &'a str,
+    messages: &'a [ChatMessage],
+    stream: bool,
+    options: Options,
+}
+
+/// Prompt already formatted by the template, passed through untouched
+#[derive(Debug, Serialize)]
+struct GenerateRequest<'a> {
+    model: &'a str,
+    prompt: String,
+    raw: bool,
+    stream: bool,
+    options: Options,
+}
+
+/// Ollama supports every sampler option
+#[derive(Debug, Serialize)]
+struct Options {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_k: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    min_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    repeat_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frequency_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    presence_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    num_predict: Option<u32>,
+    /// Ollama silently cuts prompts to its own default window unless told otherwise
+    #[serde(skip_serializing_if = "Option::is_none")]
+    num_ctx: Option<u32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    stop: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatResponse {
+    message: ChatMessage,
+    eval_count: Option<u32>,
+}
+
+/// One line of a streamed reply; the last one has `done` set and the token count
+#[derive(Debug, Deserialize)]
+struct ChatChunk {
+    message: Option<ChatMessage>,
+    #[serde(default)]
+    done: bool,
+    eval_count: Option<u32>,
+    error: Option<String>,
+}
+
+/// Locally installed models from `/api/tags`
+#[derive(Debug, Deserialize)]
+struct TagsResponse {
+    models: Vec<Tag>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Tag {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GenerateChunk {
+    #[serde(default)]
+    response: String,
+    #[serde(default)]
+    done: bool,
+    eval_count: Option<u32>,
+    error: Option<String>,
+}
+
+impl OllamaProvider {
+    pub fn new(config: &LlmProviderConfig) -> Self {
+        Self {
+            name: config.name.clone(),
+            base_url: config
+                .config
+                .base_url
+                .as_deref()
+                .unwrap_or(OLLAMA_DEFAULT_URL)
+                .trim_end_matches('/')
+                .to_string(),
+            model: config.config.model.clone(),
+            max_tokens: config.config.max_tokens,
+            temperature: config.config.temperature,
+            context_size: config.config.context_size,
+            template: None,
+            client: Client::new(),
+        }
+    }
+
+    pub fn with_template(mut self, template: Option<InstructTemplate>) -> Self {
+        self.template = template;
+        self
+    }
+
+    fn options(&self, request: &CompletionRequest) -> Options {
+        let sampler = &request.sampler;
+        Options {
+            temperature: sampler.temperature.or(self.temperature),
+            top_p: sampler.top_p,
+            top_k: sampler.top_k,
+            min_p: sampler.min_p,
+            repeat_penalty: sampler.repetition_penalty,
+            frequency_penalty: sampler.frequency_penalty,
+            presence_penalty: sampler.presence_penalty,
+            seed: sampler.seed,
+            num_predict: request.max_tokens.or(self.max_tokens),
+            num_ctx: self.context_size,
+            stop: stop_sequences(self.template.as_ref(), sampler),
+        }
+    }
+
+    fn chat_request<'a>(&'a self, request: &'a CompletionRequest, stream: bool) -> ChatRequest<'a> {
+        ChatRequest {
+            model: &self.model,
+            messages: &request.messages,
+            stream,
+            options: self.options(request),
+        }
+    }
+
+    fn generate_request<'a>(
+        &'a self,
+        template: &InstructTemplate,
+        request: &CompletionRequest,
+        stream: bool,
+    ) -> GenerateRequest<'a> {
+        GenerateRequest {
+            model: &self.model,
+            prompt: template.format(&request.messages),
+            raw: true,
+            stream,
+            options: self.options(request),
+        }
+    }
+
+    fn post(&self, request: &CompletionRequest, stream: bool) -> reqwest::RequestBuilder {
+        match &self.template {
+            Some(template) => self
+                .client
+                .post(format!("{}/api/generate", self.base_url))
+                .json(&self.generate_request(template, request, stream)),
+            None => self
+                .client
+                .post(format!("{}/api/chat", self.base_url))
+                .json(&self.chat_request(request, stream)),
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl LlmProvider for OllamaProvider {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn model(&self) -> String {
+        self.model.clone()
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, LlmError> {
+        let response = self.client.get(format!("{}/api/tags", self.base_url)).send().await?;
+        let body: TagsResponse = check(response).await?.json().await?;
+        Ok(body.models.into_iter().map(|tag| ModelInfo::new(tag.name)).collect())
+    }
+
+    async fn complete(&self, request: &CompletionRequest) -> Result<Completion, LlmError> {
+        let started_at = chrono::Utc::now();
+        let response = check(self.post(request, false).send().await?).await?;
+        let (content, token_count) = if self.template.is_some() {
+            let body: GenerateChunk = response.json().await?;
+            (body.response, body.eval_count)
+        } else {
+            let body: ChatResponse = response.json().await?;
+            (body.message.content, body.eval_count)
+        };
+        Ok(Completion {
+            content,
+            metadata: metadata(&self.name, &self.model, started_at, token_count),
+        })
+    }
+
+    async fn stream(
+        &self,
+        request: &CompletionRequest,
+        on_token: &mut OnToken<'_>,
+        stop: &StopSignal,
+    ) -> Result<Completion, LlmError> {
+        let started_at = chrono::Utc::now();
+        let response = check(self.post(request, true).send().await?).await?;
+        let parse = if self.template.is_some() {
+            parse_generate_line
+        } else {
+            parse_stream_line
+        };
+        let (content, token_count) = read_stream(response, stop, on_token, parse).await?;
+        Ok(Completion {
+            content,
+            metadata: metadata(&self.name, &self.model, started_at, token_count),
+        })
+    }
+}
+
+/// Ollama streams newline-delimited JSON objects
+fn parse_stream_line(line: &str) -> Result<StreamEvent, LlmError> {
+    let chunk: ChatChunk =
+        serde_json::from_str(line).map_err(|e| LlmError::InvalidResponse(e.to_string()))?;
+    if let Some(error) = chunk.error {
+        return Err(LlmError::Stream(error));
+    }
+    if chunk.done {
+        return Ok(chunk.eval_count.map_or(StreamEvent::Done, StreamEvent::Usage));
+    }
+    Ok(chunk
+        .message
+        .filter(|m| !m.content.is_empty())
+        .map_or(StreamEvent::Ignore, |m| StreamEvent::Text(m.content)))
+}
+
+/// Raw generation streams the same way, with the text in `response`
+fn parse_generate_line(line: &str) -> Result<StreamEvent, LlmError> {
+    let chunk: GenerateChunk =
+        serde_json::from_str(line).map_err(|e| LlmError::InvalidResponse(e.to_string()))?;
+    if let Some(error) = chunk.error {
+        return Err(LlmError::Stream(error));
+    }
+    if chunk.done {
+        return Ok(chunk.eval_count.map_or(StreamEvent::Done, StreamEvent::Usage));
+    }
+    Ok(if chunk.response.is_empty() {
+        StreamEvent::Ignore
+    } else {
+        StreamEvent::Text(chunk.response)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::ChatRole;
+    use crate::{LlmProviderSettings, LlmProviderType, SamplerSettings};
+
+    #[test]
+    fn test_request_uses_configured_options() {
+        let provider = OllamaProvider::new(&LlmProviderConfig {
+            id: "ollama".to_string(),
+            name: "Ollama".to_string(),
+            provider_type: LlmProviderType::Ollama,
+            config: LlmProviderSettings {
+                base_url: Some("http://localhost:11434/".to_string()),
+                api_key: None,
+                model: "llama3.1:8b".to_string(),
+                max_tokens: Some(256),
+                temperature: Some(0.7),
+                context_size: None,
+                instruct_template: None,
+            },
+        });
+        let request = CompletionRequest::new(vec![ChatMessage::new(ChatRole::User, "Hi")]).with_sampler(
+            SamplerSettings {
+                temperature: Some(1.2),
+                repetition_penalty: Some(1.1),
+                seed: Some(42),
+                ..SamplerSettings::default()
+            },
+        );
+
+        let body = serde_json::to_value(provider.chat_request(&request, false)).unwrap();
+        assert_eq!(provider.base_url, "http://localhost:11434");
+        assert_eq!(body["messages"][0]["role"], "user");
+        assert_eq!(body["options"]["num_predict"], 256);
+        assert_eq!(body["options"]["temperature"].as_f64().unwrap() as f32, 1.2);
+        assert_eq!(body["options"]["repeat_penalty"].as_f64().unwrap() as f32, 1.1);
+        assert_eq!(body["options"]["seed"], 42);
+        assert!(body["options"].get("top_k").is_none());
+        assert_eq!(body["stream"], false);
+    }
+
+    #[test]
+    fn test_template_uses_raw_generation() {
+        let provider = OllamaProvider::new(&LlmProviderConfig {
+            id: "ollama".to_string(),
+            name: "Ollama".to_string(),
+            provider_type: LlmProviderType::Ollama,
+            config: LlmProviderSettings {
+                base_url: None,
+                api_key: None,
+                model: "mistral:7b-text".to_string(),
+                max_tokens: None,
+                temperature: None,
+                context_size: None,
+                instruct_template: None,
+            },
+        })
+        .with_template(Some(InstructTemplate::mistral()));
+        let request = CompletionRequest::new(vec![ChatMessage::new(ChatRole::User, "Hi")]);
+
+        let template = provider.template.as_ref().unwrap();
+        let body = serde_json::to_value(provider.generate_request(template, &request, true)).unwrap();
+        assert_eq!(body["prompt"], "[INST] Hi [/INST]");
+        assert_eq!(body["raw"], true);
+        assert_eq!(body["options"]["stop"][0], "</s>");
+        assert_eq!(
+            parse_generate_line(r#"{"model":"m","response":" Hello","done":false}"#).unwrap(),
+            StreamEvent::Text(" Hello".to_string())
+        );
+    }
+
+    #[test]
+    fn test_stream_lines() {
+        assert_eq!(
+            parse_stream_line(r#"{"model":"m","message":{"role":"assistant","content":"The"},"done":false}"#).unwrap(),
+            StreamEvent::Text("The".to_string())
+        );
+        assert_eq!(
+            parse_stream_line(r#"{"model":"m","message":{"role":"assistant","content":""},"done":true,"eval_count":12}"#).unwrap(),
+            StreamEvent::Usage(12)
+        );
+        assert!(matches!(
+            parse_stream_line(r#"{"error":"model not found"}"#),
+            Err(LlmError::Stream(_))
+        ));
+    }
+}
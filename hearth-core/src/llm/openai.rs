@@ -0,0 +1,395 @@
+//! OpenAI chat completions client, also used for OpenAI-compatible servers
+//!
+//! Self-hosted servers such as llama.cpp and KoboldCpp also offer the plain
+//! `completions` endpoint, which is used when an instruct template is selected.
+
+use super::stream::{read_stream, sse_data, StreamEvent};
+use super::{
+    check, metadata, stop_sequences, ChatMessage, Completion, CompletionRequest, LlmError, LlmProvider, ModelInfo,
+    OnToken, StopSignal,
+};
+use crate::{InstructTemplate, LlmProviderConfig};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+pub const OPENAI_DEFAULT_URL: &str = "https://api.openai.com/v1";
+
+/// Client for OpenAI or any server implementing its chat completions API
+///
+/// `base_url` includes the API version, e.g. `http://localhost:5000/v1`.
+#[derive(Clone)]
+pub struct OpenAiProvider {
+    name: String,
+    base_url: String,
+    api_key: Option<String>,
+    model: String,
+    max_tokens: Option<u32>,
+    temperature: Option<f32>,
+    /// Send a formatted prompt to `completions` instead of using chat completions
+    template: Option<InstructTemplate>,
+    /// Self-hosted servers also take samplers that OpenAI does not offer
+    extended_sampling: bool,
+    client: Client,
+}
+
+/// OpenAI's API accepts at most this many stop sequences
+const MAX_OPENAI_STOP: usize = 4;
+
+#[derive(Debug, Serialize)]
+struct ChatRequest<'a> {
+    model: &'a str,
+    messages: &'a [ChatMessage],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u32>,
+    #[serde(flatten)]
+    sampling: Sampling,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct TextRequest<'a> {
+    model: &'a str,
+    prompt: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u32>,
+    #[serde(flatten)]
+    sampling: Sampling,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct Sampling {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frequency_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    presence_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<i64>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    stop: Vec<String>,
+    /// Extensions understood by llama.cpp, vLLM and KoboldCpp
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_k: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    min_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    repetition_penalty: Option<f32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatResponse {
+    choices: Vec<Choice>,
+    usage: Option<Usage>,
+}
+
+/// Chat completions return a `message`, text completions only `text`
+#[derive(Debug, Deserialize)]
+struct Choice {
+    message: Option<ChatMessage>,
+    text: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Usage {
+    completion_tokens: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelsResponse {
+    data: Vec<Model>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Model {
+    id: String,
+}
+
+/// Payload of one streamed `data:` event
+#[derive(Debug, Deserialize)]
+struct ChatChunk {
+    #[serde(default)]
+    choices: Vec<ChunkChoice>,
+    usage: Option<Usage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChunkChoice {
+    delta: Option<Delta>,
+    text: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Delta {
+    content: Option<String>,
+}
+
+impl OpenAiProvider {
+    pub fn new(config: &LlmProviderConfig) -> Self {
+        Self::with_base_url(config, config.config.base_url.as_deref().unwrap_or(OPENAI_DEFAULT_URL))
+    }
+
+    /// Client for a self-hosted OpenAI-compatible server, which needs an explicit URL
+    pub fn custom(config: &LlmProviderConfig) -> Result<Self, LlmError> {
+        match config.config.base_url.as_deref() {
+            Some(url) if !url.trim().is_empty() => Ok(Self {
+                extended_sampling: true,
+                ..Self::with_base_url(config, url)
+            }),
+            _ => Err(LlmError::NotConfigured(format!("no base URL for '{}'", config.name))),
+        }
+    }
+
+    fn with_base_url(config: &LlmProviderConfig, base_url: &str) -> Self {
+        Self {
+            name: config.name.clone(),
+            base_url: base_url.trim().trim_end_matches('/').to_string(),
+            api_key: config.config.api_key.clone().filter(|key| !key.is_empty()),
+            model: config.config.model.clone(),
+            max_tokens: config.config.max_tokens,
+            temperature: config.config.temperature,
+            template: None,
+            extended_sampling: false,
+            client: Client::new(),
+        }
+    }
+
+    pub fn with_template(mut self, template: Option<InstructTemplate>) -> Self {
+        self.template = template;
+        self
+    }
+
+    fn sampling(&self, request: &CompletionRequest) -> Sampling {
+        let sampler = &request.sampler;
+        let mut stop = stop_sequences(self.template.as_ref(), sampler);
+        let mut sampling = Sampling {
+            temperature: sampler.temperature.or(self.temperature),
+            top_p: sampler.top_p,
+            frequency_penalty: sampler.frequency_penalty,
+            presence_penalty: sampler.presence_penalty,
+            seed: sampler.seed,
+            ..Sampling::default()
+        };
+        if self.extended_sampling {
+            sampling.top_k = sampler.top_k;
+            sampling.min_p = sampler.min_p;
+            sampling.repetition_penalty = sampler.repetition_penalty;
+        } else {
+            stop.truncate(MAX_OPENAI_STOP);
+        }
+        sampling.stop = stop;
+        sampling
+    }
+
+    fn chat_request<'a>(&'a self, request: &'a CompletionRequest, stream: bool) -> ChatRequest<'a> {
+        ChatRequest {
+            model: &self.model,
+            messages: &request.messages,
+            max_tokens: request.max_tokens.or(self.max_tokens),
+            sampling: self.sampling(request),
+            stream,
+        }
+    }
+
+    fn text_request<'a>(
+        &'a self,
+        template: &InstructTemplate,
+        request: &CompletionRequest,
+        stream: bool,
+    ) -> TextRequest<'a> {
+        TextRequest {
+            model: &self.model,
+            prompt: template.format(&request.messages),
+            max_tokens: request.max_tokens.or(self.max_tokens),
+            sampling: self.sampling(request),
+            stream,
+        }
+    }
+
+    fn post(&self, request: &CompletionRequest, stream: bool) -> reqwest::RequestBuilder {
+        let builder = match &self.template {
+            Some(template) => self
+                .client
+                .post(format!("{}/completions", self.base_url))
+                .json(&self.text_request(template, request, stream)),
+            None => self
+                .client
+                .post(format!("{}/chat/completions", self.base_url))
+                .json(&self.chat_request(request, stream)),
+        };
+        self.authorize(builder)
+    }
+
+    fn authorize(&self, builder: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match &self.api_key {
+            Some(key) => builder.bearer_auth(key),
+            None => builder,
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl LlmProvider for OpenAiProvider {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn model(&self) -> String {
+        self.model.clone()
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, LlmError> {
+        let request = self.client.get(format!("{}/models", self.base_url));
+        let response = self.authorize(request).send().await?;
+        let body: ModelsResponse = check(response).await?.json().await?;
+        Ok(body.data.into_iter().map(|model| ModelInfo::new(model.id)).collect())
+    }
+
+    async fn complete(&self, request: &CompletionRequest) -> Result<Completion, LlmError> {
+        let started_at = chrono::Utc::now();
+        let response = self.post(request, false).send().await?;
+        let body: ChatResponse = check(response).await?.json().await?;
+
+        let choice = body
+            .choices
+            .into_iter()
+            .next()
+            .ok_or_else(|| LlmError::InvalidResponse("no choices returned".to_string()))?;
+        let content = choice
+            .message
+            .map(|m| m.content)
+            .or(choice.text)
+            .ok_or_else(|| LlmError::InvalidResponse("choice has no content".to_string()))?;
+        let token_count = body.usage.and_then(|u| u.completion_tokens);
+        Ok(Completion {
+            content,
+            metadata: metadata(&self.name, &self.model, started_at, token_count),
+        })
+    }
+
+    async fn stream(
+        &self,
+        request: &CompletionRequest,
+        on_token: &mut OnToken<'_>,
+        stop: &StopSignal,
+    ) -> Result<Completion, LlmError> {
+        let started_at = chrono::Utc::now();
+        let response = self.post(request, true).send().await?;
+        let (content, token_count) =
+            read_stream(check(response).await?, stop, on_token, parse_stream_line).await?;
+        Ok(Completion {
+            content,
+            metadata: metadata(&self.name, &self.model, started_at, token_count),
+        })
+    }
+}
+
+/// OpenAI streams server-sent events ending with `data: [DONE]`
+fn parse_stream_line(line: &str) -> Result<StreamEvent, LlmError> {
+    let Some(data) = sse_data(line) else {
+        return Ok(StreamEvent::Ignore);
+    };
+    if data == "[DONE]" {
+        return Ok(StreamEvent::Done);
+    }
+    let chunk: ChatChunk =
+        serde_json::from_str(data).map_err(|e| LlmError::InvalidResponse(e.to_string()))?;
+    if let Some(count) = chunk.usage.and_then(|u| u.completion_tokens) {
+        return Ok(StreamEvent::Usage(count));
+    }
+    Ok(chunk
+        .choices
+        .into_iter()
+        .next()
+        .and_then(|choice| choice.delta.and_then(|d| d.content).or(choice.text))
+        .filter(|content| !content.is_empty())
+        .map_or(StreamEvent::Ignore, StreamEvent::Text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::ChatRole;
+    use crate::{LlmProviderSettings, LlmProviderType, SamplerSettings};
+
+    fn config(base_url: Option<&str>) -> LlmProviderConfig {
+        LlmProviderConfig {
+            id: "openai".to_string(),
+            name: "OpenAI".to_string(),
+            provider_type: LlmProviderType::OpenAI,
+            config: LlmProviderSettings {
+                base_url: base_url.map(str::to_string),
+                api_key: None,
+                model: "gpt-4o-mini".to_string(),
+                max_tokens: None,
+                temperature: Some(0.7),
+                context_size: None,
+                instruct_template: None,
+            },
+        }
+    }
+
+    #[test]
+    fn test_sampler_options_per_server() {
+        let request = CompletionRequest::new(vec![ChatMessage::new(ChatRole::User, "Hi")]).with_sampler(
+            SamplerSettings {
+                top_p: Some(0.9),
+                min_p: Some(0.05),
+                seed: Some(3),
+                stop: (1..=5).map(|i| format!("###{i}")).collect(),
+                ..SamplerSettings::default()
+            },
+        );
+
+        let openai = OpenAiProvider::new(&config(None));
+        let body = serde_json::to_value(openai.chat_request(&request, false)).unwrap();
+        assert_eq!(body["temperature"].as_f64().unwrap() as f32, 0.7);
+        assert_eq!(body["top_p"].as_f64().unwrap() as f32, 0.9);
+        assert_eq!(body["seed"], 3);
+        assert!(body.get("min_p").is_none());
+        assert_eq!(body["stop"].as_array().unwrap().len(), MAX_OPENAI_STOP);
+
+        let custom = OpenAiProvider::custom(&config(Some("http://localhost:8080/v1"))).unwrap();
+        let body = serde_json::to_value(custom.chat_request(&request, false)).unwrap();
+        assert_eq!(body["min_p"].as_f64().unwrap() as f32, 0.05);
+        assert_eq!(body["stop"].as_array().unwrap().len(), 5);
+    }
+
+    #[test]
+    fn test_response_parsing() {
+        let body: ChatResponse = serde_json::from_str(
+            r#"{
+                "id": "chatcmpl-1",
+                "choices": [{"index": 0, "message": {"role": "assistant", "content": "The door creaks."}, "finish_reason": "stop"}],
+                "usage": {"prompt_tokens": 20, "completion_tokens": 4, "total_tokens": 24}
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(body.choices[0].message.as_ref().unwrap().content, "The door creaks.");
+        assert_eq!(body.usage.unwrap().completion_tokens, Some(4));
+    }
+
+    #[test]
+    fn test_stream_lines() {
+        assert_eq!(
+            parse_stream_line(r#"data: {"choices":[{"index":0,"delta":{"content":" creaks"}}]}"#).unwrap(),
+            StreamEvent::Text(" creaks".to_string())
+        );
+        assert_eq!(
+            parse_stream_line(r#"data: {"choices":[{"index":0,"delta":{"role":"assistant"}}]}"#).unwrap(),
+            StreamEvent::Ignore
+        );
+        assert_eq!(
+            parse_stream_line(r#"data: {"choices":[{"index":0,"text":" opens","finish_reason":null}]}"#).unwrap(),
+            StreamEvent::Text(" opens".to_string())
+        );
+        assert_eq!(parse_stream_line(": keep-alive").unwrap(), StreamEvent::Ignore);
+        assert_eq!(parse_stream_line("data: [DONE]").unwrap(), StreamEvent::Done);
+    }
+}
@@ -0,0 +1,335 @@
+//! Retries, timeouts and fallback providers
+//!
+//! A [`FailoverProvider`] tries each configured provider in order. Transient
+//! failures (rate limits, server errors, timeouts) are retried with exponential
+//! backoff before moving on to the next provider. A streamed reply is never
+//! retried once text has reached the user.
+
+use super::{Completion, CompletionRequest, LlmError, LlmProvider, ModelInfo, OnToken, SharedProvider, StopSignal};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::future::{poll_fn, Future};
+use std::pin::{pin, Pin};
+use std::rc::Rc;
+use std::task::Poll;
+use std::time::Duration;
+
+/// Waits for the given time on whatever runtime the app uses
+pub type Sleep = Rc<dyn Fn(Duration) -> Pin<Box<dyn Future<Output = ()>>>>;
+
+/// How generations cope with slow and failing providers
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Seconds a provider has to start answering, 0 waits forever
+    pub timeout_secs: u64,
+    /// Retries of a failed request before moving to the next provider
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled for each one after it
+    pub initial_backoff_ms: u64,
+    /// Longest delay between retries; providers asking for longer are skipped
+    pub max_backoff_ms: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            timeout_secs: 60,
+            max_retries: 2,
+            initial_backoff_ms: 1000,
+            max_backoff_ms: 30_000,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn timeout(&self) -> Option<Duration> {
+        (self.timeout_secs > 0).then(|| Duration::from_secs(self.timeout_secs))
+    }
+
+    /// Delay before retry number `retry` (from 0) after `error`, or `None` to give up
+    pub fn delay(&self, retry: u32, error: &LlmError) -> Option<Duration> {
+        if retry >= self.max_retries || !error.is_transient() {
+            return None;
+        }
+        let max = Duration::from_millis(self.max_backoff_ms);
+        let backoff = Duration::from_millis(self.initial_backoff_ms.saturating_mul(1 << retry.min(32)));
+        match error.retry_after() {
+            Some(retry_after) if retry_after > max => None,
+            Some(retry_after) => Some(retry_after.max(backoff).min(max)),
+            None => Some(backoff.min(max)),
+        }
+    }
+}
+
+/// Providers tried in order until one answers
+pub struct FailoverProvider {
+    /// Configuration id and client of each provider
+    providers: Vec<(String, SharedProvider)>,
+    policy: RetryPolicy,
+    sleep: Sleep,
+}
+
+impl FailoverProvider {
+    /// `providers` must not be empty; the first one is the primary
+    pub fn new(providers: Vec<(String, SharedProvider)>, policy: RetryPolicy, sleep: Sleep) -> Result<Self, LlmError> {
+        if providers.is_empty() {
+            return Err(LlmError::NotConfigured("no provider selected".to_string()));
+        }
+        Ok(Self {
+            providers,
+            policy,
+            sleep,
+        })
+    }
+
+    fn primary(&self) -> &SharedProvider {
+        &self.providers[0].1
+    }
+
+    /// Wait before a retry, returning false if the generation was stopped meanwhile
+    async fn backoff(&self, delay: Duration, stop: Option<&StopSignal>) -> bool {
+        let mut timer = (self.sleep)(delay);
+        poll_fn(|cx| {
+            if let Some(stop) = stop {
+                if stop.poll_stopped(cx).is_ready() {
+                    return Poll::Ready(false);
+                }
+            }
+            timer.as_mut().poll(cx).map(|()| true)
+        })
+        .await
+    }
+
+    /// Run `request`, failing with [`LlmError::Timeout`] if nothing was answered in time
+    async fn with_timeout(
+        &self,
+        request: impl Future<Output = Result<Completion, LlmError>>,
+        answered: &Cell<bool>,
+    ) -> Result<Completion, LlmError> {
+        let Some(timeout) = self.policy.timeout() else {
+            return request.await;
+        };
+        let mut request = pin!(request);
+        let mut timer = Some((self.sleep)(timeout));
+        poll_fn(|cx| {
+            if let Poll::Ready(result) = request.as_mut().poll(cx) {
+                return Poll::Ready(result);
+            }
+            if let Some(pending) = timer.as_mut() {
+                if pending.as_mut().poll(cx).is_ready() {
+                    if !answered.get() {
+                        return Poll::Ready(Err(LlmError::Timeout(timeout)));
+                    }
+                    timer = None;
+                }
+            }
+            Poll::Pending
+        })
+        .await
+    }
+
+    fn log_failure(&self, index: usize, error: &LlmError, delay: Option<Duration>) {
+        let name = self.providers[index].1.name();
+        match delay {
+            Some(delay) => log::warn!("{name} failed ({error}), retrying in {} ms", delay.as_millis()),
+            None if index + 1 < self.providers.len() => {
+                log::warn!("{name} failed ({error}), falling back to {}", self.providers[index + 1].1.name())
+            }
+            None => log::error!("{name} failed ({error}), no providers left"),
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl LlmProvider for FailoverProvider {
+    fn name(&self) -> String {
+        self.primary().name()
+    }
+
+    fn model(&self) -> String {
+        self.primary().model()
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, LlmError> {
+        self.primary().list_models().await
+    }
+
+    async fn complete(&self, request: &CompletionRequest) -> Result<Completion, LlmError> {
+        let mut last_error = None;
+        for (index, (id, provider)) in self.providers.iter().enumerate() {
+            let mut retry = 0;
+            loop {
+                match self.with_timeout(provider.complete(request), &Cell::new(false)).await {
+                    Ok(mut completion) => {
+                        completion.metadata.provider_id = Some(id.clone());
+                        return Ok(completion);
+                    }
+                    Err(error) => {
+                        let delay = self.policy.delay(retry, &error);
+                        self.log_failure(index, &error, delay);
+                        match delay {
+                            Some(delay) => {
+                                self.backoff(delay, None).await;
+                                retry += 1;
+                            }
+                            None => {
+                                last_error = Some(error);
+                                break;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Err(last_error.unwrap_or_else(|| LlmError::NotConfigured("no provider selected".to_string())))
+    }
+
+    async fn stream(
+        &self,
+        request: &CompletionRequest,
+        on_token: &mut OnToken<'_>,
+        stop: &StopSignal,
+    ) -> Result<Completion, LlmError> {
+        let mut last_error = None;
+        for (index, (id, provider)) in self.providers.iter().enumerate() {
+            let mut retry = 0;
+            loop {
+                let answered = Cell::new(false);
+                let mut forward = |token: &str| {
+                    answered.set(true);
+                    on_token(token);
+                };
+                let result = self.with_timeout(provider.stream(request, &mut forward, stop), &answered).await;
+                let error = match result {
+                    Ok(mut completion) => {
+                        completion.metadata.provider_id = Some(id.clone());
+                        return Ok(completion);
+                    }
+                    // Text the user has already seen cannot be taken back
+                    Err(error) if answered.get() || stop.is_stopped() => return Err(error),
+                    Err(error) => error,
+                };
+                let delay = self.policy.delay(retry, &error);
+                self.log_failure(index, &error, delay);
+                match delay {
+                    Some(delay) => {
+                        if !self.backoff(delay, Some(stop)).await {
+                            return Err(error);
+                        }
+                        retry += 1;
+                    }
+                    None => {
+                        last_error = Some(error);
+                        break;
+                    }
+                }
+            }
+        }
+        Err(last_error.unwrap_or_else(|| LlmError::NotConfigured("no provider selected".to_string())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::connect_provider;
+    use crate::{LlmProviderConfig, LlmProviderType, TemplateRegistry};
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const REPLY: &str = concat!(
+        r#"{"message":{"role":"assistant","content":"Hello"},"done":false}"#,
+        "\n",
+        r#"{"done":true,"eval_count":1}"#,
+        "\n"
+    );
+
+    fn provider(id: &str, server: &MockServer) -> (String, SharedProvider) {
+        let mut config = LlmProviderConfig::new(LlmProviderType::Ollama);
+        config.id = id.to_string();
+        config.name = id.to_string();
+        config.config.base_url = Some(server.uri());
+        (id.to_string(), connect_provider(&config, &TemplateRegistry::default()).unwrap())
+    }
+
+    fn failover(providers: Vec<(String, SharedProvider)>, timeout_secs: u64) -> FailoverProvider {
+        let policy = RetryPolicy {
+            timeout_secs,
+            max_retries: 2,
+            initial_backoff_ms: 10,
+            max_backoff_ms: 1000,
+        };
+        let sleep: Sleep = Rc::new(|duration| Box::pin(tokio::time::sleep(duration)));
+        FailoverProvider::new(providers, policy, sleep).unwrap()
+    }
+
+    async fn stream(provider: &FailoverProvider) -> Result<Completion, LlmError> {
+        let request = CompletionRequest::new(vec![]);
+        provider.stream(&request, &mut |_| {}, &StopSignal::new()).await
+    }
+
+    #[tokio::test]
+    async fn test_retries_server_errors_on_the_same_provider() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/chat"))
+            .respond_with(ResponseTemplate::new(503).insert_header("Retry-After", "0"))
+            .up_to_n_times(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/chat"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(REPLY))
+            .mount(&server)
+            .await;
+
+        let completion = stream(&failover(vec![provider("primary", &server)], 0)).await.unwrap();
+        assert_eq!(completion.content, "Hello");
+        assert_eq!(completion.metadata.provider_id.as_deref(), Some("primary"));
+        assert_eq!(server.received_requests().await.unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_when_rate_limited_for_too_long() {
+        let primary = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "3600"))
+            .mount(&primary)
+            .await;
+        let backup = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(REPLY))
+            .mount(&backup)
+            .await;
+
+        let provider = failover(vec![provider("primary", &primary), provider("backup", &backup)], 0);
+        let completion = stream(&provider).await.unwrap();
+        assert_eq!(completion.metadata.provider_id.as_deref(), Some("backup"));
+        assert_eq!(completion.metadata.provider.as_deref(), Some("backup"));
+        // Retry-After beyond the longest allowed delay is not waited for
+        assert_eq!(primary.received_requests().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_times_out_and_gives_up_after_last_provider() {
+        let slow = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(REPLY).set_delay(Duration::from_secs(5)))
+            .mount(&slow)
+            .await;
+        let broken = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(400).set_body_string("bad request"))
+            .mount(&broken)
+            .await;
+
+        let provider = failover(vec![provider("slow", &slow), provider("broken", &broken)], 1);
+        let error = stream(&provider).await.unwrap_err();
+        assert!(matches!(error, LlmError::Server { status: 400, .. }));
+        // Timeouts are retried, client errors are not
+        assert_eq!(slow.received_requests().await.unwrap().len(), 3);
+        assert_eq!(broken.received_requests().await.unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,178 @@
+//! Incremental reading of streamed responses
+//!
+//! OpenAI and Anthropic stream server-sent events, Ollama streams one JSON object
+//! per line. Both arrive in arbitrary chunks, so bytes are buffered until a line is
+//! complete and each provider only has to turn a line into a [`StreamEvent`].
+
+use super::LlmError;
+use reqwest::Response;
+use std::cell::RefCell;
+use std::future::{poll_fn, Future};
+use std::pin::pin;
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+
+/// Receives each piece of text as it arrives
+pub type OnToken<'a> = dyn FnMut(&str) + 'a;
+
+/// Handle for stopping a streaming generation early
+///
+/// Stopping drops the request, and the text received so far is kept as the reply.
+#[derive(Clone, Default)]
+pub struct StopSignal(Rc<RefCell<StopState>>);
+
+#[derive(Default)]
+struct StopState {
+    stopped: bool,
+    waker: Option<Waker>,
+}
+
+impl StopSignal {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn stop(&self) {
+        let waker = {
+            let mut state = self.0.borrow_mut();
+            state.stopped = true;
+            state.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.0.borrow().stopped
+    }
+
+    pub(crate) fn poll_stopped(&self, cx: &mut Context<'_>) -> Poll<()> {
+        let mut state = self.0.borrow_mut();
+        if state.stopped {
+            Poll::Ready(())
+        } else {
+            state.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+/// What a single streamed line means
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum StreamEvent {
+    /// Next piece of the reply
+    Text(String),
+    /// Number of generated tokens reported by the provider
+    Usage(u32),
+    Done,
+    /// Keep-alives, event names and other lines without content
+    Ignore,
+}
+
+/// Splits a byte stream into lines, keeping incomplete lines for the next chunk
+#[derive(Debug, Default)]
+pub(crate) struct LineDecoder {
+    buffer: Vec<u8>,
+}
+
+impl LineDecoder {
+    pub(crate) fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.buffer.extend_from_slice(chunk);
+        let mut lines = Vec::new();
+        while let Some(end) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            lines.push(Self::decode(&line));
+        }
+        lines
+    }
+
+    /// Whatever is left once the stream ended without a final newline
+    pub(crate) fn finish(self) -> Option<String> {
+        (!self.buffer.is_empty()).then(|| Self::decode(&self.buffer))
+    }
+
+    fn decode(line: &[u8]) -> String {
+        String::from_utf8_lossy(line)
+            .trim_end_matches(['\r', '\n'])
+            .to_string()
+    }
+}
+
+/// Payload of an SSE `data:` line
+pub(crate) fn sse_data(line: &str) -> Option<&str> {
+    line.strip_prefix("data:").map(str::trim)
+}
+
+/// Read a streamed response until it is done or `stop` is triggered
+///
+/// Returns the generated text and the token count if the provider reported one.
+pub(crate) async fn read_stream(
+    mut response: Response,
+    stop: &StopSignal,
+    on_token: &mut OnToken<'_>,
+    parse: impl Fn(&str) -> Result<StreamEvent, LlmError>,
+) -> Result<(String, Option<u32>), LlmError> {
+    let mut decoder = LineDecoder::default();
+    let mut content = String::new();
+    let mut token_count = None;
+
+    loop {
+        let next = {
+            let mut chunk = pin!(response.chunk());
+            poll_fn(|cx| {
+                if stop.poll_stopped(cx).is_ready() {
+                    return Poll::Ready(None);
+                }
+                chunk.as_mut().poll(cx).map(Some)
+            })
+            .await
+        };
+        let (lines, finished) = match next {
+            // Stopped: dropping the response cancels the request
+            None => return Ok((content, token_count)),
+            Some(Ok(Some(chunk))) => (decoder.push(&chunk), false),
+            Some(Ok(None)) => (std::mem::take(&mut decoder).finish().into_iter().collect(), true),
+            Some(Err(e)) => return Err(e.into()),
+        };
+
+        for line in lines.iter().filter(|line| !line.trim().is_empty()) {
+            match parse(line)? {
+                StreamEvent::Text(text) => {
+                    on_token(&text);
+                    content.push_str(&text);
+                }
+                StreamEvent::Usage(count) => token_count = Some(count),
+                StreamEvent::Done => return Ok((content, token_count)),
+                StreamEvent::Ignore => {}
+            }
+        }
+        if finished {
+            return Ok((content, token_count));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lines_split_across_chunks() {
+        let mut decoder = LineDecoder::default();
+        assert!(decoder.push(b"data: {\"a\":").is_empty());
+        assert_eq!(decoder.push(b"1}\r\n\r\ndata: caf\xC3"), vec!["data: {\"a\":1}", ""]);
+        assert_eq!(decoder.push(b"\xA9\n"), vec!["data: caf\u{e9}"]);
+        assert!(decoder.push(b"{\"done\":true}").is_empty());
+        assert_eq!(decoder.finish().as_deref(), Some("{\"done\":true}"));
+    }
+
+    #[test]
+    fn test_stop_signal_is_shared() {
+        let stop = StopSignal::new();
+        let handle = stop.clone();
+        assert!(!stop.is_stopped());
+        handle.stop();
+        assert!(stop.is_stopped());
+    }
+}
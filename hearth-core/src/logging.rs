@@ -2,7 +2,7 @@
 //!
 //! Implements the standard Rust `log` crate with persistent storage and UI access.
 
-use crate::{Storage, StorageError};
+use crate::{save_with_dialog, SaveFile, Storage, StorageError};
 use log::{Level, Metadata, Record};
 use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
@@ -450,198 +450,18 @@ impl HearthLogger {
     /// Export logs with a save dialog for all platforms
     pub async fn export_logs_with_dialog(&self) -> Result<String, LoggingError> {
         let content = self.export_logs()?;
-        
-        #[cfg(target_arch = "wasm32")]
-        {
-            self.export_logs_web(content).await
-        }
-        #[cfg(not(target_arch = "wasm32"))]
-        {
-            self.export_logs_native(content).await
-        }
-    }
-
-    #[cfg(target_arch = "wasm32")]
-    async fn export_logs_web(&self, content: String) -> Result<String, LoggingError> {
-        use web_sys::{window, Blob, BlobPropertyBag, Url};
-        use wasm_bindgen::{JsValue, JsCast};
-        
-        let window = window().ok_or_else(|| {
-            LoggingError::Io(std::io::Error::new(
-                std::io::ErrorKind::NotFound,
-                "No window available"
-            ))
-        })?;
-        let document = window.document().ok_or_else(|| {
-            LoggingError::Io(std::io::Error::new(
-                std::io::ErrorKind::NotFound,
-                "No document available"
-            ))
-        })?;
-        
-        // Create a blob with the log content
-        let blob_parts = js_sys::Array::new();
-        blob_parts.push(&JsValue::from_str(&content));
-        
-        let blob_options = BlobPropertyBag::new();
-        blob_options.set_type("text/plain");
-        
-        let blob = Blob::new_with_str_sequence_and_options(&blob_parts, &blob_options)
-            .map_err(|_| LoggingError::Io(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                "Failed to create blob"
-            )))?;
-        
-        // Create object URL
-        let url = Url::create_object_url_with_blob(&blob)
-            .map_err(|_| LoggingError::Io(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                "Failed to create object URL"
-            )))?;
-        
-        // Create a download link
-        let a = document.create_element("a")
-            .map_err(|_| LoggingError::Io(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                "Failed to create anchor element"
-            )))?;
-        a.set_attribute("href", &url)
-            .map_err(|_| LoggingError::Io(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                "Failed to set href"
-            )))?;
-        
-        // Generate filename with timestamp
-        let now = js_sys::Date::new_0();
-        let timestamp = now.to_iso_string();
-        let filename = format!("hearth-logs-{}.txt", timestamp.as_string().unwrap_or_else(|| "unknown".to_string()));
-        a.set_attribute("download", &filename)
-            .map_err(|_| LoggingError::Io(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                "Failed to set download attribute"
-            )))?;
-        
-        // Trigger download
-        let html_a = a.dyn_into::<web_sys::HtmlElement>()
-            .map_err(|_| LoggingError::Io(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                "Failed to cast to HtmlElement"
-            )))?;
-        html_a.click();
-        
-        // Clean up object URL
-        Url::revoke_object_url(&url)
-            .map_err(|_| LoggingError::Io(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                "Failed to revoke object URL"
-            )))?;
-        
-        Ok(format!("Log file '{}' downloaded successfully", filename))
-    }
-
-    #[cfg(not(target_arch = "wasm32"))]
-    async fn export_logs_native(&self, content: String) -> Result<String, LoggingError> {
-        // Generate default filename with timestamp
-        let now = chrono::Utc::now();
-        let timestamp = now.format("%Y%m%d_%H%M%S");
-        let default_filename = format!("hearth-logs-{}.txt", timestamp);
-        
-        // Use file dialog for desktop, save to documents directory for mobile
-        #[cfg(all(not(feature = "mobile"), not(target_os = "android")))]
-        {
-            use rfd::AsyncFileDialog;
-            
-            let file_path = AsyncFileDialog::new()
-                .set_title("Save Hearth Logs")
-                .set_file_name(&default_filename)
-                .add_filter("Text Files", &["txt"])
-                .add_filter("All Files", &["*"])
-                .save_file()
-                .await;
-            
-            if let Some(path) = file_path {
-                let path = path.path();
-                let mut file = std::fs::File::create(&path)?;
-                
-                std::io::Write::write_all(&mut file, content.as_bytes())?;
-                
-                log::info!("Logs exported to: {}", path.display());
-                Ok(format!("Logs saved to: {}", path.display()))
-            } else {
-                Err(LoggingError::Io(std::io::Error::new(
-                    std::io::ErrorKind::Other,
-                    "Save dialog was cancelled"
-                )))
-            }
-        }
-        
-        // For mobile platforms and Android, use Android DownloadManager (like web download)
-        #[cfg(any(feature = "mobile", target_os = "android"))]
-        {
-            self.export_logs_android_download(content, default_filename).await
-        }
-    }
-
-    #[cfg(any(feature = "mobile", target_os = "android"))]
-    async fn export_logs_android_download(&self, content: String, default_filename: String) -> Result<String, LoggingError> {
-        // Use web-style download approach for mobile - create a downloadable link
-        // This provides a proper "download" experience like web browsers
-        
-        #[cfg(target_arch = "wasm32")]
-        {
-            // If we're in a mobile web environment, use the same web API
-            self.export_logs_web(content).await
-        }
-        #[cfg(not(target_arch = "wasm32"))]
-        {
-            // For native mobile (Android), try to use the system's sharing/download capability
-            // Fall back to a user-accessible location if available
-            
-            // Try multiple common Android storage locations
-            let potential_paths = vec![
-                // Android external storage paths that are typically accessible
-                std::env::var("EXTERNAL_STORAGE").ok().map(|p| std::path::PathBuf::from(p).join("Download")),
-                // Standard Android downloads
-                Some(std::path::PathBuf::from("/storage/emulated/0/Download")),
-                // Fallback to app's external files directory
-                dirs::document_dir().map(|p| p.join("Downloads")),
-                // Last resort - current directory
-                Some(std::path::PathBuf::from(".")),
-            ];
-            
-            let mut last_error = "No accessible storage location found".to_string();
-            
-            for potential_path in potential_paths.into_iter().flatten() {
-                if let Err(_) = std::fs::create_dir_all(&potential_path) {
-                    continue;
-                }
-                
-                let file_path = potential_path.join(&default_filename);
-                match std::fs::File::create(&file_path) {
-                    Ok(mut file) => {
-                        match std::io::Write::write_all(&mut file, content.as_bytes()) {
-                            Ok(_) => {
-                                log::info!("Logs saved to: {}", file_path.display());
-                                log::info!("File downloaded successfully");
-                                return Ok(format!("Logs saved to: {}", file_path.display()));
-                            }
-                            Err(e) => {
-                                last_error = format!("Failed to write to file: {e}");
-                                continue;
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        last_error = format!("Failed to create file: {e}");
-                        continue;
-                    }
-                }
-            }
-            
-            Err(LoggingError::Io(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                last_error
-            )))
+        let timestamp = chrono::Utc::now().format("%Y%m%d_%H%M%S");
+        let file = SaveFile {
+            title: "Save Hearth Logs".to_string(),
+            file_name: format!("hearth-logs-{timestamp}.txt"),
+            kind: "Text Files".to_string(),
+            extension: "txt".to_string(),
+            mime_type: "text/plain".to_string(),
+            content: content.into_bytes(),
+        };
+        match save_with_dialog(file).await? {
+            Some(location) => Ok(format!("Logs saved to: {location}")),
+            None => Err(LoggingError::Io(std::io::Error::other("Save dialog was cancelled"))),
         }
     }
 }
@@ -25,6 +25,9 @@ pub struct MarkdownConfig {
     pub td_class: Option<String>,
     pub hr_class: Option<String>,
     pub quote_class: Option<String>,
+    /// Escape raw HTML and unlink URLs that are not http(s) or mailto,
+    /// for HTML opened outside the app
+    pub sanitize: bool,
 }
 
 impl Default for MarkdownConfig {
@@ -46,6 +49,7 @@ impl Default for MarkdownConfig {
             td_class: None,
             hr_class: None,
             quote_class: Some("text-orange-500".to_string()),
+            sanitize: false,
         }
     }
 }
@@ -292,6 +296,10 @@ fn ast_to_html(node: &mdast::Node, config: &MarkdownConfig) -> String {
                 .map(|c| format!(" class=\"{}\"", c))
                 .unwrap_or_default();
             
+            if config.sanitize && !is_safe_url(&link.url) {
+                return content;
+            }
+
             let title_attr = link.title.as_ref()
                 .map(|t| format!(" title=\"{}\"", html_escape(t)))
                 .unwrap_or_default();
@@ -439,6 +447,13 @@ fn ast_to_html(node: &mdast::Node, config: &MarkdownConfig) -> String {
             format!("<del>{}</del>", content)
         }
         
+        mdast::Node::Html(html) if config.sanitize => {
+            // Only our own quote markers survive, everything else is shown as text
+            html_escape(&html.value)
+                .replace("&lt;hearth-quote&gt;", "<hearth-quote>")
+                .replace("&lt;/hearth-quote&gt;", "</hearth-quote>")
+        }
+
         mdast::Node::Html(html) => {
             // Pass through HTML as-is, we'll handle custom quotes in post-processing
             html.value.clone()
@@ -452,6 +467,17 @@ fn ast_to_html(node: &mdast::Node, config: &MarkdownConfig) -> String {
     }
 }
 
+/// Whether a link can be followed safely from a document outside the app
+fn is_safe_url(url: &str) -> bool {
+    // Browsers ignore whitespace and control characters inside a scheme
+    let url: String = url
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .collect::<String>()
+        .to_ascii_lowercase();
+    ["http://", "https://", "mailto:"].iter().any(|scheme| url.starts_with(scheme))
+}
+
 /// Escape HTML entities
 fn html_escape(text: &str) -> String {
     text.replace('&', "&amp;")
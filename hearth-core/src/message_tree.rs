@@ -0,0 +1,366 @@
+//! Branching message history
+//!
+//! Every message in a story points at the message it replied to, so a story is a
+//! tree rather than a list. Each leaf ends a branch, and the story's cursor picks
+//! the branch whose path from the root is shown in the story view.
+
+use crate::models::StoryMessage;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum TreeError {
+    #[error("Unknown message: {0}")]
+    UnknownMessage(String),
+    #[error("Message {0} is not the end of a branch")]
+    NotALeaf(String),
+    #[error("Cannot prune the only branch of a story")]
+    OnlyBranch,
+}
+
+/// A message together with its position in the tree
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MessageNode {
+    pub message: StoryMessage,
+    /// Message this one replied to; `None` for the first message of a story
+    pub parent_id: Option<String>,
+}
+
+/// Summary of one branch for the branch history
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoryBranch {
+    /// Last message of the branch, used to switch to or prune it
+    pub leaf_id: String,
+    /// Message the branch split off from, if it shares history with another branch
+    pub fork_id: Option<String>,
+    /// Number of messages from the root to the leaf
+    pub length: usize,
+    pub preview: String,
+    pub is_active: bool,
+}
+
+/// In-memory message tree of a single story
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MessageTree {
+    /// Nodes in creation order, which is also the order of siblings
+    nodes: Vec<MessageNode>,
+    cursor: Option<String>,
+    /// Position of each message in `nodes`
+    index: HashMap<String, usize>,
+    /// Positions of each message's replies, in creation order
+    children: HashMap<String, Vec<usize>>,
+}
+
+impl MessageTree {
+    /// Build a tree from stored nodes and the story's cursor
+    ///
+    /// Without a valid cursor the most recently created branch is active.
+    pub fn new(nodes: Vec<MessageNode>, cursor: Option<String>) -> Self {
+        let mut tree = Self {
+            nodes,
+            ..Self::default()
+        };
+        tree.reindex();
+        tree.cursor = cursor
+            .filter(|id| tree.contains(id))
+            .or_else(|| tree.nodes.last().map(|n| n.message.id.clone()));
+        tree
+    }
+
+    pub fn nodes(&self) -> &[MessageNode] {
+        &self.nodes
+    }
+
+    /// Message the active path ends at; new messages are attached here
+    pub fn cursor(&self) -> Option<&str> {
+        self.cursor.as_deref()
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.node(id).is_some()
+    }
+
+    fn node(&self, id: &str) -> Option<&MessageNode> {
+        self.index.get(id).map(|&position| &self.nodes[position])
+    }
+
+    fn children<'a>(&'a self, id: &str) -> impl Iterator<Item = &'a MessageNode> + 'a {
+        self.children
+            .get(id)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .map(|&position| &self.nodes[position])
+    }
+
+    /// Rebuild the lookup maps after nodes were added or removed in bulk
+    fn reindex(&mut self) {
+        self.index.clear();
+        self.children.clear();
+        for position in 0..self.nodes.len() {
+            self.index_node(position);
+        }
+    }
+
+    fn index_node(&mut self, position: usize) {
+        let node = &self.nodes[position];
+        // The first of several messages with the same id wins, as it always has
+        self.index.entry(node.message.id.clone()).or_insert(position);
+        if let Some(parent_id) = &node.parent_id {
+            self.children.entry(parent_id.clone()).or_default().push(position);
+        }
+    }
+
+    /// Parent of `node`, unless walking up has already passed it in corrupted data
+    fn parent<'a>(&'a self, node: &MessageNode, visited: &mut HashSet<&'a str>) -> Option<&'a MessageNode> {
+        let parent = self.node(node.parent_id.as_deref()?)?;
+        if !visited.insert(parent.message.id.as_str()) {
+            log::warn!("Message {} is its own ancestor", parent.message.id);
+            return None;
+        }
+        Some(parent)
+    }
+
+    fn is_leaf(&self, id: &str) -> bool {
+        self.children(id).next().is_none()
+    }
+
+    /// Ids from the root down to and including `id`
+    fn path_ids(&self, id: &str) -> Vec<String> {
+        let mut path = Vec::new();
+        let mut visited = HashSet::from([id]);
+        let mut current = self.node(id);
+        while let Some(node) = current {
+            path.push(node.message.id.clone());
+            current = self.parent(node, &mut visited);
+        }
+        path.reverse();
+        path
+    }
+
+    /// Messages of the visible thread in conversation order
+    pub fn active_path(&self) -> Vec<StoryMessage> {
+        let Some(cursor) = &self.cursor else {
+            return Vec::new();
+        };
+        self.path_ids(cursor)
+            .iter()
+            .filter_map(|id| self.node(id))
+            .map(|n| n.message.clone())
+            .collect()
+    }
+
+    /// Attach a message after the cursor and move the cursor onto it
+    pub fn append(&mut self, message: StoryMessage) -> Option<String> {
+        let parent_id = self.cursor.clone();
+        self.cursor = Some(message.id.clone());
+        self.nodes.push(MessageNode {
+            message,
+            parent_id: parent_id.clone(),
+        });
+        self.index_node(self.nodes.len() - 1);
+        parent_id
+    }
+
+    /// Move the cursor back to `message_id` so the next message starts a new branch
+    pub fn fork(&mut self, message_id: &str) -> Result<(), TreeError> {
+        if !self.contains(message_id) {
+            return Err(TreeError::UnknownMessage(message_id.to_string()));
+        }
+        self.cursor = Some(message_id.to_string());
+        Ok(())
+    }
+
+    /// Make the branch ending at `leaf_id` the visible one
+    pub fn switch_branch(&mut self, leaf_id: &str) -> Result<(), TreeError> {
+        if !self.contains(leaf_id) {
+            return Err(TreeError::UnknownMessage(leaf_id.to_string()));
+        }
+        if !self.is_leaf(leaf_id) {
+            return Err(TreeError::NotALeaf(leaf_id.to_string()));
+        }
+        self.cursor = Some(leaf_id.to_string());
+        Ok(())
+    }
+
+    /// All branches, oldest first
+    pub fn branches(&self) -> Vec<StoryBranch> {
+        let active: HashSet<String> = self
+            .cursor
+            .as_deref()
+            .map(|c| self.path_ids(c).into_iter().collect())
+            .unwrap_or_default();
+
+        self.nodes
+            .iter()
+            .filter(|n| self.is_leaf(&n.message.id))
+            .map(|leaf| {
+                let leaf_id = leaf.message.id.clone();
+                let segment = self.unique_segment(&leaf_id);
+                let fork_id = segment
+                    .last()
+                    .and_then(|top| self.node(top))
+                    .and_then(|n| n.parent_id.clone());
+                StoryBranch {
+                    length: self.path_ids(&leaf_id).len(),
+                    preview: leaf.message.content.chars().take(80).collect(),
+                    is_active: active.contains(&leaf_id),
+                    fork_id,
+                    leaf_id,
+                }
+            })
+            .collect()
+    }
+
+    /// Remove the messages that belong only to the branch ending at `leaf_id`
+    ///
+    /// Returns the removed ids, leaf first. If the pruned branch was active the
+    /// cursor moves to the newest remaining branch at the fork point.
+    pub fn prune_branch(&mut self, leaf_id: &str) -> Result<Vec<String>, TreeError> {
+        if !self.contains(leaf_id) {
+            return Err(TreeError::UnknownMessage(leaf_id.to_string()));
+        }
+        if !self.is_leaf(leaf_id) {
+            return Err(TreeError::NotALeaf(leaf_id.to_string()));
+        }
+        let leaf_count = self.nodes.iter().filter(|n| self.is_leaf(&n.message.id)).count();
+        if leaf_count <= 1 {
+            return Err(TreeError::OnlyBranch);
+        }
+
+        let removed = self.unique_segment(leaf_id);
+        let fork_id = removed
+            .last()
+            .and_then(|top| self.node(top))
+            .and_then(|n| n.parent_id.clone());
+        let cursor_removed = self
+            .cursor
+            .as_ref()
+            .is_some_and(|c| removed.contains(c));
+
+        self.nodes.retain(|n| !removed.contains(&n.message.id));
+        self.reindex();
+        if cursor_removed {
+            self.cursor = match fork_id {
+                Some(fork_id) => Some(self.newest_leaf_under(&fork_id)),
+                None => self.nodes.last().map(|n| n.message.id.clone()),
+            };
+        }
+        Ok(removed)
+    }
+
+    /// Walk up from a leaf until reaching a message shared with another branch
+    fn unique_segment(&self, leaf_id: &str) -> Vec<String> {
+        let mut segment = Vec::new();
+        let mut visited = HashSet::from([leaf_id]);
+        let mut current = self.node(leaf_id);
+        while let Some(node) = current {
+            segment.push(node.message.id.clone());
+            current = self
+                .parent(node, &mut visited)
+                .filter(|parent| self.children(&parent.message.id).count() == 1);
+        }
+        segment
+    }
+
+    /// Follow the newest child from `id` down to a leaf
+    fn newest_leaf_under(&self, id: &str) -> String {
+        let mut current = id;
+        let mut visited = HashSet::from([id]);
+        while let Some(child) = self.children(current).last() {
+            if !visited.insert(child.message.id.as_str()) {
+                break;
+            }
+            current = &child.message.id;
+        }
+        current.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::StoryRole;
+
+    fn message(id: &str) -> StoryMessage {
+        StoryMessage::new(id, StoryRole::Narrator, format!("message {id}"))
+    }
+
+    fn ids(messages: &[StoryMessage]) -> Vec<&str> {
+        messages.iter().map(|m| m.id.as_str()).collect()
+    }
+
+    /// a - b - c, then a fork at b with d
+    fn forked_tree() -> MessageTree {
+        let mut tree = MessageTree::default();
+        for id in ["a", "b", "c"] {
+            tree.append(message(id));
+        }
+        tree.fork("b").unwrap();
+        tree.append(message("d"));
+        tree
+    }
+
+    #[test]
+    fn test_fork_starts_new_branch() {
+        let tree = forked_tree();
+        assert_eq!(ids(&tree.active_path()), vec!["a", "b", "d"]);
+
+        let branches = tree.branches();
+        assert_eq!(branches.len(), 2);
+        assert_eq!(branches[0].leaf_id, "c");
+        assert_eq!(branches[0].fork_id.as_deref(), Some("b"));
+        assert!(!branches[0].is_active);
+        assert!(branches[1].is_active);
+    }
+
+    #[test]
+    fn test_switch_branch_requires_leaf() {
+        let mut tree = forked_tree();
+        tree.switch_branch("c").unwrap();
+        assert_eq!(ids(&tree.active_path()), vec!["a", "b", "c"]);
+        assert_eq!(tree.switch_branch("b"), Err(TreeError::NotALeaf("b".into())));
+        assert_eq!(
+            tree.switch_branch("x"),
+            Err(TreeError::UnknownMessage("x".into()))
+        );
+    }
+
+    #[test]
+    fn test_parent_cycle_does_not_hang() {
+        let node = |id: &str, parent: &str| MessageNode {
+            message: message(id),
+            parent_id: Some(parent.to_string()),
+        };
+        let tree = MessageTree::new(vec![node("a", "b"), node("b", "a")], Some("b".to_string()));
+        assert_eq!(ids(&tree.active_path()), vec!["a", "b"]);
+        assert!(tree.branches().is_empty());
+    }
+
+    #[test]
+    fn test_prune_active_branch_moves_cursor() {
+        let mut tree = forked_tree();
+        assert_eq!(tree.prune_branch("d").unwrap(), vec!["d"]);
+        assert_eq!(ids(&tree.active_path()), vec!["a", "b", "c"]);
+        assert_eq!(tree.prune_branch("c"), Err(TreeError::OnlyBranch));
+    }
+
+    #[test]
+    fn test_prune_removes_whole_unique_segment() {
+        let mut tree = forked_tree();
+        tree.append(message("e"));
+        tree.switch_branch("c").unwrap();
+
+        assert_eq!(tree.prune_branch("e").unwrap(), vec!["e", "d"]);
+        assert_eq!(tree.nodes().len(), 3);
+        assert_eq!(tree.cursor(), Some("c"));
+    }
+
+    #[test]
+    fn test_new_falls_back_to_newest_message() {
+        let nodes = forked_tree().nodes().to_vec();
+        let tree = MessageTree::new(nodes, Some("missing".into()));
+        assert_eq!(tree.cursor(), Some("d"));
+    }
+}
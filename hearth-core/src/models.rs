@@ -1,6 +1,9 @@
 //! Shared data models and types
 
+use crate::sampler::SamplerSettings;
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use thiserror::Error;
 
 // Tag data
 #[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
@@ -40,10 +43,175 @@ pub struct StoryItem {
     pub timestamp: String,
     pub scenario_name: Option<String>,
     pub message_count: u32,
+    /// Generation settings of this story, used instead of the global preset
+    #[serde(default)]
+    pub sampler: Option<SamplerSettings>,
 }
 
-// Character data
-#[derive(Clone, PartialEq, Serialize, Deserialize)]
+// Story message models for interactive storytelling interface
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoryMessage {
+    pub id: String,
+    pub role: StoryRole,
+    /// Content shown in the story; mirrors the selected alternate when there are any
+    pub content: String,
+    /// Generated versions of this message, in the order they were produced
+    #[serde(default)]
+    pub alternates: Vec<MessageAlternate>,
+    #[serde(default)]
+    pub selected_alternate: usize,
+    /// When the message was sent, if known
+    #[serde(default)]
+    pub created_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl StoryMessage {
+    pub fn new(id: impl Into<String>, role: StoryRole, content: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            role,
+            content: content.into(),
+            alternates: Vec::new(),
+            selected_alternate: 0,
+            created_at: None,
+        }
+    }
+
+    /// A freshly generated message, keeping how it was generated as its first alternate
+    pub fn generated(
+        id: impl Into<String>,
+        role: StoryRole,
+        content: impl Into<String>,
+        metadata: GenerationMetadata,
+    ) -> Self {
+        let content = content.into();
+        Self {
+            id: id.into(),
+            role,
+            alternates: vec![MessageAlternate {
+                content: content.clone(),
+                metadata,
+            }],
+            content,
+            selected_alternate: 0,
+            created_at: None,
+        }
+    }
+
+    /// Narrator and character messages are generated and can have alternates
+    pub fn is_generated(&self) -> bool {
+        !matches!(self.role, StoryRole::User { .. })
+    }
+
+    /// Number of versions the user can swipe through
+    pub fn alternate_count(&self) -> usize {
+        self.alternates.len().max(1)
+    }
+
+    /// Keep a newly generated version and select it
+    ///
+    /// The first call also records the existing content as the first alternate so
+    /// regenerating never loses the original reply.
+    pub fn add_alternate(
+        &mut self,
+        content: impl Into<String>,
+        metadata: GenerationMetadata,
+    ) -> Result<(), ValidationError> {
+        if !self.is_generated() {
+            return Err(ValidationError::Invalid(
+                "User messages do not have alternates".to_string(),
+            ));
+        }
+        if self.alternates.is_empty() {
+            self.alternates.push(MessageAlternate {
+                content: self.content.clone(),
+                metadata: GenerationMetadata::default(),
+            });
+        }
+        self.alternates.push(MessageAlternate {
+            content: content.into(),
+            metadata,
+        });
+        self.select_alternate(self.alternates.len() - 1)
+    }
+
+    pub fn select_alternate(&mut self, index: usize) -> Result<(), ValidationError> {
+        let Some(alternate) = self.alternates.get(index) else {
+            return Err(ValidationError::Invalid(format!(
+                "Alternate {index} does not exist"
+            )));
+        };
+        self.content = alternate.content.clone();
+        self.selected_alternate = index;
+        Ok(())
+    }
+
+    /// Select the next alternate; returns `false` if already at the newest one
+    pub fn next_alternate(&mut self) -> bool {
+        self.select_alternate(self.selected_alternate + 1).is_ok()
+    }
+
+    /// Select the previous alternate; returns `false` if already at the first one
+    pub fn previous_alternate(&mut self) -> bool {
+        self.selected_alternate > 0 && self.select_alternate(self.selected_alternate - 1).is_ok()
+    }
+
+    pub fn selected_metadata(&self) -> Option<&GenerationMetadata> {
+        self.alternates
+            .get(self.selected_alternate)
+            .map(|alternate| &alternate.metadata)
+    }
+
+    /// Replace the visible content, keeping the selected alternate in sync
+    pub fn set_content(&mut self, content: impl Into<String>) {
+        self.content = content.into();
+        if let Some(alternate) = self.alternates.get_mut(self.selected_alternate) {
+            alternate.content = self.content.clone();
+        }
+    }
+}
+
+/// Earlier content of a message, saved whenever the message is edited
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MessageRevision {
+    pub id: String,
+    pub message_id: String,
+    /// Content the message had before the edit
+    pub content: String,
+    /// When this content was replaced
+    pub edited_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// One generated version of a message
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MessageAlternate {
+    pub content: String,
+    #[serde(default)]
+    pub metadata: GenerationMetadata,
+}
+
+/// How an alternate was generated
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GenerationMetadata {
+    pub provider: Option<String>,
+    /// Configuration id of the provider that answered, which may be a fallback
+    pub provider_id: Option<String>,
+    pub model: Option<String>,
+    pub created_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub duration_ms: Option<u64>,
+    pub token_count: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum StoryRole {
+    User { name: String },
+    Narrator,
+    Character { name: String },
+}
+
+// Character data - lightweight projection of `Character` for cards and lists
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct CharacterItem {
     pub id: String,
     pub name: String,
@@ -55,6 +223,230 @@ pub struct CharacterItem {
     pub story_count: u32,
 }
 
+/// Maximum length of entity names
+pub const MAX_NAME_LENGTH: usize = 100;
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum ValidationError {
+    #[error("{0} must not be empty")]
+    Empty(&'static str),
+    #[error("{field} is too long ({len} > {max} characters)")]
+    TooLong {
+        field: &'static str,
+        len: usize,
+        max: usize,
+    },
+    #[error("Duplicate {0}: {1}")]
+    Duplicate(&'static str, String),
+    #[error("{0}")]
+    Invalid(String),
+}
+
+// Full character definition - an entity configured for AI-controlled roleplay
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Character {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    #[serde(default)]
+    pub personality: String,
+    pub avatar_url: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Sample exchanges that reinforce the character's voice
+    #[serde(default)]
+    pub example_dialogue: String,
+    /// Preferred scenario to start new stories in
+    #[serde(default)]
+    pub default_scenario_id: Option<String>,
+    #[serde(default)]
+    pub response_style: ResponseStyle,
+    /// Pre-written openings the user can start a story from
+    #[serde(default)]
+    pub default_histories: Vec<DefaultHistory>,
+    #[serde(default)]
+    pub is_favorite: bool,
+    #[serde(default)]
+    pub last_used: Option<String>,
+    #[serde(default)]
+    pub story_count: u32,
+    /// Library entity this character was created from
+    #[serde(default)]
+    pub template_id: Option<String>,
+    /// Character card fields Hearth has no use for, kept so exporting loses nothing
+    #[serde(default)]
+    pub card_extras: serde_json::Map<String, serde_json::Value>,
+}
+
+impl Character {
+    /// Create a character with only the required fields set
+    pub fn new(id: impl Into<String>, name: impl Into<String>, description: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            name: name.into(),
+            description: description.into(),
+            personality: String::new(),
+            avatar_url: None,
+            tags: Vec::new(),
+            example_dialogue: String::new(),
+            default_scenario_id: None,
+            response_style: ResponseStyle::default(),
+            default_histories: Vec::new(),
+            is_favorite: false,
+            last_used: None,
+            story_count: 0,
+            template_id: None,
+            card_extras: serde_json::Map::new(),
+        }
+    }
+
+    /// Check the definition before it is saved
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        if self.id.trim().is_empty() {
+            return Err(ValidationError::Empty("Character id"));
+        }
+        validate_name("Character name", &self.name)?;
+        validate_tags(&self.tags)?;
+
+        let mut history_ids = HashSet::new();
+        for history in &self.default_histories {
+            if !history_ids.insert(history.id.as_str()) {
+                return Err(ValidationError::Duplicate("default history", history.id.clone()));
+            }
+            history.validate()?;
+        }
+        Ok(())
+    }
+
+    /// Lightweight projection used by cards and lists
+    pub fn to_item(&self) -> CharacterItem {
+        CharacterItem::from(self)
+    }
+}
+
+impl From<&Character> for CharacterItem {
+    fn from(character: &Character) -> Self {
+        Self {
+            id: character.id.clone(),
+            name: character.name.clone(),
+            description: character.description.clone(),
+            avatar_url: character.avatar_url.clone(),
+            tags: character.tags.clone(),
+            is_favorite: character.is_favorite,
+            last_used: character.last_used.clone(),
+            story_count: character.story_count,
+        }
+    }
+}
+
+impl From<CharacterItem> for Character {
+    fn from(item: CharacterItem) -> Self {
+        Self {
+            avatar_url: item.avatar_url,
+            tags: item.tags,
+            is_favorite: item.is_favorite,
+            last_used: item.last_used,
+            story_count: item.story_count,
+            ..Self::new(item.id, item.name, item.description)
+        }
+    }
+}
+
+// Preferred length, tone and format of generated replies
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ResponseStyle {
+    pub length: ResponseLength,
+    pub tone: String,
+    pub format: String,
+}
+
+impl Default for ResponseStyle {
+    fn default() -> Self {
+        Self {
+            length: ResponseLength::Medium,
+            tone: String::new(),
+            format: String::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ResponseLength {
+    Short,
+    Medium,
+    Long,
+}
+
+// A pre-written conversation a story can start from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DefaultHistory {
+    pub id: String,
+    pub name: String,
+    pub kind: DefaultHistoryKind,
+    /// Messages may use `{{char}}` and `{{user}}` placeholders
+    pub messages: Vec<StoryMessage>,
+}
+
+impl DefaultHistory {
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        if self.name.trim().is_empty() {
+            return Err(ValidationError::Empty("Default history name"));
+        }
+        match self.kind {
+            DefaultHistoryKind::EmptyStart if !self.messages.is_empty() => Err(
+                ValidationError::Invalid(format!("Default history '{}' is an empty start but has messages", self.name)),
+            ),
+            DefaultHistoryKind::EmptyStart => Ok(()),
+            _ if self.messages.is_empty() => Err(ValidationError::Invalid(format!(
+                "Default history '{}' has no messages",
+                self.name
+            ))),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DefaultHistoryKind {
+    /// No pre-existing context, the conversation begins fresh
+    EmptyStart,
+    /// Exchange that establishes relationship, setting or situation
+    ContextualOpening,
+    /// Ongoing dialogue that drops the user into an existing scene
+    MidConversation,
+    /// Scattered pieces that suggest shared history
+    MemoryFragments,
+}
+
+pub(crate) fn validate_name(field: &'static str, name: &str) -> Result<(), ValidationError> {
+    let len = name.trim().chars().count();
+    if len == 0 {
+        return Err(ValidationError::Empty(field));
+    }
+    if len > MAX_NAME_LENGTH {
+        return Err(ValidationError::TooLong {
+            field,
+            len,
+            max: MAX_NAME_LENGTH,
+        });
+    }
+    Ok(())
+}
+
+pub(crate) fn validate_tags(tags: &[String]) -> Result<(), ValidationError> {
+    let mut seen = HashSet::new();
+    for tag in tags {
+        if tag.trim().is_empty() {
+            return Err(ValidationError::Empty("Tag"));
+        }
+        if !seen.insert(tag.to_lowercase()) {
+            return Err(ValidationError::Duplicate("tag", tag.clone()));
+        }
+    }
+    Ok(())
+}
+
 #[derive(Clone, PartialEq, Serialize, Deserialize)]
 pub enum CharacterFilter {
     All,
@@ -69,7 +461,7 @@ pub enum ViewMode {
 }
 
 // Scenario data
-#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ScenarioItem {
     pub id: String,
     pub name: String,
@@ -140,6 +532,20 @@ impl CardItem for ScenarioItem {
     }
 }
 
+// Count tag usage across items, sorted by count (descending), then by name (ascending)
+pub fn sorted_tag_counts<T: CardItem>(items: &[T]) -> Vec<(String, u32)> {
+    let mut tag_counts: HashMap<String, u32> = HashMap::new();
+    for item in items {
+        for tag in item.tags() {
+            *tag_counts.entry(tag.clone()).or_insert(0) += 1;
+        }
+    }
+
+    let mut sorted_tags: Vec<(String, u32)> = tag_counts.into_iter().collect();
+    sorted_tags.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    sorted_tags
+}
+
 // Flexible metadata for card display
 #[derive(Clone, PartialEq, Serialize, Deserialize)]
 pub struct CardMetadata {
@@ -147,3 +553,79 @@ pub struct CardMetadata {
     pub count: u32,
     pub label: &'static str,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_character_projection_keeps_card_fields() {
+        let mut character = Character::new("1", "Alice", "A cheerful tavern keeper");
+        character.personality = "Warm, secretive".to_string();
+        character.tags = vec!["Fantasy".to_string()];
+        character.is_favorite = true;
+
+        let item = character.to_item();
+        assert_eq!(item.name, "Alice");
+        assert_eq!(item.tags, vec!["Fantasy".to_string()]);
+        assert!(item.is_favorite);
+    }
+
+    #[test]
+    fn test_character_validation() {
+        let mut character = Character::new("1", "  ", "");
+        assert_eq!(character.validate(), Err(ValidationError::Empty("Character name")));
+
+        character.name = "Alice".to_string();
+        character.tags = vec!["Fantasy".to_string(), "fantasy".to_string()];
+        assert!(matches!(character.validate(), Err(ValidationError::Duplicate(..))));
+
+        character.tags.pop();
+        character.default_histories.push(DefaultHistory {
+            id: "opening".to_string(),
+            name: "Tavern opening".to_string(),
+            kind: DefaultHistoryKind::ContextualOpening,
+            messages: Vec::new(),
+        });
+        assert!(matches!(character.validate(), Err(ValidationError::Invalid(_))));
+
+        character.default_histories[0].kind = DefaultHistoryKind::EmptyStart;
+        assert!(character.validate().is_ok());
+    }
+
+    #[test]
+    fn test_alternates_keep_original_and_swipe() {
+        let mut message = StoryMessage::new("1", StoryRole::Narrator, "First");
+        assert_eq!(message.alternate_count(), 1);
+        assert!(!message.next_alternate());
+
+        message
+            .add_alternate("Second", GenerationMetadata::default())
+            .unwrap();
+        assert_eq!(message.alternate_count(), 2);
+        assert_eq!(message.content, "Second");
+
+        assert!(message.previous_alternate());
+        assert_eq!(message.content, "First");
+        assert!(!message.previous_alternate());
+        assert!(message.next_alternate());
+        assert_eq!(message.selected_alternate, 1);
+
+        let mut user = StoryMessage::new(
+            "2",
+            StoryRole::User {
+                name: "Alex".to_string(),
+            },
+            "Hi",
+        );
+        assert!(user.add_alternate("Hello", GenerationMetadata::default()).is_err());
+    }
+
+    #[test]
+    fn test_character_deserializes_with_missing_optional_fields() {
+        let character: Character =
+            serde_json::from_str(r#"{"id":"1","name":"Alice","description":"","avatar_url":null}"#).unwrap();
+        assert_eq!(character.response_style.length, ResponseLength::Medium);
+        assert!(character.default_histories.is_empty());
+    }
+}
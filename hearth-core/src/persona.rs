@@ -0,0 +1,225 @@
+//! User personas
+//!
+//! A persona is an entity configured to represent the user in a story. Personas
+//! carry their own injection settings and can be locked to a story, character or
+//! scenario so the right one is picked automatically.
+
+use crate::models::{validate_name, validate_tags, StoryItem, StoryParticipant, ValidationError};
+use serde::{Deserialize, Serialize};
+
+// Persona - an entity configured for user representation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Persona {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub avatar_url: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub injection: PersonaInjection,
+    /// Where this persona is selected automatically
+    #[serde(default)]
+    pub locks: Vec<PersonaLock>,
+    /// Library entity this persona was created from
+    #[serde(default)]
+    pub template_id: Option<String>,
+}
+
+/// How the persona description is injected into prompts
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "position", rename_all = "snake_case")]
+pub enum PersonaInjection {
+    /// Alongside the character definition in the system prompt
+    #[default]
+    InPrompt,
+    /// As a separate entry `depth` messages from the end of the history
+    AtDepth { depth: u32 },
+    /// Only the persona name is used
+    Disabled,
+}
+
+/// Locking preference that makes a persona the active one in some context
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum PersonaLock {
+    /// Fallback persona when nothing more specific matches
+    Default,
+    /// Used whenever the story includes this character
+    Character { character_id: String },
+    /// Used for this one story
+    Chat { story_id: String },
+    /// Used for stories set in this scenario
+    Scenario { scenario_id: String },
+}
+
+impl PersonaLock {
+    /// Higher values win when several personas match
+    fn priority(&self) -> u8 {
+        match self {
+            PersonaLock::Chat { .. } => 3,
+            PersonaLock::Character { .. } => 2,
+            PersonaLock::Scenario { .. } => 1,
+            PersonaLock::Default => 0,
+        }
+    }
+
+    fn matches(&self, context: &PersonaContext) -> bool {
+        match self {
+            PersonaLock::Default => true,
+            PersonaLock::Chat { story_id } => context.story_id == Some(story_id.as_str()),
+            PersonaLock::Character { character_id } => {
+                context.character_ids.contains(&character_id.as_str())
+            }
+            PersonaLock::Scenario { scenario_id } => {
+                context.scenario_id == Some(scenario_id.as_str())
+            }
+        }
+    }
+}
+
+impl Persona {
+    /// Create an unlocked persona with default injection settings
+    pub fn new(id: impl Into<String>, name: impl Into<String>, description: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            name: name.into(),
+            description: description.into(),
+            avatar_url: None,
+            tags: Vec::new(),
+            injection: PersonaInjection::default(),
+            locks: Vec::new(),
+            template_id: None,
+        }
+    }
+
+    /// Check the persona before it is saved
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        if self.id.trim().is_empty() {
+            return Err(ValidationError::Empty("Persona id"));
+        }
+        validate_name("Persona name", &self.name)?;
+        validate_tags(&self.tags)?;
+
+        for (i, lock) in self.locks.iter().enumerate() {
+            if self.locks[..i].contains(lock) {
+                return Err(ValidationError::Duplicate("persona lock", format!("{lock:?}")));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn is_default(&self) -> bool {
+        self.locks.contains(&PersonaLock::Default)
+    }
+
+    /// Participant entry for stories this persona takes part in
+    pub fn to_participant(&self) -> StoryParticipant {
+        StoryParticipant {
+            id: self.id.clone(),
+            name: self.name.clone(),
+            avatar_url: self.avatar_url.clone(),
+        }
+    }
+
+    /// Best matching lock priority for the context, if any lock matches
+    fn match_priority(&self, context: &PersonaContext) -> Option<u8> {
+        self.locks
+            .iter()
+            .filter(|lock| lock.matches(context))
+            .map(PersonaLock::priority)
+            .max()
+    }
+}
+
+/// The story, characters and scenario a persona is resolved for
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PersonaContext<'a> {
+    pub story_id: Option<&'a str>,
+    pub character_ids: Vec<&'a str>,
+    pub scenario_id: Option<&'a str>,
+}
+
+impl<'a> PersonaContext<'a> {
+    /// Context for an existing story and its participating characters
+    pub fn for_story(story: &'a StoryItem) -> Self {
+        Self {
+            story_id: Some(&story.id),
+            character_ids: story.characters.iter().map(|c| c.id.as_str()).collect(),
+            scenario_id: None,
+        }
+    }
+
+    pub fn with_scenario(mut self, scenario_id: Option<&'a str>) -> Self {
+        self.scenario_id = scenario_id;
+        self
+    }
+
+    /// Pick the active persona
+    ///
+    /// Chat locks win over character locks, which win over scenario locks, which
+    /// win over the default persona. Ties go to the persona listed first.
+    pub fn resolve<'p>(&self, personas: &'p [Persona]) -> Option<&'p Persona> {
+        let mut best: Option<(&Persona, u8)> = None;
+        for persona in personas {
+            if let Some(priority) = persona.match_priority(self) {
+                if best.is_none_or(|(_, best_priority)| priority > best_priority) {
+                    best = Some((persona, priority));
+                }
+            }
+        }
+        best.map(|(persona, _)| persona)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn locked(id: &str, lock: PersonaLock) -> Persona {
+        Persona {
+            locks: vec![lock],
+            ..Persona::new(id, id, "")
+        }
+    }
+
+    #[test]
+    fn test_resolve_prefers_most_specific_lock() {
+        let personas = vec![
+            locked("default", PersonaLock::Default),
+            locked("scenario", PersonaLock::Scenario { scenario_id: "s1".into() }),
+            locked("character", PersonaLock::Character { character_id: "c1".into() }),
+            locked("chat", PersonaLock::Chat { story_id: "story".into() }),
+        ];
+
+        let mut context = PersonaContext {
+            story_id: Some("story"),
+            character_ids: vec!["c1"],
+            scenario_id: Some("s1"),
+        };
+        assert_eq!(context.resolve(&personas).unwrap().id, "chat");
+
+        context.story_id = Some("other");
+        assert_eq!(context.resolve(&personas).unwrap().id, "character");
+
+        context.character_ids.clear();
+        assert_eq!(context.resolve(&personas).unwrap().id, "scenario");
+
+        context.scenario_id = None;
+        assert_eq!(context.resolve(&personas).unwrap().id, "default");
+
+        assert!(context.resolve(&personas[1..]).is_none());
+    }
+
+    #[test]
+    fn test_validate_rejects_duplicate_locks() {
+        let mut persona = locked("p", PersonaLock::Default);
+        assert!(persona.validate().is_ok());
+
+        persona.locks.push(PersonaLock::Default);
+        assert!(matches!(
+            persona.validate(),
+            Err(ValidationError::Duplicate("persona lock", _))
+        ));
+    }
+}
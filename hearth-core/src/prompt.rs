@@ -0,0 +1,551 @@
+//! Prompt assembly
+//!
+//! Turns a story into what is sent to a model. The prompt is made of sections in
+//! a configurable order; each section renders to zero or more chat messages, and
+//! the whole prompt can be flattened into a single text prompt for backends
+//! without a chat API.
+
+use crate::guidance::MessageGuidance;
+use crate::llm::{ChatMessage, ChatRole, CompletionRequest};
+use crate::models::{Character, ResponseLength, ScenarioItem, StoryMessage, StoryRole};
+use crate::persona::{Persona, PersonaInjection};
+use crate::tokens::{count_message, count_messages, TokenCounter};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PromptSection {
+    SystemPrompt,
+    CharacterDefinition,
+    Persona,
+    /// The scenario the story takes place in, i.e. its world
+    Scenario,
+    ExampleDialogue,
+    ChatHistory,
+    Guidance,
+    NarratorInstruction,
+}
+
+impl PromptSection {
+    pub const ALL: [PromptSection; 8] = [
+        PromptSection::SystemPrompt,
+        PromptSection::CharacterDefinition,
+        PromptSection::Persona,
+        PromptSection::Scenario,
+        PromptSection::ExampleDialogue,
+        PromptSection::ChatHistory,
+        PromptSection::Guidance,
+        PromptSection::NarratorInstruction,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PromptSection::SystemPrompt => "System Prompt",
+            PromptSection::CharacterDefinition => "Character Definitions",
+            PromptSection::Persona => "Persona",
+            PromptSection::Scenario => "Scenario",
+            PromptSection::ExampleDialogue => "Example Dialogue",
+            PromptSection::ChatHistory => "Chat History",
+            PromptSection::Guidance => "Guidance",
+            PromptSection::NarratorInstruction => "Narrator Instruction",
+        }
+    }
+}
+
+/// User-editable prompt layout
+///
+/// `{{char}}` and `{{user}}` in any text are replaced with the character's and the
+/// user's names.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PromptConfig {
+    pub system_prompt: String,
+    /// Final instruction telling the model what to write next
+    pub narrator_instruction: String,
+    /// Sections in the order they are sent; sections left out are not sent at all
+    pub sections: Vec<PromptSection>,
+}
+
+impl Default for PromptConfig {
+    fn default() -> Self {
+        Self {
+            system_prompt: "You are the narrator of an interactive story. Write vivid, immersive \
+                            prose and keep every character consistent with their definition."
+                .to_string(),
+            narrator_instruction: "Continue the story from {{user}}'s last message. Narrate what \
+                                   happens next and voice the characters where they speak, but \
+                                   never write {{user}}'s actions or words."
+                .to_string(),
+            sections: PromptSection::ALL.to_vec(),
+        }
+    }
+}
+
+/// One step of fitting a prompt into the context window
+#[derive(Debug, Clone, Copy)]
+enum TrimStep {
+    Drop(PromptSection),
+    /// Leave out the oldest history messages, down to the last one
+    TrimHistory,
+}
+
+/// Least important content first; the system prompt, guidance, the narrator
+/// instruction and the latest message are always kept
+const TRIM_ORDER: [TrimStep; 5] = [
+    TrimStep::Drop(PromptSection::ExampleDialogue),
+    TrimStep::TrimHistory,
+    TrimStep::Drop(PromptSection::Scenario),
+    TrimStep::Drop(PromptSection::Persona),
+    TrimStep::Drop(PromptSection::CharacterDefinition),
+];
+
+/// What was left out to fit a prompt into its token budget
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TrimReport {
+    pub dropped_sections: Vec<PromptSection>,
+    /// Number of oldest history messages left out
+    pub dropped_messages: usize,
+    pub prompt_tokens: usize,
+    pub budget: usize,
+}
+
+impl TrimReport {
+    pub fn is_trimmed(&self) -> bool {
+        !self.dropped_sections.is_empty() || self.dropped_messages > 0
+    }
+
+    /// False if the prompt is still too long after trimming everything allowed
+    pub fn fits(&self) -> bool {
+        self.prompt_tokens <= self.budget
+    }
+
+    /// Short description for the user, e.g. "12 older messages, Example Dialogue"
+    pub fn summary(&self) -> String {
+        let mut items = Vec::new();
+        if self.dropped_messages > 0 {
+            let plural = if self.dropped_messages == 1 { "" } else { "s" };
+            items.push(format!("{} older message{plural}", self.dropped_messages));
+        }
+        items.extend(self.dropped_sections.iter().map(|s| s.label().to_string()));
+        items.join(", ")
+    }
+}
+
+/// Messages one section contributed to the prompt
+#[derive(Debug, Clone, PartialEq)]
+pub struct PromptPart {
+    pub section: PromptSection,
+    pub messages: Vec<ChatMessage>,
+}
+
+/// An assembled prompt, ready to be sent as chat messages or as text
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Prompt {
+    pub parts: Vec<PromptPart>,
+}
+
+impl Prompt {
+    pub fn to_messages(&self) -> Vec<ChatMessage> {
+        self.parts
+            .iter()
+            .flat_map(|part| part.messages.iter().cloned())
+            .collect()
+    }
+
+    /// Single prompt for text-completion backends, one paragraph per message
+    pub fn to_text(&self) -> String {
+        self.parts
+            .iter()
+            .flat_map(|part| part.messages.iter())
+            .map(|message| message.content.as_str())
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+
+    pub fn to_request(&self) -> CompletionRequest {
+        CompletionRequest::new(self.to_messages())
+    }
+
+    pub fn token_count(&self, counter: &dyn TokenCounter) -> usize {
+        self.parts
+            .iter()
+            .map(|part| count_messages(counter, &part.messages))
+            .sum()
+    }
+
+    /// Leave out the least important content until the prompt fits in `budget` tokens
+    pub fn fit(&mut self, counter: &dyn TokenCounter, budget: usize) -> TrimReport {
+        let mut report = TrimReport {
+            budget,
+            ..TrimReport::default()
+        };
+        let mut total = self.token_count(counter);
+
+        for step in TRIM_ORDER {
+            if total <= budget {
+                break;
+            }
+            match step {
+                TrimStep::Drop(section) => {
+                    if let Some(index) = self.parts.iter().position(|p| p.section == section) {
+                        let part = self.parts.remove(index);
+                        total -= count_messages(counter, &part.messages);
+                        report.dropped_sections.push(section);
+                    }
+                }
+                TrimStep::TrimHistory => {
+                    let Some(part) = self
+                        .parts
+                        .iter_mut()
+                        .find(|p| p.section == PromptSection::ChatHistory)
+                    else {
+                        continue;
+                    };
+                    // System messages in the history are injected personas and stay
+                    while total > budget {
+                        let conversation: Vec<usize> = part
+                            .messages
+                            .iter()
+                            .enumerate()
+                            .filter(|(_, m)| m.role != ChatRole::System)
+                            .map(|(i, _)| i)
+                            .collect();
+                        if conversation.len() <= 1 {
+                            break;
+                        }
+                        let message = part.messages.remove(conversation[0]);
+                        total -= count_message(counter, &message);
+                        report.dropped_messages += 1;
+                    }
+                }
+            }
+        }
+
+        report.prompt_tokens = total;
+        report
+    }
+}
+
+/// Collects everything a story prompt is made of
+///
+/// ```ignore
+/// let prompt = PromptBuilder::new(&config)
+///     .characters(&characters)
+///     .persona(persona.as_ref())
+///     .history(&path)
+///     .build();
+/// ```
+pub struct PromptBuilder<'a> {
+    config: &'a PromptConfig,
+    characters: &'a [Character],
+    persona: Option<&'a Persona>,
+    scenario: Option<&'a ScenarioItem>,
+    history: &'a [StoryMessage],
+    guidance: Vec<&'a MessageGuidance>,
+}
+
+impl<'a> PromptBuilder<'a> {
+    pub fn new(config: &'a PromptConfig) -> Self {
+        Self {
+            config,
+            characters: &[],
+            persona: None,
+            scenario: None,
+            history: &[],
+            guidance: Vec::new(),
+        }
+    }
+
+    pub fn characters(mut self, characters: &'a [Character]) -> Self {
+        self.characters = characters;
+        self
+    }
+
+    pub fn persona(mut self, persona: Option<&'a Persona>) -> Self {
+        self.persona = persona;
+        self
+    }
+
+    pub fn scenario(mut self, scenario: Option<&'a ScenarioItem>) -> Self {
+        self.scenario = scenario;
+        self
+    }
+
+    /// Active path of the story, oldest message first
+    pub fn history(mut self, history: &'a [StoryMessage]) -> Self {
+        self.history = history;
+        self
+    }
+
+    /// Guidance for the reply being generated, see `guidance_for_next_generation`
+    pub fn guidance(mut self, guidance: Vec<&'a MessageGuidance>) -> Self {
+        self.guidance = guidance;
+        self
+    }
+
+    pub fn build(&self) -> Prompt {
+        let parts = self
+            .config
+            .sections
+            .iter()
+            .map(|&section| PromptPart {
+                section,
+                messages: self.render(section),
+            })
+            .filter(|part| !part.messages.is_empty())
+            .collect();
+        Prompt { parts }
+    }
+
+    fn user_name(&self) -> &str {
+        self.persona.map_or("User", |p| p.name.as_str())
+    }
+
+    /// Name `{{char}}` stands for outside of a character's own definition
+    fn char_name(&self) -> &str {
+        self.characters.first().map_or("Narrator", |c| c.name.as_str())
+    }
+
+    fn substitute(&self, text: &str, char_name: &str) -> String {
+        text.replace("{{char}}", char_name)
+            .replace("{{user}}", self.user_name())
+    }
+
+    fn system(&self, text: &str) -> Vec<ChatMessage> {
+        let text = self.substitute(text.trim(), self.char_name());
+        if text.is_empty() {
+            return Vec::new();
+        }
+        vec![ChatMessage::new(ChatRole::System, text)]
+    }
+
+    fn render(&self, section: PromptSection) -> Vec<ChatMessage> {
+        match section {
+            PromptSection::SystemPrompt => self.system(&self.config.system_prompt),
+            PromptSection::CharacterDefinition => self
+                .characters
+                .iter()
+                .map(|character| {
+                    ChatMessage::new(ChatRole::System, self.character_definition(character))
+                })
+                .collect(),
+            PromptSection::Persona => match self.persona {
+                Some(persona) if persona.injection == PersonaInjection::InPrompt => {
+                    vec![self.persona_message(persona)]
+                }
+                _ => Vec::new(),
+            },
+            PromptSection::Scenario => self
+                .scenario
+                .map(|scenario| {
+                    self.system(&format!("[Scenario: {}]\n{}", scenario.name, scenario.description))
+                })
+                .unwrap_or_default(),
+            PromptSection::ExampleDialogue => self
+                .characters
+                .iter()
+                .filter(|c| !c.example_dialogue.trim().is_empty())
+                .map(|c| {
+                    let dialogue = self.substitute(c.example_dialogue.trim(), &c.name);
+                    ChatMessage::new(
+                        ChatRole::System,
+                        format!("[Example dialogue for {}]\n{dialogue}", c.name),
+                    )
+                })
+                .collect(),
+            PromptSection::ChatHistory => self.history_messages(),
+            PromptSection::Guidance => self
+                .guidance
+                .iter()
+                .map(|g| ChatMessage::new(ChatRole::System, g.to_system_instruction()))
+                .collect(),
+            PromptSection::NarratorInstruction => self.system(&self.config.narrator_instruction),
+        }
+    }
+
+    fn character_definition(&self, character: &Character) -> String {
+        let mut lines = vec![format!("[Character: {}]", character.name)];
+        for text in [&character.description, &character.personality] {
+            if !text.trim().is_empty() {
+                lines.push(self.substitute(text.trim(), &character.name));
+            }
+        }
+        let style = &character.response_style;
+        if !style.tone.trim().is_empty() {
+            lines.push(format!("Tone: {}", style.tone.trim()));
+        }
+        if !style.format.trim().is_empty() {
+            lines.push(format!("Format: {}", style.format.trim()));
+        }
+        match style.length {
+            ResponseLength::Short => lines.push("Keep replies short.".to_string()),
+            ResponseLength::Medium => {}
+            ResponseLength::Long => lines.push("Write long, detailed replies.".to_string()),
+        }
+        lines.join("\n")
+    }
+
+    fn persona_message(&self, persona: &Persona) -> ChatMessage {
+        let mut text = format!("[User: {}]", persona.name);
+        if !persona.description.trim().is_empty() {
+            text.push('\n');
+            text.push_str(&self.substitute(persona.description.trim(), self.char_name()));
+        }
+        ChatMessage::new(ChatRole::System, text)
+    }
+
+    /// The conversation, with an `AtDepth` persona inserted `depth` messages from the end
+    fn history_messages(&self) -> Vec<ChatMessage> {
+        let mut messages: Vec<ChatMessage> = self
+            .history
+            .iter()
+            .map(|message| match &message.role {
+                StoryRole::User { name } => {
+                    ChatMessage::new(ChatRole::User, format!("{name}: {}", message.content))
+                }
+                StoryRole::Character { name } => {
+                    ChatMessage::new(ChatRole::Assistant, format!("{name}: {}", message.content))
+                }
+                StoryRole::Narrator => {
+                    ChatMessage::new(ChatRole::Assistant, message.content.clone())
+                }
+            })
+            .collect();
+
+        if let Some(persona) = self.persona {
+            if let PersonaInjection::AtDepth { depth } = persona.injection {
+                let index = messages.len().saturating_sub(depth as usize);
+                messages.insert(index, self.persona_message(persona));
+            }
+        }
+        messages
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::guidance::{guidance_for_next_generation, LOCAL_USER_ID};
+    use crate::sample::{sample_characters, sample_personas, sample_scenarios, sample_story_messages};
+    use crate::tokens::HeuristicCounter;
+
+    fn alice() -> Character {
+        let mut alice = Character::from(sample_characters()[0].clone());
+        alice.personality = "Warm and teasing, but guarded about her adventuring days.".to_string();
+        alice.example_dialogue =
+            "{{user}}: What's good tonight?\n{{char}}: *wipes the counter* The stew, if you're brave."
+                .to_string();
+        alice.response_style.tone = "Cozy".to_string();
+        alice
+    }
+
+    fn scenario(name: &str) -> ScenarioItem {
+        sample_scenarios()
+            .into_iter()
+            .find(|s| s.name == name)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_tavern_story_chat_prompt() {
+        let config = PromptConfig::default();
+        let characters = vec![alice()];
+        let personas = sample_personas();
+        let scenario = scenario("Tavern Adventure");
+        let history = sample_story_messages("Alex");
+        let guidance = vec![MessageGuidance::new(
+            "7",
+            LOCAL_USER_ID,
+            Some("Forest Guide".to_string()),
+            "Hint that she knows the user's mentor",
+        )];
+
+        let prompt = PromptBuilder::new(&config)
+            .characters(&characters)
+            .persona(Some(&personas[0]))
+            .scenario(Some(&scenario))
+            .history(&history)
+            .guidance(guidance_for_next_generation(&history, &guidance))
+            .build();
+
+        let rendered: Vec<String> = prompt
+            .to_messages()
+            .iter()
+            .map(|m| format!("<{:?}>\n{}", m.role, m.content))
+            .collect();
+        insta::assert_snapshot!(rendered.join("\n\n"));
+    }
+
+    #[test]
+    fn test_academy_story_text_prompt() {
+        let config = PromptConfig::default();
+        let personas = sample_personas();
+        let scenario = scenario("Magical Academy");
+        let history = sample_story_messages("Detective Sage");
+
+        let prompt = PromptBuilder::new(&config)
+            .persona(Some(&personas[1]))
+            .scenario(Some(&scenario))
+            .history(&history)
+            .build();
+
+        insta::assert_snapshot!(prompt.to_text());
+    }
+
+    #[test]
+    fn test_fit_drops_lowest_priority_first() {
+        let config = PromptConfig::default();
+        let characters = vec![alice()];
+        let scenario = scenario("Tavern Adventure");
+        let history = sample_story_messages("Alex");
+        let mut prompt = PromptBuilder::new(&config)
+            .characters(&characters)
+            .scenario(Some(&scenario))
+            .history(&history)
+            .build();
+        let counter = HeuristicCounter;
+        let full = prompt.token_count(&counter);
+
+        let mut untouched = prompt.clone();
+        let report = untouched.fit(&counter, full);
+        assert!(!report.is_trimmed());
+        assert_eq!(untouched, prompt);
+
+        let report = prompt.fit(&counter, full - 100);
+        assert!(report.fits());
+        assert_eq!(report.dropped_sections, vec![PromptSection::ExampleDialogue]);
+        assert!(report.dropped_messages > 0);
+        assert_eq!(prompt.token_count(&counter), report.prompt_tokens);
+        let last = prompt.to_messages().into_iter().rev().nth(1).unwrap();
+        assert!(last.content.starts_with("Alex: *I remember"));
+
+        // Even the last message and the system prompt do not fit: trim all that may go
+        let report = prompt.fit(&counter, 10);
+        assert!(!report.fits());
+        assert_eq!(
+            report.dropped_sections,
+            vec![PromptSection::Scenario, PromptSection::CharacterDefinition]
+        );
+        assert_eq!(prompt.parts.len(), 3);
+    }
+
+    #[test]
+    fn test_sections_follow_configured_order() {
+        let config = PromptConfig {
+            sections: vec![PromptSection::ChatHistory, PromptSection::SystemPrompt],
+            ..PromptConfig::default()
+        };
+        let characters = vec![alice()];
+        let history = sample_story_messages("Alex");
+        let prompt = PromptBuilder::new(&config)
+            .characters(&characters)
+            .history(&history)
+            .build();
+
+        let sections: Vec<PromptSection> = prompt.parts.iter().map(|p| p.section).collect();
+        assert_eq!(sections, vec![PromptSection::ChatHistory, PromptSection::SystemPrompt]);
+        let messages = prompt.to_messages();
+        assert_eq!(messages.len(), history.len() + 1);
+        assert_eq!(messages[1].role, ChatRole::User);
+        assert!(messages[1].content.starts_with("Alex: "));
+    }
+}
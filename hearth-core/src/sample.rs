@@ -1,6 +1,9 @@
 //! Sample data for demo purposes
+//!
+//! Used as a fixture to seed an empty database on first launch.
 
 use crate::models::*;
+use crate::persona::*;
 use std::collections::HashMap;
 
 // Character sample data
@@ -484,6 +487,7 @@ pub fn sample_stories() -> Vec<StoryItem> {
             timestamp: "2 minutes ago".to_string(),
             scenario_name: Some("Medieval Tavern".to_string()),
             message_count: 47,
+            sampler: None,
         },
         StoryItem {
             id: "2".to_string(),
@@ -515,6 +519,7 @@ pub fn sample_stories() -> Vec<StoryItem> {
             timestamp: "15 minutes ago".to_string(),
             scenario_name: Some("Magical Academy".to_string()),
             message_count: 124,
+            sampler: None,
         },
         StoryItem {
             id: "3".to_string(),
@@ -546,6 +551,7 @@ pub fn sample_stories() -> Vec<StoryItem> {
             timestamp: "1 hour ago".to_string(),
             scenario_name: Some("Space Station Alpha".to_string()),
             message_count: 89,
+            sampler: None,
         },
         StoryItem {
             id: "4".to_string(),
@@ -582,6 +588,7 @@ pub fn sample_stories() -> Vec<StoryItem> {
             timestamp: "3 hours ago".to_string(),
             scenario_name: Some("Cyberpunk 2087".to_string()),
             message_count: 203,
+            sampler: None,
         },
         StoryItem {
             id: "5".to_string(),
@@ -603,6 +610,7 @@ pub fn sample_stories() -> Vec<StoryItem> {
             timestamp: "Yesterday".to_string(),
             scenario_name: Some("Modern Romance".to_string()),
             message_count: 32,
+            sampler: None,
         },
         StoryItem {
             id: "6".to_string(),
@@ -634,6 +642,76 @@ pub fn sample_stories() -> Vec<StoryItem> {
             timestamp: "2 days ago".to_string(),
             scenario_name: Some("Medieval Kingdom".to_string()),
             message_count: 156,
+            sampler: None,
         }
     ]
 }
+
+// Persona sample data
+pub fn sample_personas() -> Vec<Persona> {
+    vec![
+        Persona {
+            id: "persona_1".to_string(),
+            name: "Alex".to_string(),
+            description: "A curious wanderer who is always ready for the next adventure".to_string(),
+            avatar_url: None,
+            tags: vec!["Adventurer".to_string(), "Curious".to_string()],
+            injection: PersonaInjection::InPrompt,
+            locks: vec![PersonaLock::Default],
+            template_id: None,
+        },
+        Persona {
+            id: "persona_2".to_string(),
+            name: "Detective Sage".to_string(),
+            description: "A sharp-eyed investigator with a knack for noticing what others miss".to_string(),
+            avatar_url: None,
+            tags: vec!["Mystery".to_string(), "Investigator".to_string()],
+            injection: PersonaInjection::AtDepth { depth: 4 },
+            locks: vec![PersonaLock::Scenario {
+                scenario_id: "2".to_string(),
+            }],
+            template_id: None,
+        },
+    ]
+}
+
+// Opening messages used for every sample story
+pub fn sample_story_messages(user_name: &str) -> Vec<StoryMessage> {
+    vec![
+        StoryMessage::new(
+            "1",
+            StoryRole::Narrator,
+            "You find yourself standing at the edge of an ancient forest. The towering trees whisper secrets in the wind, and a narrow path winds deeper into the shadows.",
+        ),
+        StoryMessage::new(
+            "2",
+            StoryRole::User { name: user_name.to_string() },
+            "*I step carefully onto the forest path, scanning the ground for tracks while keeping my hand near my weapon* This place feels alive... I need to stay alert.",
+        ),
+        StoryMessage::new(
+            "3",
+            StoryRole::Character { name: "Forest Guide".to_string() },
+            "*An elderly woman emerges from the bushes, her walking stick tapping against the ground as she approaches* Wait, traveler! That path leads to the Heart of the Wilds. Are you certain you're prepared for such a journey?",
+        ),
+        StoryMessage::new(
+            "4",
+            StoryRole::User { name: user_name.to_string() },
+            "*I think to myself \"Should I trust this stranger?\" before responding carefully* What dangers should I be aware of? Do you have any advice for a traveler like myself?",
+        ),
+        StoryMessage::new(
+            "5",
+            StoryRole::Character { name: "Forest Guide".to_string() },
+            "*She leans heavily on her gnarled staff and points toward the dark path ahead* Many have ventured into those depths, young one. The forest itself is alive, and it does not welcome intruders. Trust the silver moonlight, and beware the whispering stones.",
+        ),
+        StoryMessage::new(
+            "6",
+            StoryRole::Narrator,
+            "As the old woman's words fade into the forest air, a sudden chill runs down your spine. The wind picks up, rustling the leaves overhead, and somewhere in the distance you hear the haunting call of an unknown creature.",
+        ),
+        StoryMessage::new(
+            "7",
+            StoryRole::User { name: user_name.to_string() },
+            "*I remember what my mentor always said \"Knowledge is the best weapon\" and decide to heed her advice* Thank you for the warning. I'll be careful and watch for the silver moonlight.",
+        ),
+    ]
+}
@@ -0,0 +1,186 @@
+//! Sampler presets
+//!
+//! A preset is a named set of sampling options. One preset is used for every
+//! story unless a story keeps its own settings. Options a provider does not
+//! support are left out of its requests.
+
+use serde::{Deserialize, Serialize};
+
+/// Sampling options for generation; `None` leaves the provider's default
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SamplerSettings {
+    pub temperature: Option<f32>,
+    pub top_p: Option<f32>,
+    pub top_k: Option<u32>,
+    pub min_p: Option<f32>,
+    pub repetition_penalty: Option<f32>,
+    pub frequency_penalty: Option<f32>,
+    pub presence_penalty: Option<f32>,
+    pub seed: Option<i64>,
+    /// Generation stops at any of these
+    pub stop: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SamplerPreset {
+    pub name: String,
+    #[serde(default)]
+    pub settings: SamplerSettings,
+}
+
+/// Preset used when none is selected; sends nothing beyond the provider settings
+pub const DEFAULT_PRESET: &str = "Default";
+
+impl SamplerPreset {
+    pub fn new(name: impl Into<String>, settings: SamplerSettings) -> Self {
+        Self {
+            name: name.into(),
+            settings,
+        }
+    }
+}
+
+/// Presets that ship with Hearth
+pub fn builtin_presets() -> Vec<SamplerPreset> {
+    vec![
+        SamplerPreset::new(DEFAULT_PRESET, SamplerSettings::default()),
+        SamplerPreset::new(
+            "Balanced",
+            SamplerSettings {
+                temperature: Some(0.8),
+                top_p: Some(0.95),
+                min_p: Some(0.05),
+                repetition_penalty: Some(1.1),
+                ..SamplerSettings::default()
+            },
+        ),
+        SamplerPreset::new(
+            "Creative",
+            SamplerSettings {
+                temperature: Some(1.1),
+                min_p: Some(0.1),
+                repetition_penalty: Some(1.05),
+                presence_penalty: Some(0.3),
+                ..SamplerSettings::default()
+            },
+        ),
+        SamplerPreset::new(
+            "Precise",
+            SamplerSettings {
+                temperature: Some(0.5),
+                top_p: Some(0.9),
+                top_k: Some(40),
+                repetition_penalty: Some(1.15),
+                ..SamplerSettings::default()
+            },
+        ),
+    ]
+}
+
+/// Built-in presets together with the user's own, and the one selected globally
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SamplerPresets {
+    custom: Vec<SamplerPreset>,
+    /// Name of the preset used by stories without their own settings
+    pub selected: Option<String>,
+}
+
+impl SamplerPresets {
+    pub fn get(&self, name: &str) -> Option<SamplerPreset> {
+        self.custom
+            .iter()
+            .find(|p| p.name == name)
+            .cloned()
+            .or_else(|| builtin_presets().into_iter().find(|p| p.name == name))
+    }
+
+    /// Every available preset, built-ins first
+    pub fn all(&self) -> Vec<SamplerPreset> {
+        let mut presets: Vec<SamplerPreset> = builtin_presets()
+            .into_iter()
+            .map(|builtin| self.get(&builtin.name).unwrap_or(builtin))
+            .collect();
+        presets.extend(
+            self.custom
+                .iter()
+                .filter(|p| !Self::is_builtin(&p.name))
+                .cloned(),
+        );
+        presets
+    }
+
+    /// Add a preset, replacing any user preset with the same name
+    pub fn save(&mut self, preset: SamplerPreset) {
+        match self.custom.iter_mut().find(|p| p.name == preset.name) {
+            Some(existing) => *existing = preset,
+            None => self.custom.push(preset),
+        }
+    }
+
+    /// Remove a user preset, bringing back the built-in it overrode
+    pub fn remove(&mut self, name: &str) -> bool {
+        let before = self.custom.len();
+        self.custom.retain(|p| p.name != name);
+        if self.selected.as_deref() == Some(name) && self.get(name).is_none() {
+            self.selected = None;
+        }
+        self.custom.len() != before
+    }
+
+    pub fn is_builtin(name: &str) -> bool {
+        builtin_presets().iter().any(|p| p.name == name)
+    }
+
+    pub fn is_custom(&self, name: &str) -> bool {
+        self.custom.iter().any(|p| p.name == name)
+    }
+
+    /// Settings of the globally selected preset
+    pub fn active(&self) -> SamplerSettings {
+        self.selected
+            .as_deref()
+            .and_then(|name| self.get(name))
+            .map(|preset| preset.settings)
+            .unwrap_or_default()
+    }
+
+    /// Settings for a story, which may replace the global preset with its own
+    pub fn for_story(&self, story_sampler: Option<&SamplerSettings>) -> SamplerSettings {
+        story_sampler.cloned().unwrap_or_else(|| self.active())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_story_settings_replace_global_preset() {
+        let mut presets = SamplerPresets::default();
+        assert_eq!(presets.active(), SamplerSettings::default());
+
+        presets.selected = Some("Precise".to_string());
+        assert_eq!(presets.active().top_k, Some(40));
+
+        let story = SamplerSettings {
+            seed: Some(7),
+            ..SamplerSettings::default()
+        };
+        assert_eq!(presets.for_story(Some(&story)), story);
+        assert_eq!(presets.for_story(None).top_k, Some(40));
+    }
+
+    #[test]
+    fn test_removing_selected_preset_falls_back_to_default() {
+        let mut presets = SamplerPresets::default();
+        presets.save(SamplerPreset::new("Mine", SamplerSettings { seed: Some(1), ..SamplerSettings::default() }));
+        presets.selected = Some("Mine".to_string());
+        assert_eq!(presets.all().len(), 5);
+
+        assert!(presets.remove("Mine"));
+        assert_eq!(presets.selected, None);
+        assert_eq!(presets.active(), SamplerSettings::default());
+    }
+}
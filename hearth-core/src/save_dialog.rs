@@ -0,0 +1,115 @@
+//! Saving exported files where the user chooses
+//!
+//! Desktop asks with a native save dialog, the web downloads through a link
+//! and Android writes into the first downloads folder that accepts the file.
+
+use std::io;
+
+/// A file offered to the user
+#[derive(Debug, Clone)]
+pub struct SaveFile {
+    /// Title of the save dialog
+    pub title: String,
+    /// Suggested name, including the extension
+    pub file_name: String,
+    /// Name of the file type in the dialog, such as "Text Files"
+    pub kind: String,
+    pub extension: String,
+    pub mime_type: String,
+    pub content: Vec<u8>,
+}
+
+/// Save a file, returning where it went or `None` if the user cancelled
+pub async fn save_with_dialog(file: SaveFile) -> io::Result<Option<String>> {
+    #[cfg(target_arch = "wasm32")]
+    {
+        download_web(file).map(Some)
+    }
+    #[cfg(target_os = "android")]
+    {
+        save_android(file).map(Some)
+    }
+    #[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+    {
+        save_native(file).await
+    }
+}
+
+#[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+async fn save_native(file: SaveFile) -> io::Result<Option<String>> {
+    let Some(handle) = rfd::AsyncFileDialog::new()
+        .set_title(&file.title)
+        .set_file_name(&file.file_name)
+        .add_filter(&file.kind, &[file.extension.as_str()])
+        .add_filter("All Files", &["*"])
+        .save_file()
+        .await
+    else {
+        return Ok(None);
+    };
+    let path = handle.path();
+    std::fs::write(path, &file.content)?;
+    log::info!("Saved {} to: {}", file.file_name, path.display());
+    Ok(Some(path.display().to_string()))
+}
+
+#[cfg(target_arch = "wasm32")]
+fn download_web(file: SaveFile) -> io::Result<String> {
+    use wasm_bindgen::JsCast;
+    use web_sys::{window, Blob, BlobPropertyBag, Url};
+
+    let failed = |what: &str| io::Error::other(format!("Failed to {what}"));
+    let document = window()
+        .and_then(|window| window.document())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No document available"))?;
+
+    let blob_parts = js_sys::Array::new();
+    blob_parts.push(&js_sys::Uint8Array::from(file.content.as_slice()));
+    let blob_options = BlobPropertyBag::new();
+    blob_options.set_type(&file.mime_type);
+    let blob = Blob::new_with_u8_array_sequence_and_options(&blob_parts, &blob_options)
+        .map_err(|_| failed("create blob"))?;
+    let url = Url::create_object_url_with_blob(&blob).map_err(|_| failed("create object URL"))?;
+
+    let link = document
+        .create_element("a")
+        .map_err(|_| failed("create anchor element"))?;
+    link.set_attribute("href", &url).map_err(|_| failed("set href"))?;
+    link.set_attribute("download", &file.file_name)
+        .map_err(|_| failed("set download attribute"))?;
+    link.dyn_into::<web_sys::HtmlElement>()
+        .map_err(|_| failed("cast to HtmlElement"))?
+        .click();
+    Url::revoke_object_url(&url).map_err(|_| failed("revoke object URL"))?;
+
+    Ok(file.file_name)
+}
+
+#[cfg(target_os = "android")]
+fn save_android(file: SaveFile) -> io::Result<String> {
+    // Android has no save dialog, so try the usual user-visible folders in turn
+    let folders = [
+        std::env::var("EXTERNAL_STORAGE")
+            .ok()
+            .map(|p| std::path::PathBuf::from(p).join("Download")),
+        Some(std::path::PathBuf::from("/storage/emulated/0/Download")),
+        dirs::document_dir().map(|p| p.join("Downloads")),
+        Some(std::path::PathBuf::from(".")),
+    ];
+
+    let mut last_error = io::Error::new(io::ErrorKind::NotFound, "No accessible storage location found");
+    for folder in folders.into_iter().flatten() {
+        if std::fs::create_dir_all(&folder).is_err() {
+            continue;
+        }
+        let path = folder.join(&file.file_name);
+        match std::fs::write(&path, &file.content) {
+            Ok(()) => {
+                log::info!("Saved {} to: {}", file.file_name, path.display());
+                return Ok(path.display().to_string());
+            }
+            Err(e) => last_error = e,
+        }
+    }
+    Err(last_error)
+}
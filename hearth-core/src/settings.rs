@@ -1,5 +1,9 @@
-use crate::{Storage, StorageError};
+use crate::{
+    credential_name, credential_reference, CredentialError, CredentialStore, PromptConfig, RetryPolicy,
+    SamplerPresets, Storage, StorageError, TemplateRegistry,
+};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 use thiserror::Error;
 
@@ -18,6 +22,23 @@ pub enum SettingsError {
     Deserialization(String),
     #[error("Backend not found: {0}")]
     BackendNotFound(String),
+    #[error("Credential error: {0}")]
+    Credentials(#[from] CredentialError),
+}
+
+/// Why a provider configuration cannot be used
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum ProviderConfigError {
+    #[error("Name is required")]
+    MissingName,
+    #[error("Model is required")]
+    MissingModel,
+    #[error("Base URL is required for custom providers")]
+    MissingBaseUrl,
+    #[error("Invalid base URL: {0}")]
+    InvalidBaseUrl(String),
+    #[error("An API key is required for {0}")]
+    MissingApiKey(String),
 }
 
 pub type BackendId = String;
@@ -33,6 +54,12 @@ pub struct AppSettings {
     pub theme: Theme,
     pub ui_preferences: UiPreferences,
     pub chat_preferences: ChatPreferences,
+    #[serde(default)]
+    pub prompt: PromptConfig,
+    #[serde(default)]
+    pub instruct_templates: TemplateRegistry,
+    #[serde(default)]
+    pub sampler_presets: SamplerPresets,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -40,6 +67,11 @@ pub struct LocalBackendConfig {
     pub database_path: Option<PathBuf>, // None = default path
     pub llm_providers: Vec<LlmProviderConfig>,
     pub selected_llm_provider: Option<String>, // ID of selected provider
+    /// IDs of providers to try, in order, when the selected one fails
+    #[serde(default)]
+    pub fallback_providers: Vec<String>,
+    #[serde(default)]
+    pub retry_policy: RetryPolicy,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -74,6 +106,14 @@ pub struct LlmProviderSettings {
     pub model: String,
     pub max_tokens: Option<u32>,
     pub temperature: Option<f32>,
+    /// Context window of the model in tokens; `None` uses the model's known size
+    #[serde(default)]
+    pub context_size: Option<u32>,
+    /// Name of the instruct template for text completion; `None` uses the chat API
+    ///
+    /// Only Ollama and custom servers offer raw completion, other providers ignore it.
+    #[serde(default)]
+    pub instruct_template: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -109,6 +149,64 @@ impl Default for AppSettings {
             theme: Theme::Dark,
             ui_preferences: UiPreferences::default(),
             chat_preferences: ChatPreferences::default(),
+            prompt: PromptConfig::default(),
+            instruct_templates: TemplateRegistry::default(),
+            sampler_presets: SamplerPresets::default(),
+        }
+    }
+}
+
+impl AppSettings {
+    /// Every secret field, with the name its secret is stored under
+    fn secret_fields(&mut self) -> Vec<(String, &mut Option<String>)> {
+        let mut fields = Vec::new();
+        if let Some(local) = self.local_backend.as_mut() {
+            fields.extend(
+                local
+                    .llm_providers
+                    .iter_mut()
+                    .map(|p| (format!("llm/{}", p.id), &mut p.config.api_key)),
+            );
+        }
+        fields.extend(
+            self.remote_backends
+                .iter_mut()
+                .map(|b| (format!("backend/{}", b.id), &mut b.auth_token)),
+        );
+        fields
+    }
+
+    /// Replace plaintext secrets with credential references, returning the secrets
+    pub fn seal_secrets(&mut self) -> BTreeMap<String, String> {
+        let mut secrets = BTreeMap::new();
+        for (name, field) in self.secret_fields() {
+            match field.take() {
+                Some(value) if value.is_empty() => {}
+                Some(value) if credential_name(&value).is_some() => *field = Some(value),
+                Some(value) => {
+                    *field = Some(credential_reference(&name));
+                    secrets.insert(name, value);
+                }
+                None => {}
+            }
+        }
+        secrets
+    }
+
+    /// Replace credential references with their secrets; a locked store leaves them in place
+    pub fn resolve_secrets(&mut self, credentials: &CredentialStore) {
+        if credentials.is_locked() {
+            return;
+        }
+        for (_, field) in self.secret_fields() {
+            let Some(name) = field.as_deref().and_then(credential_name) else {
+                continue;
+            };
+            let secret = credentials.get(name).map(str::to_string);
+            if secret.is_none() {
+                log::warn!("Credential '{name}' is missing from the credential store");
+            }
+            *field = secret;
         }
     }
 }
@@ -129,14 +227,161 @@ impl Default for LocalBackendConfig {
                         model: "llama3.1:8b".to_string(),
                         max_tokens: Some(2048),
                         temperature: Some(0.7),
+                        context_size: None,
+                        instruct_template: None,
                     },
                 },
             ],
             selected_llm_provider: Some("ollama_default".to_string()),
+            fallback_providers: Vec::new(),
+            retry_policy: RetryPolicy::default(),
+        }
+    }
+}
+
+impl LocalBackendConfig {
+    /// Provider used for generation, falling back to the first configured one
+    pub fn selected_provider(&self) -> Option<&LlmProviderConfig> {
+        self.selected_llm_provider
+            .as_ref()
+            .and_then(|id| self.llm_providers.iter().find(|p| &p.id == id))
+            .or_else(|| self.llm_providers.first())
+    }
+
+    /// Selected provider followed by its fallbacks, each at most once
+    pub fn provider_chain(&self) -> Vec<&LlmProviderConfig> {
+        let mut chain: Vec<&LlmProviderConfig> = self.selected_provider().into_iter().collect();
+        for id in &self.fallback_providers {
+            if let Some(provider) = self.llm_providers.iter().find(|p| &p.id == id) {
+                if !chain.iter().any(|p| p.id == provider.id) {
+                    chain.push(provider);
+                }
+            }
+        }
+        chain
+    }
+
+    /// Provider in the chain with the least room for a prompt
+    ///
+    /// A prompt fitted to it fits whichever provider in the chain ends up answering.
+    pub fn tightest_provider(&self) -> Option<&LlmProviderConfig> {
+        self.provider_chain().into_iter().min_by_key(|p| p.config.prompt_budget())
+    }
+
+    /// Add a provider, or replace the one with the same id
+    pub fn save_provider(&mut self, provider: LlmProviderConfig) {
+        match self.llm_providers.iter_mut().find(|p| p.id == provider.id) {
+            Some(existing) => *existing = provider,
+            None => self.llm_providers.push(provider),
+        }
+    }
+
+    /// Copy a provider under a new id, returning the copy
+    pub fn duplicate_provider(&mut self, id: &str) -> Option<LlmProviderConfig> {
+        let mut copy = self.llm_providers.iter().find(|p| p.id == id)?.clone();
+        copy.id = uuid::Uuid::new_v4().to_string();
+        copy.name = format!("{} (copy)", copy.name);
+        self.llm_providers.push(copy.clone());
+        Some(copy)
+    }
+
+    pub fn remove_provider(&mut self, id: &str) {
+        self.llm_providers.retain(|p| p.id != id);
+        self.fallback_providers.retain(|p| p != id);
+        if self.selected_llm_provider.as_deref() == Some(id) {
+            self.selected_llm_provider = self.llm_providers.first().map(|p| p.id.clone());
+        }
+    }
+}
+
+impl LlmProviderConfig {
+    /// New provider of the given type with its usual defaults
+    pub fn new(provider_type: LlmProviderType) -> Self {
+        let (name, base_url, model) = match provider_type {
+            LlmProviderType::Ollama => ("Ollama", Some(crate::llm::OLLAMA_DEFAULT_URL), "llama3.1:8b"),
+            LlmProviderType::OpenAI => ("OpenAI", None, "gpt-4o-mini"),
+            LlmProviderType::Anthropic => ("Anthropic", None, "claude-3-5-haiku-latest"),
+            // llama.cpp and KoboldCpp serve whichever model they loaded under any name
+            LlmProviderType::Custom => ("Custom", Some("http://localhost:8080/v1"), "local-model"),
+        };
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            provider_type,
+            config: LlmProviderSettings {
+                base_url: base_url.map(str::to_string),
+                api_key: None,
+                model: model.to_string(),
+                max_tokens: None,
+                temperature: None,
+                context_size: None,
+                instruct_template: None,
+            },
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), ProviderConfigError> {
+        if self.name.trim().is_empty() {
+            return Err(ProviderConfigError::MissingName);
+        }
+        if self.config.model.trim().is_empty() {
+            return Err(ProviderConfigError::MissingModel);
+        }
+        match self.config.base_url.as_deref().map(str::trim) {
+            Some(url) if !url.is_empty() => validate_base_url(url)?,
+            _ if self.provider_type == LlmProviderType::Custom => {
+                return Err(ProviderConfigError::MissingBaseUrl)
+            }
+            _ => {}
         }
+        let needs_key = matches!(self.provider_type, LlmProviderType::OpenAI | LlmProviderType::Anthropic);
+        if needs_key && self.config.api_key.as_deref().is_none_or(|key| key.trim().is_empty()) {
+            return Err(ProviderConfigError::MissingApiKey(self.name.clone()));
+        }
+        Ok(())
+    }
+}
+
+fn validate_base_url(url: &str) -> Result<(), ProviderConfigError> {
+    let parsed = reqwest::Url::parse(url).map_err(|e| ProviderConfigError::InvalidBaseUrl(e.to_string()))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(ProviderConfigError::InvalidBaseUrl(format!(
+            "unsupported scheme '{}'",
+            parsed.scheme()
+        )));
+    }
+    if parsed.host_str().is_none_or(str::is_empty) {
+        return Err(ProviderConfigError::InvalidBaseUrl("missing host".to_string()));
+    }
+    Ok(())
+}
+
+/// Show only the end of a secret, e.g. `••••cdef`
+pub fn mask_secret(secret: &str) -> String {
+    let chars: Vec<char> = secret.chars().collect();
+    if chars.len() <= 8 {
+        return "••••••••".to_string();
     }
+    let tail: String = chars[chars.len() - 4..].iter().collect();
+    format!("••••{tail}")
 }
 
+impl LlmProviderSettings {
+    pub fn context_window(&self) -> u32 {
+        self.context_size
+            .unwrap_or_else(|| crate::tokens::default_context_size(&self.model))
+    }
+
+    /// Tokens left for the prompt once room for the reply is set aside
+    pub fn prompt_budget(&self) -> usize {
+        let reply = self.max_tokens.unwrap_or(DEFAULT_REPLY_TOKENS);
+        self.context_window().saturating_sub(reply) as usize
+    }
+}
+
+/// Room kept for the reply when a provider has no `max_tokens`
+const DEFAULT_REPLY_TOKENS: u32 = 512;
+
 impl Default for UiPreferences {
     fn default() -> Self {
         Self {
@@ -163,6 +408,10 @@ impl Default for ChatPreferences {
 pub struct SettingsManager {
     settings: AppSettings,
     storage: Storage,
+    credentials: CredentialStore,
+    /// Directory holding the settings file, the storage directory when `None`
+    #[cfg(not(target_arch = "wasm32"))]
+    dir: Option<PathBuf>,
 }
 
 impl SettingsManager {
@@ -173,25 +422,71 @@ impl SettingsManager {
                 manager
             }
             Err(e) => {
-                log::warn!("Failed to load settings ({e}), using defaults");
-                let manager = Self::default();
-                if let Err(save_err) = manager.save() {
-                    log::error!("Failed to save default settings: {save_err}");
-                } else {
-                    log::info!("Default settings saved successfully");
+                // Leave the unreadable file and the vault alone, nothing here is worth saving over them
+                log::error!("Failed to load settings ({e}), using defaults");
+                Self {
+                    credentials: CredentialStore::unavailable(&CredentialError::Unavailable(format!(
+                        "settings could not be loaded: {e}"
+                    ))),
+                    ..Self::default()
                 }
-                manager
             }
         }
     }
 
+    /// Load the settings, saving defaults when there are none yet
     pub fn load() -> Result<Self, SettingsError> {
-        let storage = Storage::new();
-        let settings = Self::load_settings(&storage)?;
-        Ok(Self { settings, storage })
+        Self::default().read(CredentialStore::open())
     }
 
-    fn load_settings(storage: &Storage) -> Result<AppSettings, SettingsError> {
+    /// Load the settings kept in `dir`
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load_in(dir: PathBuf) -> Result<Self, SettingsError> {
+        Self {
+            dir: Some(dir.clone()),
+            ..Self::default()
+        }
+        .read(CredentialStore::open_in(dir))
+    }
+
+    fn read(mut self, credentials: Result<CredentialStore, CredentialError>) -> Result<Self, SettingsError> {
+        // Settings stay usable without their secrets, so a broken vault only locks the store
+        self.credentials = credentials.unwrap_or_else(|e| {
+            log::error!("Failed to open credential store: {e}");
+            CredentialStore::unavailable(&e)
+        });
+
+        let Some(settings) = self.load_settings()? else {
+            log::info!("No settings found, saving defaults");
+            if let Err(e) = self.save() {
+                log::error!("Failed to save default settings: {e}");
+            }
+            return Ok(self);
+        };
+        self.settings = settings;
+        let plaintext = self.settings.clone().seal_secrets().len();
+        self.settings.resolve_secrets(&self.credentials);
+
+        // Settings from before the credential store still hold their secrets
+        if plaintext > 0 && !self.credentials.is_locked() {
+            log::info!("Moving {plaintext} plaintext secrets into the credential store");
+            if let Err(e) = self.save() {
+                log::warn!("Failed to migrate plaintext secrets: {e}");
+            }
+        }
+        Ok(self)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn settings_path(&self) -> Result<PathBuf, SettingsError> {
+        match &self.dir {
+            Some(dir) => Ok(dir.join("settings.toml")),
+            None => Ok(self.storage.get_file_path("settings.toml")?),
+        }
+    }
+
+    /// Stored settings, or `None` when nothing has been saved yet
+    fn load_settings(&self) -> Result<Option<AppSettings>, SettingsError> {
         #[cfg(target_arch = "wasm32")]
         {
             // Web: Use localStorage
@@ -218,43 +513,41 @@ impl SettingsManager {
                 })?;
 
             let storage_key = "hearth_settings";
-            let content = local_storage
-                .get_item(storage_key)
-                .map_err(|_| {
-                    SettingsError::Storage(StorageError::Io(std::io::Error::new(
-                        std::io::ErrorKind::Other,
-                        "Failed to read from localStorage",
-                    )))
-                })?
-                .ok_or_else(|| {
-                    SettingsError::Storage(StorageError::Io(std::io::Error::new(
-                        std::io::ErrorKind::NotFound,
-                        "Settings not found in localStorage",
-                    )))
-                })?;
+            let Some(content) = local_storage.get_item(storage_key).map_err(|_| {
+                SettingsError::Storage(StorageError::Io(std::io::Error::new(
+                    std::io::ErrorKind::Other,
+                    "Failed to read from localStorage",
+                )))
+            })?
+            else {
+                return Ok(None);
+            };
 
-            toml::from_str(&content).map_err(|e| SettingsError::Deserialization(e.to_string()))
+            toml::from_str(&content).map(Some).map_err(|e| SettingsError::Deserialization(e.to_string()))
         }
         #[cfg(not(target_arch = "wasm32"))]
         {
             // Native: Use file system
-            let file_path = storage.get_file_path("settings.toml")?;
-            if !file_path.exists() {
-                return Err(SettingsError::Storage(StorageError::Io(
-                    std::io::Error::new(
-                        std::io::ErrorKind::NotFound,
-                        "Settings file does not exist",
-                    ),
-                )));
-            }
+            let content = match std::fs::read_to_string(self.settings_path()?) {
+                Ok(content) => content,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+                Err(e) => return Err(e.into()),
+            };
 
-            let content = std::fs::read_to_string(file_path)?;
-            
-            toml::from_str(&content).map_err(|e| SettingsError::Deserialization(e.to_string()))
+            toml::from_str(&content).map(Some).map_err(|e| SettingsError::Deserialization(e.to_string()))
         }
     }
 
     pub fn save(&self) -> Result<(), SettingsError> {
+        let mut settings = self.settings.clone();
+        let secrets = settings.seal_secrets();
+        if !self.credentials.is_locked() {
+            self.credentials.write(&secrets)?;
+        } else if !secrets.is_empty() {
+            // New secrets cannot be stored until the passphrase is entered
+            return Err(CredentialError::Locked.into());
+        }
+
         #[cfg(target_arch = "wasm32")]
         {
             // Web: Use localStorage
@@ -281,7 +574,7 @@ impl SettingsManager {
                 })?;
 
             let storage_key = "hearth_settings";
-            let content = toml::to_string_pretty(&self.settings)
+            let content = toml::to_string_pretty(&settings)
                 .map_err(|e| SettingsError::Serialization(e.to_string()))?;
 
             local_storage.set_item(storage_key, &content).map_err(|_| {
@@ -296,10 +589,9 @@ impl SettingsManager {
         #[cfg(not(target_arch = "wasm32"))]
         {
             // Native: Use file system
-            let file_path = self.storage.get_file_path("settings.toml")?;
-            let content = toml::to_string_pretty(&self.settings)
+            let content = toml::to_string_pretty(&settings)
                 .map_err(|e| SettingsError::Serialization(e.to_string()))?;
-            std::fs::write(file_path, content)?;
+            crate::write_atomic(&self.settings_path()?, content.as_bytes(), false)?;
             Ok(())
         }
     }
@@ -360,4 +652,153 @@ impl SettingsManager {
             log::error!("Failed to save theme: {e}");
         }
     }
+
+    pub fn credentials_locked(&self) -> bool {
+        self.credentials.is_locked()
+    }
+
+    pub fn credentials_use_passphrase(&self) -> bool {
+        self.credentials.uses_passphrase()
+    }
+
+    /// Why stored secrets could not be read, shown instead of the unlock prompt
+    pub fn credentials_error(&self) -> Option<&str> {
+        self.credentials.problem()
+    }
+
+    /// Unlock a passphrase-protected credential store and fill in the secrets
+    pub fn unlock_credentials(&mut self, passphrase: &str) -> Result<(), SettingsError> {
+        self.credentials.unlock(passphrase)?;
+        self.settings.resolve_secrets(&self.credentials);
+        Ok(())
+    }
+
+    /// Protect secrets with a passphrase, or with a generated key file when `None`
+    pub fn set_credentials_passphrase(&mut self, passphrase: Option<&str>) -> Result<(), SettingsError> {
+        self.credentials.set_passphrase(passphrase)?;
+        self.save()
+    }
+
+    /// Change the local provider list, then save
+    pub fn update_providers(&mut self, change: impl FnOnce(&mut LocalBackendConfig)) {
+        change(self.settings.local_backend.get_or_insert_with(LocalBackendConfig::default));
+        if let Err(e) = self.save() {
+            log::error!("Failed to save LLM providers: {e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_provider_validation() {
+        let mut custom = LlmProviderConfig::new(LlmProviderType::Custom);
+        assert_eq!(custom.validate(), Ok(()));
+
+        custom.config.base_url = Some("localhost:8080".to_string());
+        assert!(matches!(custom.validate(), Err(ProviderConfigError::InvalidBaseUrl(_))));
+        custom.config.base_url = Some("ftp://models.local".to_string());
+        assert!(matches!(custom.validate(), Err(ProviderConfigError::InvalidBaseUrl(_))));
+        custom.config.base_url = None;
+        assert_eq!(custom.validate(), Err(ProviderConfigError::MissingBaseUrl));
+
+        let openai = LlmProviderConfig::new(LlmProviderType::OpenAI);
+        assert!(matches!(openai.validate(), Err(ProviderConfigError::MissingApiKey(_))));
+    }
+
+    #[test]
+    fn test_tightest_provider_is_the_smallest_context_in_the_chain() {
+        let mut local = LocalBackendConfig::default();
+        local.llm_providers[0].config.context_size = Some(8192);
+        let mut small = local.duplicate_provider("ollama_default").unwrap();
+        small.config.context_size = Some(2048);
+        local.save_provider(small.clone());
+        assert_eq!(local.tightest_provider().map(|p| &p.id), Some(&local.llm_providers[0].id));
+
+        // Only providers that can answer count
+        local.fallback_providers = vec![small.id.clone()];
+        assert_eq!(local.tightest_provider().map(|p| &p.id), Some(&small.id));
+    }
+
+    #[test]
+    fn test_removing_selected_provider_selects_another() {
+        let mut local = LocalBackendConfig::default();
+        let copy = local.duplicate_provider("ollama_default").unwrap();
+        assert_eq!(copy.name, "Ollama (Local) (copy)");
+        assert_ne!(copy.id, "ollama_default");
+
+        local.fallback_providers = vec!["ollama_default".to_string(), copy.id.clone()];
+        assert_eq!(local.provider_chain().len(), 2);
+
+        local.remove_provider("ollama_default");
+        assert_eq!(local.selected_llm_provider, Some(copy.id.clone()));
+        assert_eq!(local.fallback_providers, vec![copy.id.clone()]);
+        assert_eq!(local.provider_chain(), vec![&copy]);
+        assert_eq!(local.selected_provider(), Some(&copy));
+        assert_eq!(mask_secret("sk-abcdefghijkl"), "••••ijkl");
+    }
+
+    #[test]
+    fn test_plaintext_secrets_are_replaced_by_references() {
+        let mut settings = AppSettings::default();
+        settings.local_backend.as_mut().unwrap().llm_providers[0].config.api_key = Some("sk-secret".to_string());
+        settings.remote_backends.push(RemoteBackendConfig {
+            id: "server".to_string(),
+            name: "Server".to_string(),
+            url: "https://hearth.example".to_string(),
+            auth_token: Some("token".to_string()),
+            last_connected: None,
+        });
+
+        let mut sealed = settings.clone();
+        let secrets = sealed.seal_secrets();
+        assert_eq!(secrets.len(), 2);
+        let toml = toml::to_string(&sealed).unwrap();
+        assert!(!toml.contains("sk-secret") && !toml.contains("\"token\""));
+        assert!(toml.contains("credential:llm/ollama_default"));
+        assert!(sealed.seal_secrets().is_empty());
+
+        let dir = std::env::temp_dir().join(format!("hearth-settings-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        CredentialStore::open_in(dir.clone()).unwrap().write(&secrets).unwrap();
+        sealed.resolve_secrets(&CredentialStore::open_in(dir.clone()).unwrap());
+        assert_eq!(sealed.local_backend, settings.local_backend);
+        assert_eq!(sealed.remote_backends[0].auth_token, Some("token".to_string()));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_broken_key_file_keeps_settings() {
+        let dir = std::env::temp_dir().join(format!("hearth-settings-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut manager = SettingsManager::load_in(dir.clone()).unwrap();
+        assert!(dir.join("settings.toml").exists());
+        manager.update_providers(|local| local.llm_providers[0].config.api_key = Some("sk-secret".to_string()));
+        let settings_file = std::fs::read(dir.join("settings.toml")).unwrap();
+        let vault = std::fs::read(dir.join("credentials.vault")).unwrap();
+
+        for key in [None, Some(&b"short"[..])] {
+            match key {
+                Some(bytes) => std::fs::write(dir.join("credentials.key"), bytes).unwrap(),
+                None => std::fs::remove_file(dir.join("credentials.key")).unwrap(),
+            }
+            let mut manager = SettingsManager::load_in(dir.clone()).unwrap();
+            assert!(manager.credentials_locked());
+            assert!(manager.credentials_error().is_some());
+            let provider = &manager.get().local_backend.as_ref().unwrap().llm_providers[0];
+            assert_eq!(provider.config.api_key.as_deref(), Some("credential:llm/ollama_default"));
+            assert!(manager.unlock_credentials("anything").is_err());
+
+            // Saving other changes keeps the reference and leaves the vault untouched
+            manager.update_theme(Theme::Dark);
+            assert!(String::from_utf8(std::fs::read(dir.join("settings.toml")).unwrap())
+                .unwrap()
+                .contains("credential:llm/ollama_default"));
+            assert_eq!(std::fs::read(dir.join("credentials.vault")).unwrap(), vault);
+            std::fs::write(dir.join("settings.toml"), &settings_file).unwrap();
+        }
+        std::fs::remove_dir_all(dir).unwrap();
+    }
 }
@@ -3,6 +3,8 @@
 //! Provides consistent storage directory management across platforms.
 
 use std::path::PathBuf;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -102,3 +104,41 @@ impl Storage {
         Ok(storage_dir.join(filename))
     }
 }
+
+/// Replace a file without ever leaving it half written
+///
+/// The bytes go to a temporary file in the same directory, which is synced
+/// and then renamed over `path`. When `private`, only the user can read it.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn write_atomic(path: &Path, bytes: &[u8], private: bool) -> std::io::Result<()> {
+    use std::io::Write;
+
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+    let temp = PathBuf::from(temp);
+    // A leftover from an interrupted write may have the wrong permissions
+    let _ = std::fs::remove_file(&temp);
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    if private {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    #[cfg(not(unix))]
+    let _ = private;
+
+    let mut file = options.open(&temp)?;
+    file.write_all(bytes)?;
+    file.sync_all()?;
+    drop(file);
+    std::fs::rename(&temp, path)?;
+
+    // Make the rename itself durable
+    #[cfg(unix)]
+    if let Some(dir) = path.parent().and_then(|dir| std::fs::File::open(dir).ok()) {
+        let _ = dir.sync_all();
+    }
+    Ok(())
+}
@@ -0,0 +1,394 @@
+//! Story export
+//!
+//! Writes the active branch of a story as a lossless JSON document, or as
+//! Markdown, plain text, standalone HTML or EPUB for reading. Guidance is kept
+//! apart from messages and is never exported.
+
+use crate::markdown::{markdown_to_html, MarkdownConfig};
+use crate::message_tree::MessageTree;
+use crate::models::{StoryItem, StoryMessage, StoryRole};
+use crate::save_dialog::SaveFile;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::io::{Cursor, Write};
+use thiserror::Error;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+/// Version of the JSON document layout
+pub const STORY_DOCUMENT_VERSION: u32 = 1;
+
+#[derive(Error, Debug)]
+pub enum ExportError {
+    #[error("Serialization error: {0}")]
+    Serialization(String),
+    #[error("Failed to write EPUB: {0}")]
+    Epub(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Json,
+    Markdown,
+    PlainText,
+    Html,
+    Epub,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 5] = [Self::Json, Self::Markdown, Self::PlainText, Self::Html, Self::Epub];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Json => "JSON",
+            Self::Markdown => "Markdown",
+            Self::PlainText => "Plain text",
+            Self::Html => "HTML",
+            Self::Epub => "EPUB",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Markdown => "md",
+            Self::PlainText => "txt",
+            Self::Html => "html",
+            Self::Epub => "epub",
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            Self::Json => "application/json",
+            Self::Markdown => "text/markdown",
+            Self::PlainText => "text/plain",
+            Self::Html => "text/html",
+            Self::Epub => "application/epub+zip",
+        }
+    }
+}
+
+/// Everything needed to restore a story, as written to JSON
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoryDocument {
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub story: StoryItem,
+    /// Messages of the active branch in conversation order
+    pub messages: Vec<StoryMessage>,
+}
+
+impl StoryDocument {
+    pub fn new(story: StoryItem, messages: Vec<StoryMessage>) -> Self {
+        Self {
+            version: STORY_DOCUMENT_VERSION,
+            exported_at: Utc::now(),
+            story,
+            messages,
+        }
+    }
+
+    /// A document of the branch the story currently shows
+    pub fn from_tree(story: StoryItem, tree: &MessageTree) -> Self {
+        Self::new(story, tree.active_path())
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, ExportError> {
+        serde_json::from_str(json).map_err(|e| ExportError::Serialization(e.to_string()))
+    }
+
+    pub fn export(&self, format: ExportFormat) -> Result<Vec<u8>, ExportError> {
+        match format {
+            ExportFormat::Json => {
+                serde_json::to_vec_pretty(self).map_err(|e| ExportError::Serialization(e.to_string()))
+            }
+            ExportFormat::Markdown => Ok(self.to_markdown().into_bytes()),
+            ExportFormat::PlainText => Ok(self.to_plain_text().into_bytes()),
+            ExportFormat::Html => Ok(self.to_html().into_bytes()),
+            ExportFormat::Epub => self.to_epub(),
+        }
+    }
+
+    /// The export as a file for [`crate::save_with_dialog`]
+    pub fn save_file(&self, format: ExportFormat) -> Result<SaveFile, ExportError> {
+        Ok(SaveFile {
+            title: "Export Story".to_string(),
+            file_name: format!("{}.{}", file_stem(&self.story.title, "story"), format.extension()),
+            kind: format!("{} Files", format.label()),
+            extension: format.extension().to_string(),
+            mime_type: format.mime_type().to_string(),
+            content: self.export(format)?,
+        })
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut text = format!("# {}\n", self.story.title);
+        for message in &self.messages {
+            match speaker(message) {
+                Some(name) => text.push_str(&format!("\n**{name}:** {}\n", message.content.trim())),
+                None => text.push_str(&format!("\n{}\n", message.content.trim())),
+            }
+        }
+        text
+    }
+
+    pub fn to_plain_text(&self) -> String {
+        let title = &self.story.title;
+        let mut text = format!("{title}\n{}\n", "=".repeat(title.chars().count()));
+        for message in &self.messages {
+            match speaker(message) {
+                Some(name) => text.push_str(&format!("\n{name}: {}\n", message.content.trim())),
+                None => text.push_str(&format!("\n{}\n", message.content.trim())),
+            }
+        }
+        text
+    }
+
+    /// A single page with its styles inlined
+    pub fn to_html(&self) -> String {
+        let config = MarkdownConfig {
+            quote_class: Some("dialogue".to_string()),
+            // Imported chats often carry raw HTML, which must not run in a standalone file
+            sanitize: true,
+            ..Default::default()
+        };
+        let title = escape(&self.story.title);
+        let mut body = String::new();
+        for message in &self.messages {
+            let content =
+                markdown_to_html(&message.content, &config).unwrap_or_else(|_| paragraphs(&message.content));
+            body.push_str(&format!("<article class=\"message {}\">\n", role_class(&message.role)));
+            if let Some(name) = speaker(message) {
+                body.push_str(&format!("<div class=\"speaker\">{}</div>\n", escape(name)));
+            }
+            body.push_str(&format!("{content}\n</article>\n"));
+        }
+        format!(
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+             <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+             <title>{title}</title>\n<style>\n{HTML_STYLE}</style>\n</head>\n\
+             <body>\n<main>\n<h1>{title}</h1>\n{body}</main>\n</body>\n</html>\n"
+        )
+    }
+
+    /// An EPUB 3 book with the story as its only chapter
+    pub fn to_epub(&self) -> Result<Vec<u8>, ExportError> {
+        let title = escape(&self.story.title);
+        let mut chapter = String::new();
+        for message in &self.messages {
+            chapter.push_str(&format!("<div class=\"message {}\">\n", role_class(&message.role)));
+            if let Some(name) = speaker(message) {
+                chapter.push_str(&format!("<p class=\"speaker\">{}</p>\n", escape(name)));
+            }
+            chapter.push_str(&format!("{}</div>\n", paragraphs(&message.content)));
+        }
+        let nav = format!("<nav epub:type=\"toc\">\n<ol><li><a href=\"story.xhtml\">{title}</a></li></ol>\n</nav>\n");
+        let modified = self.exported_at.format("%Y-%m-%dT%H:%M:%SZ");
+        let files = [
+            ("META-INF/container.xml", EPUB_CONTAINER.to_string()),
+            (
+                "OEBPS/content.opf",
+                format!(
+                    "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+                     <package xmlns=\"http://www.idpf.org/2007/opf\" version=\"3.0\" unique-identifier=\"id\">\n\
+                     <metadata xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\n\
+                     <dc:identifier id=\"id\">urn:uuid:{}</dc:identifier>\n\
+                     <dc:title>{title}</dc:title>\n<dc:language>en</dc:language>\n\
+                     <meta property=\"dcterms:modified\">{modified}</meta>\n</metadata>\n\
+                     <manifest>\n\
+                     <item id=\"nav\" href=\"nav.xhtml\" media-type=\"application/xhtml+xml\" properties=\"nav\"/>\n\
+                     <item id=\"story\" href=\"story.xhtml\" media-type=\"application/xhtml+xml\"/>\n\
+                     <item id=\"style\" href=\"style.css\" media-type=\"text/css\"/>\n\
+                     </manifest>\n<spine>\n<itemref idref=\"story\"/>\n</spine>\n</package>\n",
+                    uuid::Uuid::new_v4()
+                ),
+            ),
+            ("OEBPS/nav.xhtml", xhtml(&title, &nav)),
+            ("OEBPS/story.xhtml", xhtml(&title, &format!("<h1>{title}</h1>\n{chapter}"))),
+            ("OEBPS/style.css", EPUB_STYLE.to_string()),
+        ];
+
+        let epub_error = |e: zip::result::ZipError| ExportError::Epub(e.to_string());
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        // Readers expect the uncompressed mimetype entry first
+        let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+        zip.start_file("mimetype", stored).map_err(epub_error)?;
+        zip.write_all(b"application/epub+zip")
+            .map_err(|e| ExportError::Epub(e.to_string()))?;
+        let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+        for (path, content) in files {
+            zip.start_file(path, deflated).map_err(epub_error)?;
+            zip.write_all(content.as_bytes())
+                .map_err(|e| ExportError::Epub(e.to_string()))?;
+        }
+        Ok(zip.finish().map_err(epub_error)?.into_inner())
+    }
+}
+
+/// Name shown before a message; narration stands on its own
+fn speaker(message: &StoryMessage) -> Option<&str> {
+    match &message.role {
+        StoryRole::User { name } | StoryRole::Character { name } => Some(name),
+        StoryRole::Narrator => None,
+    }
+}
+
+fn role_class(role: &StoryRole) -> &'static str {
+    match role {
+        StoryRole::User { .. } => "user",
+        StoryRole::Character { .. } => "character",
+        StoryRole::Narrator => "narrator",
+    }
+}
+
+/// A title usable as a file name, `fallback` when nothing is left of it
+pub(crate) fn file_stem(title: &str, fallback: &str) -> String {
+    let stem: String = title
+        .chars()
+        .map(|c| if c.is_control() || r#"/\:*?"<>|"#.contains(c) { '_' } else { c })
+        .collect();
+    match stem.trim() {
+        "" => fallback.to_string(),
+        stem => stem.to_string(),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Escaped paragraphs, for XHTML where raw HTML in messages could break the page
+fn paragraphs(text: &str) -> String {
+    text.split("\n\n")
+        .map(str::trim)
+        .filter(|paragraph| !paragraph.is_empty())
+        .map(|paragraph| format!("<p>{}</p>\n", escape(paragraph).replace('\n', "<br/>")))
+        .collect()
+}
+
+fn xhtml(title: &str, body: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<!DOCTYPE html>\n\
+         <html xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:epub=\"http://www.idpf.org/2007/ops\" lang=\"en\">\n\
+         <head>\n<title>{title}</title>\n<link rel=\"stylesheet\" href=\"style.css\"/>\n</head>\n\
+         <body>\n{body}</body>\n</html>\n"
+    )
+}
+
+const EPUB_CONTAINER: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+<rootfiles>
+<rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+</rootfiles>
+</container>
+"#;
+
+const EPUB_STYLE: &str = "\
+.message { margin: 0 0 1em; }
+.speaker { font-weight: bold; margin: 0; }
+.narrator { font-style: italic; }
+";
+
+const HTML_STYLE: &str = "\
+body { margin: 0; background: #faf8f5; color: #2b2724; font: 17px/1.6 Georgia, serif; }
+main { max-width: 42rem; margin: 0 auto; padding: 2rem 1.25rem 4rem; }
+h1 { font-weight: normal; text-align: center; margin-bottom: 2rem; }
+.message { margin: 0 0 1.5rem; }
+.message p { margin: 0.4rem 0; }
+.speaker { font: bold 0.8rem/1.2 system-ui, sans-serif; text-transform: uppercase; letter-spacing: 0.05em; }
+.user .speaker { color: #3b6ea5; }
+.character .speaker { color: #a5553b; }
+.narrator { font-style: italic; color: #5c5550; }
+.dialogue { color: #b45309; }
+@media (prefers-color-scheme: dark) {
+  body { background: #1c1a19; color: #e8e2dc; }
+  .narrator { color: #b3aba4; }
+  .dialogue { color: #f59e0b; }
+}
+";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message_tree::MessageTree;
+    use crate::models::GenerationMetadata;
+    use std::io::Read;
+
+    fn document() -> StoryDocument {
+        let story = crate::sample::sample_stories().remove(0);
+        let mut tree = MessageTree::default();
+        tree.append(StoryMessage::new("1", StoryRole::Narrator, "The tavern is <quiet>."));
+        tree.append(StoryMessage::new("2", StoryRole::User { name: "Theron".to_string() }, "\"Hello?\""));
+        let mut reply = StoryMessage::new("3", StoryRole::Character { name: "Alice".to_string() }, "*Waves.* Hi!");
+        reply.add_alternate("Welcome!", GenerationMetadata::default()).unwrap();
+        tree.append(reply);
+        // A branch that is not shown must not be exported
+        tree.fork("2").unwrap();
+        tree.append(StoryMessage::new("4", StoryRole::Character { name: "Alice".to_string() }, "Go away."));
+        tree.switch_branch("3").unwrap();
+        StoryDocument::from_tree(story, &tree)
+    }
+
+    #[test]
+    fn test_json_export_is_lossless() {
+        let document = document();
+        let json = String::from_utf8(document.export(ExportFormat::Json).unwrap()).unwrap();
+        let restored = StoryDocument::from_json(&json).unwrap();
+        assert!(restored == document);
+        assert_eq!(restored.messages.len(), 3);
+        assert_eq!(restored.messages[2].alternates.len(), 2);
+        assert!(!json.contains("Go away."));
+    }
+
+    #[test]
+    fn test_readable_exports_follow_active_branch() {
+        let document = document();
+        let markdown = document.to_markdown();
+        assert!(markdown.contains("\n**Theron:** \"Hello?\"\n"));
+        assert!(markdown.contains("\n**Alice:** Welcome!\n"));
+        assert!(!markdown.contains("Go away."));
+
+        let text = document.to_plain_text();
+        assert!(text.contains("\nAlice: Welcome!\n"));
+
+        let html = document.to_html();
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<div class=\"speaker\">Theron</div>"));
+        assert!(html.contains("class=\"dialogue\""));
+        assert!(!html.contains("Go away."));
+    }
+
+    #[test]
+    fn test_html_export_neutralises_raw_html_and_script_links() {
+        let story = crate::sample::sample_stories().remove(0);
+        let content = "<script>alert(1)</script>\n\nShe says \"<b onclick=x>hi</b>\" and [waves](javascript:alert(2)) \
+                       at [the inn](https://example.com/inn).";
+        let message = StoryMessage::new("1", StoryRole::Character { name: "Alice".to_string() }, content);
+        let html = StoryDocument::new(story, vec![message]).to_html();
+        assert!(!html.contains("<script>") && !html.contains("<b onclick"));
+        assert!(html.contains("&lt;script&gt;alert(1)&lt;/script&gt;"));
+        assert!(!html.contains("javascript:"));
+        assert!(html.contains("waves"));
+        assert!(html.contains("<a href=\"https://example.com/inn\">the inn</a>"));
+        assert!(html.contains("class=\"dialogue\""));
+    }
+
+    #[test]
+    fn test_epub_has_stored_mimetype_first() {
+        let epub = document().export(ExportFormat::Epub).unwrap();
+        assert_eq!(&epub[30..58], b"mimetypeapplication/epub+zip");
+
+        let mut archive = zip::ZipArchive::new(Cursor::new(epub)).unwrap();
+        let mut chapter = String::new();
+        archive.by_name("OEBPS/story.xhtml").unwrap().read_to_string(&mut chapter).unwrap();
+        assert!(chapter.contains("<p>The tavern is &lt;quiet&gt;.</p>"));
+        assert!(chapter.contains("<p class=\"speaker\">Alice</p>"));
+        assert!(archive.by_name("OEBPS/content.opf").is_ok());
+
+        assert_eq!(file_stem("A/B: \"C\"", "story"), "A_B_ _C_");
+    }
+}
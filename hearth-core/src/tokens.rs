@@ -0,0 +1,155 @@
+//! Token counting and context window sizes
+//!
+//! Prompts are measured in tokens to keep them inside a model's context window.
+//! OpenAI models get their exact BPE tokenizer; every other model is estimated,
+//! erring on the high side so a trimmed prompt still fits.
+
+use crate::llm::ChatMessage;
+
+/// Chat APIs wrap every message in a few formatting tokens
+pub const MESSAGE_OVERHEAD: usize = 4;
+
+/// Context window assumed for models we know nothing about
+pub const DEFAULT_CONTEXT_SIZE: u32 = 4096;
+
+pub trait TokenCounter {
+    fn count(&self, text: &str) -> usize;
+}
+
+pub fn count_message(counter: &dyn TokenCounter, message: &ChatMessage) -> usize {
+    counter.count(&message.content) + MESSAGE_OVERHEAD
+}
+
+pub fn count_messages(counter: &dyn TokenCounter, messages: &[ChatMessage]) -> usize {
+    messages.iter().map(|m| count_message(counter, m)).sum()
+}
+
+/// Estimate of roughly 3.5 characters per token
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HeuristicCounter;
+
+impl TokenCounter for HeuristicCounter {
+    fn count(&self, text: &str) -> usize {
+        (text.chars().count() * 2).div_ceil(7)
+    }
+}
+
+/// Exact counts with one of OpenAI's byte pair encodings
+///
+/// Llama 3 extends `cl100k_base`, so it is counted with that encoding as well,
+/// which is very close but not exact.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct BpeCounter {
+    bpe: &'static tiktoken_rs::CoreBPE,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl BpeCounter {
+    pub fn cl100k() -> Self {
+        Self {
+            bpe: tiktoken_rs::cl100k_base_singleton(),
+        }
+    }
+
+    pub fn o200k() -> Self {
+        Self {
+            bpe: tiktoken_rs::o200k_base_singleton(),
+        }
+    }
+
+    pub fn for_model(model: &str) -> Option<Self> {
+        use tiktoken_rs::tokenizer::{get_tokenizer, Tokenizer};
+
+        let model = model.to_lowercase();
+        match get_tokenizer(&model) {
+            Some(Tokenizer::O200kBase) => Some(Self::o200k()),
+            Some(Tokenizer::Cl100kBase) => Some(Self::cl100k()),
+            _ if model.contains("llama3") || model.contains("llama-3") => Some(Self::cl100k()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl TokenCounter for BpeCounter {
+    fn count(&self, text: &str) -> usize {
+        self.bpe.encode_with_special_tokens(text).len()
+    }
+}
+
+/// Most accurate counter available for `model`
+pub fn counter_for_model(model: &str) -> Box<dyn TokenCounter> {
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(counter) = BpeCounter::for_model(model) {
+        return Box::new(counter);
+    }
+    let _ = model;
+    Box::new(HeuristicCounter)
+}
+
+/// Context window of well-known model families, by name
+pub fn default_context_size(model: &str) -> u32 {
+    // Checked in order, so more specific names come first
+    const KNOWN: &[(&str, u32)] = &[
+        ("gpt-4o", 128_000),
+        ("gpt-4.1", 1_000_000),
+        ("gpt-4-turbo", 128_000),
+        ("gpt-4", 8_192),
+        ("gpt-3.5", 16_385),
+        ("o1", 200_000),
+        ("o3", 200_000),
+        ("o4", 200_000),
+        ("claude", 200_000),
+        ("llama3.1", 131_072),
+        ("llama3.2", 131_072),
+        ("llama3.3", 131_072),
+        ("llama-3.1", 131_072),
+        ("llama3", 8_192),
+        ("llama-3", 8_192),
+        ("mistral-nemo", 128_000),
+        ("mistral", 32_768),
+        ("mixtral", 32_768),
+        ("qwen2.5", 32_768),
+        ("gemma2", 8_192),
+    ];
+
+    let model = model.to_lowercase();
+    let name = model.rsplit('/').next().unwrap_or(&model);
+    KNOWN
+        .iter()
+        .find(|(prefix, _)| name.starts_with(prefix))
+        .map_or(DEFAULT_CONTEXT_SIZE, |&(_, size)| size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::ChatRole;
+
+    #[test]
+    fn test_heuristic_overestimates_english() {
+        let text = "The tavern door creaks open and a cold wind sweeps across the room.";
+        assert_eq!(HeuristicCounter.count(text), 20);
+        assert_eq!(HeuristicCounter.count(""), 0);
+        assert_eq!(
+            count_messages(&HeuristicCounter, &[ChatMessage::new(ChatRole::User, "Hi")]),
+            1 + MESSAGE_OVERHEAD
+        );
+    }
+
+    #[test]
+    fn test_openai_models_use_exact_tokenizer() {
+        let counter = counter_for_model("gpt-4o-mini");
+        assert_eq!(counter.count("Hello world"), 2);
+        assert!(BpeCounter::for_model("claude-sonnet-4").is_none());
+    }
+
+    #[test]
+    fn test_default_context_sizes() {
+        assert_eq!(default_context_size("llama3.1:8b"), 131_072);
+        assert_eq!(default_context_size("llama3:8b"), 8_192);
+        assert_eq!(default_context_size("gpt-4o-mini"), 128_000);
+        assert_eq!(default_context_size("mistralai/Mistral-7B-Instruct-v0.3"), 32_768);
+        assert_eq!(default_context_size("my-finetune"), DEFAULT_CONTEXT_SIZE);
+    }
+}
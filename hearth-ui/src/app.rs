@@ -1,7 +1,7 @@
 //! Unified main app with adaptive routing
 
 use crate::{
-    provide_settings_context, AdaptiveLayout, AppLoading, CharactersView, DarkModeContext, Design,
+    provide_backend_context, provide_settings_context, reconnect_backend, AdaptiveLayout, AppLoading, CharactersView, DarkModeContext, Design,
     LoadingState, LoadingStage, Route, ScenariosView, SettingsView, 
     StoriesView, StoryView, ToastManager, Toaster, ViewportProvider, use_is_loading, use_loading_controller, Platform,
 };
@@ -79,6 +79,9 @@ pub fn App() -> Element {
     let is_dark = use_signal(|| matches!(settings.read().get().theme, Theme::Dark));
     use_context_provider(|| DarkModeContext { is_dark });
 
+    // Data backend, connected during the loading sequence
+    let backend = provide_backend_context();
+
     // Provide toast manager context
     let toast_manager = use_context_provider(ToastManager::new);
     
@@ -95,6 +98,11 @@ pub fn App() -> Element {
                 
                 loading_controller.advance_now(LoadingStage::LoadingSettings);
                 Platform::sleep(Duration::from_millis(100)).await;
+
+                if let Err(e) = reconnect_backend(backend, settings.peek().get()) {
+                    loading_controller.set_error(format!("Failed to connect to backend: {e}"));
+                    return;
+                }
                 
                 loading_controller.advance_now(LoadingStage::Ready);
             }
@@ -103,6 +111,18 @@ pub fn App() -> Element {
                 // On native platforms, use the proper timing
                 loading_controller.try_advance(LoadingStage::LoadingAssets).await;
                 loading_controller.try_advance(LoadingStage::LoadingSettings).await;
+
+                // Connect the selected backend; in local mode this opens the database
+                // and runs pending migrations before showing the app
+                let app_settings = settings.peek().get().clone();
+                if app_settings.selected_backend.is_none() {
+                    loading_controller.try_advance(LoadingStage::LoadingDatabase).await;
+                }
+                if let Err(e) = reconnect_backend(backend, &app_settings) {
+                    loading_controller.set_error(format!("Failed to connect to backend: {e}"));
+                    return;
+                }
+
                 loading_controller.complete().await;
             }
         });
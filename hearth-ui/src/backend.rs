@@ -0,0 +1,29 @@
+//! Active data backend context
+//!
+//! The backend is connected during the loading sequence and swapped whenever the
+//! user picks a different backend in settings. Views read it through
+//! [`use_backend`] so they reload when it changes.
+
+use dioxus::prelude::*;
+use hearth_core::{connect_backend, AppSettings, BackendError, SharedBackend};
+
+pub type BackendSignal = Signal<Option<SharedBackend>>;
+
+pub fn provide_backend_context() -> BackendSignal {
+    use_context_provider(|| Signal::new(None::<SharedBackend>))
+}
+
+pub fn use_backend() -> BackendSignal {
+    use_context()
+}
+
+/// Connect the backend selected in `settings` and make it the active one
+pub fn reconnect_backend(
+    mut backend: BackendSignal,
+    settings: &AppSettings,
+) -> Result<(), BackendError> {
+    let connected = connect_backend(settings)?;
+    log::info!("Active backend: {}", connected.name());
+    backend.set(Some(connected));
+    Ok(())
+}
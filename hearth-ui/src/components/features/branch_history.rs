@@ -0,0 +1,95 @@
+//! Branch history modal for switching between and pruning story branches
+
+use crate::{Badge, BadgeVariant, Button, ButtonSize, ButtonVariant, FadeMode, Modal, ScrollArea};
+use dioxus::prelude::*;
+use hearth_core::message_tree::StoryBranch;
+
+#[derive(Props, Clone, PartialEq)]
+pub struct BranchHistoryProps {
+    pub is_open: Signal<bool>,
+    pub branches: Vec<StoryBranch>,
+    /// Called with the leaf id of the branch to show
+    pub on_switch: EventHandler<String>,
+    /// Called with the leaf id of the branch to delete
+    pub on_prune: EventHandler<String>,
+}
+
+#[component]
+pub fn BranchHistory(props: BranchHistoryProps) -> Element {
+    if !(props.is_open)() {
+        return rsx! { div {} };
+    }
+
+    let can_prune = props.branches.len() > 1;
+
+    rsx! {
+        Modal {
+            title: "Branch History".to_string(),
+            is_open: props.is_open,
+
+            ScrollArea {
+                height: "h-[50vh]".to_string(),
+                fade_mode: FadeMode::Both,
+                fade_color: Some("from-card".to_string()),
+                class: "p-4",
+
+                if props.branches.is_empty() {
+                    div { class: "flex items-center justify-center h-32 text-center text-muted-foreground",
+                        "This story has no messages yet."
+                    }
+                } else {
+                    div { class: "space-y-2",
+                        for (index, branch) in props.branches.iter().enumerate() {
+                            div {
+                                key: "{branch.leaf_id}",
+                                class: "p-3 rounded-lg border border-border flex items-start space-x-3",
+
+                                div { class: "flex-1 min-w-0",
+                                    div { class: "flex items-center space-x-2 mb-1",
+                                        span { class: "font-medium text-foreground", "Branch {index + 1}" }
+                                        span { class: "text-xs text-muted-foreground", "{branch.length} messages" }
+                                        if branch.is_active {
+                                            Badge {
+                                                variant: BadgeVariant::Secondary,
+                                                class: "text-xs".to_string(),
+                                                "Active"
+                                            }
+                                        }
+                                    }
+                                    div { class: "text-sm text-muted-foreground truncate",
+                                        "{branch.preview}"
+                                    }
+                                }
+
+                                div { class: "flex items-center space-x-1 flex-shrink-0",
+                                    if !branch.is_active {
+                                        Button {
+                                            variant: ButtonVariant::Ghost,
+                                            size: ButtonSize::Small,
+                                            onclick: {
+                                                let leaf_id = branch.leaf_id.clone();
+                                                move |_| props.on_switch.call(leaf_id.clone())
+                                            },
+                                            i { class: "fas fa-eye text-xs" }
+                                        }
+                                    }
+                                    if can_prune {
+                                        Button {
+                                            variant: ButtonVariant::Ghost,
+                                            size: ButtonSize::Small,
+                                            onclick: {
+                                                let leaf_id = branch.leaf_id.clone();
+                                                move |_| props.on_prune.call(leaf_id.clone())
+                                            },
+                                            i { class: "fas fa-trash text-xs" }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,226 @@
+//! Character definition editor with structured views for W++ and Ali:Chat text
+
+use crate::{
+    Badge, BadgeVariant, Button, ButtonVariant, Input, Modal, ModalSize, Textarea, ToggleGroup, ToggleGroupItem,
+    ToggleSize, ToggleVariant,
+};
+use dioxus::prelude::*;
+use hearth_core::models::Character;
+use hearth_core::{parse_ali_chat, parse_wpp, print_ali_chat, print_wpp, AliChatSpeaker, DefinitionFormat};
+
+#[derive(Props, Clone, PartialEq)]
+pub struct CharacterDefinitionEditorProps {
+    pub is_open: Signal<bool>,
+    /// Character being edited
+    pub character: Signal<Character>,
+    pub on_save: EventHandler<Character>,
+}
+
+#[component]
+pub fn CharacterDefinitionEditor(mut props: CharacterDefinitionEditorProps) -> Element {
+    if !(props.is_open)() {
+        return rsx! { div {} };
+    }
+
+    let character = (props.character)();
+    let mut edit = move |change: fn(&mut Character, String), value: String| {
+        props.character.with_mut(|c| change(c, value));
+    };
+
+    rsx! {
+        Modal {
+            title: "Edit Character".to_string(),
+            is_open: props.is_open,
+            size: ModalSize::Large,
+
+            div { class: "flex flex-col p-4 space-y-4",
+                div { class: "space-y-1",
+                    div { class: "text-sm font-medium", "Name" }
+                    Input {
+                        value: character.name.clone(),
+                        placeholder: "Character name".to_string(),
+                        oninput: move |value| edit(|c, v| c.name = v, value),
+                    }
+                }
+                DefinitionField {
+                    key: "{character.id}-description",
+                    label: "Description",
+                    value: character.description.clone(),
+                    oninput: move |value| edit(|c, v| c.description = v, value),
+                }
+                DefinitionField {
+                    key: "{character.id}-personality",
+                    label: "Personality",
+                    value: character.personality.clone(),
+                    oninput: move |value| edit(|c, v| c.personality = v, value),
+                }
+                DefinitionField {
+                    key: "{character.id}-examples",
+                    label: "Example dialogue",
+                    value: character.example_dialogue.clone(),
+                    oninput: move |value| edit(|c, v| c.example_dialogue = v, value),
+                }
+
+                div { class: "flex justify-end space-x-2",
+                    Button {
+                        variant: ButtonVariant::Ghost,
+                        onclick: move |_| props.is_open.set(false),
+                        "Cancel"
+                    }
+                    Button {
+                        variant: ButtonVariant::Primary,
+                        onclick: move |_| props.on_save.call((props.character)()),
+                        "Save"
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A definition text that can be edited as fields when it is written in W++ or Ali:Chat
+#[component]
+fn DefinitionField(label: &'static str, value: String, oninput: EventHandler<String>) -> Element {
+    let mut structured = use_signal(|| false);
+
+    let format = DefinitionFormat::detect(&value);
+    let show_fields = structured() && format != DefinitionFormat::PlainText;
+    // Text that starts like W++ but doesn't parse gets a hint at where it went wrong
+    let problem = match format {
+        DefinitionFormat::PlainText if value.trim_start().starts_with('[') => {
+            parse_wpp(&value).err().map(|e| format!("Not valid W++: {e}"))
+        }
+        _ => None,
+    };
+    let format_label = match format {
+        DefinitionFormat::PlainText => "Plain text",
+        DefinitionFormat::WPlusPlus => "W++",
+        DefinitionFormat::AliChat => "Ali:Chat",
+    };
+
+    rsx! {
+        div { class: "space-y-1",
+            div { class: "flex items-center justify-between",
+                div { class: "flex items-center space-x-2",
+                    div { class: "text-sm font-medium", "{label}" }
+                    Badge { variant: BadgeVariant::Outline, "{format_label}" }
+                }
+                ToggleGroup {
+                    exclusive: true,
+                    size: ToggleSize::Small,
+                    variant: ToggleVariant::Outline,
+                    ToggleGroupItem {
+                        value: "plain",
+                        pressed: !show_fields,
+                        size: ToggleSize::Small,
+                        variant: ToggleVariant::Outline,
+                        onclick: move |_| structured.set(false),
+                        "Plain"
+                    }
+                    ToggleGroupItem {
+                        value: "structured",
+                        pressed: show_fields,
+                        disabled: format == DefinitionFormat::PlainText,
+                        size: ToggleSize::Small,
+                        variant: ToggleVariant::Outline,
+                        onclick: move |_| structured.set(true),
+                        "Structured"
+                    }
+                }
+            }
+            if let Some(problem) = problem {
+                div { class: "text-xs text-destructive", "{problem}" }
+            }
+            if show_fields && format == DefinitionFormat::WPlusPlus {
+                WppFields { value: value.clone(), oninput }
+            } else if show_fields && format == DefinitionFormat::AliChat {
+                AliChatFields { value: value.clone(), oninput }
+            } else {
+                Textarea {
+                    value: value.clone(),
+                    rows: 5,
+                    class: "font-mono text-xs".to_string(),
+                    oninput: move |value| oninput.call(value),
+                }
+            }
+        }
+    }
+}
+
+/// One input per W++ attribute, with its values separated by ` + `
+#[component]
+fn WppFields(value: String, oninput: EventHandler<String>) -> Element {
+    let blocks = parse_wpp(&value).unwrap_or_default();
+
+    rsx! {
+        div { class: "space-y-3 p-3 rounded-lg border border-border",
+            for (block_index, block) in blocks.iter().enumerate() {
+                div { key: "{block_index}", class: "space-y-2",
+                    div { class: "text-xs font-medium text-muted-foreground", "{block.kind}: {block.name}" }
+                    for (attribute_index, attribute) in block.attributes.iter().enumerate() {
+                        div {
+                            key: "{attribute_index}",
+                            class: "grid grid-cols-3 gap-2 items-center",
+                            div { class: "text-sm truncate", "{attribute.name}" }
+                            div { class: "col-span-2",
+                                Input {
+                                    value: attribute.values.join(" + "),
+                                    oninput: {
+                                        let mut blocks = blocks.clone();
+                                        move |text: String| {
+                                            let values = text.split(" + ").map(str::to_string).collect();
+                                            blocks[block_index].attributes[attribute_index].values = values;
+                                            oninput.call(print_wpp(&blocks));
+                                        }
+                                    },
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The example exchanges, one text box per line of dialogue
+#[component]
+fn AliChatFields(value: String, oninput: EventHandler<String>) -> Element {
+    let blocks = parse_ali_chat(&value).unwrap_or_default();
+
+    rsx! {
+        div { class: "space-y-3 p-3 rounded-lg border border-border",
+            for (block_index, block) in blocks.iter().enumerate() {
+                div { key: "{block_index}", class: "space-y-2",
+                    div { class: "text-xs font-medium text-muted-foreground", "Example {block_index + 1}" }
+                    for (line_index, line) in block.iter().enumerate() {
+                        div { key: "{line_index}", class: "flex items-start space-x-2",
+                            div { class: "w-16 pt-2 text-xs font-mono text-muted-foreground",
+                                {speaker_label(line.speaker)}
+                            }
+                            Textarea {
+                                value: line.text.clone(),
+                                rows: 2,
+                                class: "flex-1 text-sm".to_string(),
+                                oninput: {
+                                    let mut blocks = blocks.clone();
+                                    move |text: String| {
+                                        blocks[block_index][line_index].text = text;
+                                        oninput.call(print_ali_chat(&blocks));
+                                    }
+                                },
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn speaker_label(speaker: AliChatSpeaker) -> &'static str {
+    match speaker {
+        AliChatSpeaker::User => "{{user}}",
+        AliChatSpeaker::Character => "{{char}}",
+    }
+}
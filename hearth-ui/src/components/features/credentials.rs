@@ -0,0 +1,115 @@
+//! Passphrase protection for stored API keys and auth tokens
+
+use crate::{
+    use_settings, use_toaster, Badge, BadgeSize, BadgeVariant, Button, ButtonSize, ButtonVariant, Input,
+    InputType, SettingsSection,
+};
+use dioxus::prelude::*;
+
+#[component]
+pub fn CredentialsSection() -> Element {
+    let mut settings = use_settings();
+    let toaster = use_toaster();
+    let mut passphrase = use_signal(String::new);
+
+    let locked = settings.read().credentials_locked();
+    let uses_passphrase = settings.read().credentials_use_passphrase();
+    let error = settings.read().credentials_error().map(str::to_string);
+    let description = match (&error, locked) {
+        (Some(error), _) => format!("Stored keys could not be read: {error}"),
+        (None, true) => "Enter the passphrase to use stored keys".to_string(),
+        (None, false) => "Stored encrypted, outside the settings file".to_string(),
+    };
+    let status = match (locked, uses_passphrase) {
+        _ if error.is_some() => "Unavailable",
+        (true, _) => "Locked",
+        (false, true) => "Passphrase",
+        (false, false) => "Key file",
+    };
+
+    rsx! {
+        SettingsSection { title: "Credentials",
+            div { class: "space-y-3 px-4 py-2",
+                div { class: "flex items-center justify-between",
+                    div {
+                        div { class: "text-sm font-medium", "API keys and tokens" }
+                        div { class: "text-xs text-muted-foreground",
+                            "{description}"
+                        }
+                    }
+                    Badge {
+                        variant: match (&error, locked) {
+                            (Some(_), _) => BadgeVariant::Error,
+                            (None, true) => BadgeVariant::Warning,
+                            (None, false) => BadgeVariant::Secondary,
+                        },
+                        size: BadgeSize::Small,
+                        "{status}"
+                    }
+                }
+
+                if error.is_none() {
+                    div { class: "flex items-center space-x-2",
+                        div { class: "flex-1",
+                            Input {
+                                input_type: InputType::Password,
+                                value: passphrase(),
+                                placeholder: if locked { "Passphrase".to_string() } else { "New passphrase".to_string() },
+                                oninput: move |value| passphrase.set(value),
+                            }
+                        }
+                        if locked {
+                            Button {
+                                variant: ButtonVariant::Primary,
+                                size: ButtonSize::Small,
+                                onclick: move |_| {
+                                    match settings.write().unlock_credentials(&passphrase()) {
+                                        Ok(()) => {
+                                            toaster.success("Credentials unlocked");
+                                        }
+                                        Err(e) => {
+                                            toaster.error(e.to_string());
+                                        }
+                                    }
+                                    passphrase.set(String::new());
+                                },
+                                "Unlock"
+                            }
+                        } else {
+                            Button {
+                                variant: ButtonVariant::Outline,
+                                size: ButtonSize::Small,
+                                disabled: passphrase().is_empty(),
+                                onclick: move |_| {
+                                    match settings.write().set_credentials_passphrase(Some(&passphrase())) {
+                                        Ok(()) => {
+                                            toaster.success("Credentials are now protected by your passphrase");
+                                        }
+                                        Err(e) => {
+                                            toaster.error(format!("Failed to set passphrase: {e}"));
+                                        }
+                                    }
+                                    passphrase.set(String::new());
+                                },
+                                "Set Passphrase"
+                            }
+                        }
+                    }
+                }
+
+                if uses_passphrase && !locked {
+                    Button {
+                        variant: ButtonVariant::Ghost,
+                        size: ButtonSize::Small,
+                        onclick: move |_| {
+                            if let Err(e) = settings.write().set_credentials_passphrase(None) {
+                                toaster.error(format!("Failed to remove passphrase: {e}"));
+                            }
+                        },
+                        "Use Key File Instead"
+                    }
+                }
+            }
+        }
+    }
+}
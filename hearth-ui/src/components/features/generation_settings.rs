@@ -0,0 +1,204 @@
+//! Sampler preset selection and the per-story generation settings panel
+
+use crate::{
+    use_settings, use_toaster, Button, ButtonSize, ButtonVariant, Input, InputType, Modal, Select,
+    SelectOption, SettingsSection, Textarea,
+};
+use dioxus::prelude::*;
+use hearth_core::{SamplerPreset, SamplerPresets, SamplerSettings, DEFAULT_PRESET};
+use super::optional_value::{parse, show};
+
+/// Global preset choice and removal of user presets
+#[component]
+pub fn SamplerPresetsSection() -> Element {
+    let mut settings = use_settings();
+    let presets = settings.read().get().sampler_presets.clone();
+    let selected = presets.selected.clone().unwrap_or_else(|| DEFAULT_PRESET.to_string());
+
+    let mut update_presets = move |change: &dyn Fn(&mut SamplerPresets)| {
+        let mut updated = settings.peek().get().clone();
+        change(&mut updated.sampler_presets);
+        settings.write().update(updated);
+    };
+
+    rsx! {
+        SettingsSection { title: "Generation",
+            div { class: "space-y-2 px-4",
+                div { class: "flex items-center justify-between py-2 border-b border-border",
+                    div {
+                        div { class: "text-sm font-medium", "Sampler Preset" }
+                        div { class: "text-xs text-muted-foreground", "Used by stories without their own settings" }
+                    }
+                    div { class: "w-48",
+                        Select {
+                            value: selected,
+                            options: presets.all().iter().map(|p| SelectOption::new(&p.name, &p.name)).collect::<Vec<_>>(),
+                            onchange: move |name: String| update_presets(&|presets| {
+                                presets.selected = (name != DEFAULT_PRESET).then(|| name.clone());
+                            }),
+                        }
+                    }
+                }
+
+                for preset in presets.all().into_iter().filter(|p| presets.is_custom(&p.name)) {
+                    div {
+                        key: "{preset.name}",
+                        class: "flex items-center justify-between py-1",
+                        span { class: "text-sm", "{preset.name}" }
+                        Button {
+                            variant: ButtonVariant::Ghost,
+                            size: ButtonSize::Small,
+                            onclick: {
+                                let name = preset.name.clone();
+                                move |_| update_presets(&|presets| {
+                                    presets.remove(&name);
+                                })
+                            },
+                            if SamplerPresets::is_builtin(&preset.name) {
+                                i { class: "fas fa-undo text-xs" }
+                            } else {
+                                i { class: "fas fa-trash text-xs" }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Props, Clone, PartialEq)]
+pub struct GenerationSettingsPanelProps {
+    pub is_open: Signal<bool>,
+    /// Settings being edited
+    pub draft: Signal<SamplerSettings>,
+    /// Called with the story's own settings, or `None` to follow the global preset
+    pub on_save: EventHandler<Option<SamplerSettings>>,
+}
+
+#[component]
+pub fn GenerationSettingsPanel(mut props: GenerationSettingsPanelProps) -> Element {
+    let mut settings = use_settings();
+    let toaster = use_toaster();
+    let mut preset_name = use_signal(String::new);
+
+    if !(props.is_open)() {
+        return rsx! { div {} };
+    }
+
+    let presets = settings.read().get().sampler_presets.clone();
+    let preset_options: Vec<SelectOption> =
+        presets.all().iter().map(|p| SelectOption::new(&p.name, &p.name)).collect();
+    let draft = (props.draft)();
+    let mut edit = move |change: fn(&mut SamplerSettings, String), value: String| {
+        props.draft.with_mut(|s| change(s, value));
+    };
+
+    rsx! {
+        Modal {
+            title: "Generation Settings".to_string(),
+            is_open: props.is_open,
+
+            div { class: "flex flex-col p-4 space-y-4",
+                div { class: "text-sm text-muted-foreground",
+                    "These settings apply to this story only. Empty fields use the provider's default."
+                }
+
+                div { class: "space-y-1",
+                    div { class: "text-sm font-medium", "Start from preset" }
+                    Select {
+                        value: String::new(),
+                        placeholder: "Choose a preset...".to_string(),
+                        options: preset_options,
+                        onchange: move |name: String| {
+                            if let Some(preset) = presets.get(&name) {
+                                props.draft.set(preset.settings);
+                            }
+                        },
+                    }
+                }
+
+                div { class: "grid grid-cols-2 gap-3",
+                    SamplerField { label: "Temperature", value: show(draft.temperature), onchange: move |v| edit(|s, v| s.temperature = parse(&v), v) }
+                    SamplerField { label: "Top P", value: show(draft.top_p), onchange: move |v| edit(|s, v| s.top_p = parse(&v), v) }
+                    SamplerField { label: "Top K", value: show(draft.top_k), onchange: move |v| edit(|s, v| s.top_k = parse(&v), v) }
+                    SamplerField { label: "Min P", value: show(draft.min_p), onchange: move |v| edit(|s, v| s.min_p = parse(&v), v) }
+                    SamplerField { label: "Repetition penalty", value: show(draft.repetition_penalty), onchange: move |v| edit(|s, v| s.repetition_penalty = parse(&v), v) }
+                    SamplerField { label: "Frequency penalty", value: show(draft.frequency_penalty), onchange: move |v| edit(|s, v| s.frequency_penalty = parse(&v), v) }
+                    SamplerField { label: "Presence penalty", value: show(draft.presence_penalty), onchange: move |v| edit(|s, v| s.presence_penalty = parse(&v), v) }
+                    SamplerField { label: "Seed", value: show(draft.seed), onchange: move |v| edit(|s, v| s.seed = parse(&v), v) }
+                }
+
+                div { class: "space-y-1",
+                    div { class: "text-sm font-medium", "Stop strings" }
+                    div { class: "text-xs text-muted-foreground", "One per line" }
+                    Textarea {
+                        value: draft.stop.join("\n"),
+                        rows: 3,
+                        onchange: move |value: String| {
+                            edit(
+                                |s, v| s.stop = v.lines().filter(|l| !l.is_empty()).map(str::to_string).collect(),
+                                value,
+                            )
+                        },
+                    }
+                }
+
+                div { class: "flex items-center space-x-2",
+                    div { class: "flex-1",
+                        Input {
+                            value: preset_name(),
+                            placeholder: "Preset name".to_string(),
+                            oninput: move |value| preset_name.set(value),
+                        }
+                    }
+                    Button {
+                        variant: ButtonVariant::Outline,
+                        size: ButtonSize::Small,
+                        onclick: move |_| {
+                            let name = preset_name().trim().to_string();
+                            if name.is_empty() {
+                                toaster.error("Presets need a name");
+                                return;
+                            }
+                            let mut updated = settings.peek().get().clone();
+                            updated.sampler_presets.save(SamplerPreset::new(name.clone(), (props.draft)()));
+                            settings.write().update(updated);
+                            preset_name.set(String::new());
+                            toaster.success(format!("Saved preset '{name}'"));
+                        },
+                        "Save as Preset"
+                    }
+                }
+
+                div { class: "flex justify-end space-x-2",
+                    Button {
+                        variant: ButtonVariant::Ghost,
+                        onclick: move |_| props.on_save.call(None),
+                        "Use Global Preset"
+                    }
+                    Button {
+                        variant: ButtonVariant::Primary,
+                        onclick: move |_| props.on_save.call(Some((props.draft)())),
+                        "Save"
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[component]
+fn SamplerField(label: &'static str, value: String, onchange: EventHandler<String>) -> Element {
+    rsx! {
+        div { class: "space-y-1",
+            div { class: "text-xs font-medium text-muted-foreground", "{label}" }
+            Input {
+                input_type: InputType::Number,
+                value: value,
+                placeholder: "Default".to_string(),
+                onchange: move |value| onchange.call(value),
+            }
+        }
+    }
+}
@@ -0,0 +1,293 @@
+//! Instruct template management for text completion providers
+
+use crate::{
+    use_settings, use_toaster, Badge, BadgeSize, BadgeVariant, Button, ButtonSize, ButtonVariant,
+    Input, Modal, Select, SelectOption, SettingsSection, Textarea,
+};
+use dioxus::prelude::*;
+use hearth_core::{InstructTemplate, LlmProviderType, TemplateRegistry};
+
+/// Select value for providers that use their chat API
+const CHAT_API: &str = "chat";
+
+#[component]
+pub fn InstructTemplatesSection() -> Element {
+    let mut settings = use_settings();
+    let toaster = use_toaster();
+    let mut editor_open = use_signal(|| false);
+    let mut editing = use_signal(InstructTemplate::default);
+    let mut import_open = use_signal(|| false);
+    let mut import_text = use_signal(String::new);
+
+    let current = settings.read().get().clone();
+    let templates = current.instruct_templates.all();
+    // Only these providers can generate from a raw prompt
+    let providers: Vec<_> = current
+        .local_backend
+        .iter()
+        .flat_map(|local| local.llm_providers.iter())
+        .filter(|p| matches!(p.provider_type, LlmProviderType::Ollama | LlmProviderType::Custom))
+        .cloned()
+        .collect();
+    let template_options = {
+        let mut options = vec![SelectOption::new(CHAT_API, "None (chat API)")];
+        options.extend(templates.iter().map(|t| SelectOption::new(&t.name, &t.name)));
+        options
+    };
+
+    let mut update_templates = move |change: &dyn Fn(&mut TemplateRegistry)| {
+        let mut updated = settings.peek().get().clone();
+        change(&mut updated.instruct_templates);
+        settings.write().update(updated);
+    };
+
+    rsx! {
+        SettingsSection { title: "Instruct Templates",
+            div { class: "space-y-4 px-4",
+                for provider in providers.iter() {
+                    div {
+                        key: "{provider.id}",
+                        class: "flex items-center justify-between py-2 border-b border-border",
+                        div {
+                            div { class: "text-sm font-medium", "{provider.name}" }
+                            div { class: "text-xs text-muted-foreground", "Prompt format for text completion" }
+                        }
+                        div { class: "w-48",
+                            Select {
+                                value: provider.config.instruct_template.clone().unwrap_or_else(|| CHAT_API.to_string()),
+                                options: template_options.clone(),
+                                onchange: {
+                                    let provider_id = provider.id.clone();
+                                    move |value: String| {
+                                        let mut updated = settings.peek().get().clone();
+                                        if let Some(provider) = updated
+                                            .local_backend
+                                            .as_mut()
+                                            .and_then(|local| local.llm_providers.iter_mut().find(|p| p.id == provider_id))
+                                        {
+                                            provider.config.instruct_template = (value != CHAT_API).then_some(value);
+                                        }
+                                        settings.write().update(updated);
+                                    }
+                                },
+                            }
+                        }
+                    }
+                }
+
+                div { class: "space-y-2",
+                    for template in templates.iter() {
+                        div {
+                            key: "{template.name}",
+                            class: "flex items-center justify-between py-2",
+                            div { class: "flex items-center space-x-2",
+                                span { class: "text-sm font-medium", "{template.name}" }
+                                if !TemplateRegistry::is_builtin(&template.name) {
+                                    Badge { variant: BadgeVariant::Secondary, size: BadgeSize::Small, "Custom" }
+                                } else if current.instruct_templates.is_custom(&template.name) {
+                                    Badge { variant: BadgeVariant::Secondary, size: BadgeSize::Small, "Modified" }
+                                }
+                            }
+                            div { class: "flex items-center space-x-1",
+                                Button {
+                                    variant: ButtonVariant::Ghost,
+                                    size: ButtonSize::Small,
+                                    onclick: {
+                                        let template = template.clone();
+                                        move |_| {
+                                            editing.set(template.clone());
+                                            editor_open.set(true);
+                                        }
+                                    },
+                                    i { class: "fas fa-pen text-xs" }
+                                }
+                                if current.instruct_templates.is_custom(&template.name) {
+                                    Button {
+                                        variant: ButtonVariant::Ghost,
+                                        size: ButtonSize::Small,
+                                        onclick: {
+                                            let name = template.name.clone();
+                                            move |_| update_templates(&|registry| {
+                                                registry.remove(&name);
+                                            })
+                                        },
+                                        if TemplateRegistry::is_builtin(&template.name) {
+                                            i { class: "fas fa-undo text-xs" }
+                                        } else {
+                                            i { class: "fas fa-trash text-xs" }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+
+                div { class: "flex justify-end space-x-2",
+                    Button {
+                        variant: ButtonVariant::Outline,
+                        size: ButtonSize::Small,
+                        onclick: move |_| {
+                            import_text.set(String::new());
+                            import_open.set(true);
+                        },
+                        "Import"
+                    }
+                    Button {
+                        variant: ButtonVariant::Outline,
+                        size: ButtonSize::Small,
+                        onclick: move |_| {
+                            editing.set(InstructTemplate::default());
+                            editor_open.set(true);
+                        },
+                        "New Template"
+                    }
+                }
+            }
+        }
+
+        InstructTemplateEditor {
+            is_open: editor_open,
+            template: editing,
+            on_save: move |template: InstructTemplate| {
+                if template.name.trim().is_empty() {
+                    toaster.error("Templates need a name");
+                    return;
+                }
+                update_templates(&|registry| registry.save(template.clone()));
+                editor_open.set(false);
+                toaster.success("Template saved");
+            },
+        }
+
+        if import_open() {
+            Modal {
+                title: "Import Template".to_string(),
+                is_open: import_open,
+
+                div { class: "flex flex-col p-4 space-y-4",
+                    div { class: "text-sm text-muted-foreground",
+                        "Paste a template exported from Hearth or a SillyTavern instruct preset."
+                    }
+                    Textarea {
+                        value: import_text(),
+                        rows: 10,
+                        oninput: move |value: String| import_text.set(value),
+                    }
+                    div { class: "flex justify-end space-x-2",
+                        Button {
+                            variant: ButtonVariant::Ghost,
+                            onclick: move |_| import_open.set(false),
+                            "Cancel"
+                        }
+                        Button {
+                            variant: ButtonVariant::Primary,
+                            onclick: move |_| match InstructTemplate::import(&import_text()) {
+                                Ok(template) => {
+                                    let name = template.name.clone();
+                                    update_templates(&|registry| registry.save(template.clone()));
+                                    import_open.set(false);
+                                    toaster.success(format!("Imported '{name}'"));
+                                }
+                                Err(e) => {
+                                    toaster.error(format!("Failed to import template: {e}"));
+                                }
+                            },
+                            "Import"
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Props, Clone, PartialEq)]
+pub struct InstructTemplateEditorProps {
+    pub is_open: Signal<bool>,
+    /// Template being edited
+    pub template: Signal<InstructTemplate>,
+    pub on_save: EventHandler<InstructTemplate>,
+}
+
+#[component]
+pub fn InstructTemplateEditor(mut props: InstructTemplateEditorProps) -> Element {
+    if !(props.is_open)() {
+        return rsx! { div {} };
+    }
+
+    let template = (props.template)();
+    let mut edit = move |change: fn(&mut InstructTemplate, String), value: String| {
+        props.template.with_mut(|t| change(t, value));
+    };
+
+    rsx! {
+        Modal {
+            title: "Edit Template".to_string(),
+            is_open: props.is_open,
+
+            div { class: "flex flex-col p-4 space-y-4",
+                div { class: "space-y-1",
+                    div { class: "text-sm font-medium", "Name" }
+                    Input {
+                        value: template.name.clone(),
+                        placeholder: "Template name".to_string(),
+                        oninput: move |value| edit(|t, v| t.name = v, value),
+                    }
+                }
+
+                div { class: "grid grid-cols-2 gap-3",
+                    WrapperField { label: "System prefix", value: template.system_prefix.clone(), oninput: move |value| edit(|t, v| t.system_prefix = v, value) }
+                    WrapperField { label: "System suffix", value: template.system_suffix.clone(), oninput: move |value| edit(|t, v| t.system_suffix = v, value) }
+                    WrapperField { label: "User prefix", value: template.user_prefix.clone(), oninput: move |value| edit(|t, v| t.user_prefix = v, value) }
+                    WrapperField { label: "User suffix", value: template.user_suffix.clone(), oninput: move |value| edit(|t, v| t.user_suffix = v, value) }
+                    WrapperField { label: "Assistant prefix", value: template.assistant_prefix.clone(), oninput: move |value| edit(|t, v| t.assistant_prefix = v, value) }
+                    WrapperField { label: "Assistant suffix", value: template.assistant_suffix.clone(), oninput: move |value| edit(|t, v| t.assistant_suffix = v, value) }
+                }
+
+                div { class: "space-y-1",
+                    div { class: "text-sm font-medium", "Stop sequences" }
+                    div { class: "text-xs text-muted-foreground", "One per line" }
+                    Textarea {
+                        value: template.stop_sequences.join("\n"),
+                        rows: 3,
+                        oninput: move |value: String| {
+                            edit(
+                                |t, v| t.stop_sequences = v.lines().filter(|l| !l.is_empty()).map(str::to_string).collect(),
+                                value,
+                            )
+                        },
+                    }
+                }
+
+                div { class: "flex justify-end space-x-2",
+                    Button {
+                        variant: ButtonVariant::Ghost,
+                        onclick: move |_| props.is_open.set(false),
+                        "Cancel"
+                    }
+                    Button {
+                        variant: ButtonVariant::Primary,
+                        onclick: move |_| props.on_save.call((props.template)()),
+                        "Save"
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[component]
+fn WrapperField(label: &'static str, value: String, oninput: EventHandler<String>) -> Element {
+    rsx! {
+        div { class: "space-y-1",
+            div { class: "text-xs font-medium text-muted-foreground", "{label}" }
+            Textarea {
+                value: value,
+                rows: 2,
+                class: "font-mono text-xs".to_string(),
+                oninput: move |value| oninput.call(value),
+            }
+        }
+    }
+}
@@ -0,0 +1,436 @@
+//! Provider management shown in the language model settings
+
+use crate::{
+    use_settings, use_toaster, Badge, BadgeSize, BadgeVariant, Button, ButtonSize, ButtonVariant, Input,
+    InputType, Modal, ModelPicker, Platform, Select, SelectOption, ToastManager,
+};
+use dioxus::prelude::*;
+use hearth_core::llm::{connect_for_discovery, test_connection, LlmError};
+use hearth_core::{mask_secret, LlmProviderConfig, LlmProviderType, TemplateRegistry};
+use super::optional_value::{parse, show};
+
+const PROVIDER_TYPES: [LlmProviderType; 4] = [
+    LlmProviderType::Ollama,
+    LlmProviderType::OpenAI,
+    LlmProviderType::Anthropic,
+    LlmProviderType::Custom,
+];
+
+fn type_label(provider_type: &LlmProviderType) -> &'static str {
+    match provider_type {
+        LlmProviderType::Ollama => "Ollama",
+        LlmProviderType::OpenAI => "OpenAI",
+        LlmProviderType::Anthropic => "Anthropic",
+        LlmProviderType::Custom => "OpenAI-compatible",
+    }
+}
+
+/// Configured providers, which one is selected and the fallbacks tried after it
+#[component]
+pub fn LlmProviderList() -> Element {
+    let mut settings = use_settings();
+    let toaster = use_toaster();
+    let mut editor_open = use_signal(|| false);
+    let mut draft = use_signal(|| LlmProviderConfig::new(LlmProviderType::Ollama));
+
+    let local = settings.read().get().local_backend.clone().unwrap_or_default();
+    let selected_id = local.selected_provider().map(|p| p.id.clone());
+    let templates = settings.read().get().instruct_templates.clone();
+    let fallback_options: Vec<SelectOption> = local
+        .llm_providers
+        .iter()
+        .filter(|p| Some(&p.id) != selected_id.as_ref() && !local.fallback_providers.contains(&p.id))
+        .map(|p| SelectOption::new(&p.id, &p.name))
+        .collect();
+    let policy = local.retry_policy.clone();
+
+    rsx! {
+        div { class: "space-y-1",
+            for provider in local.llm_providers.iter() {
+                div {
+                    key: "{provider.id}",
+                    class: "w-full py-3 px-4 flex items-center space-x-4 hover:bg-muted transition-colors rounded-lg",
+
+                    button {
+                        class: "flex-1 min-w-0 flex items-center space-x-4 text-left",
+                        onclick: {
+                            let id = provider.id.clone();
+                            move |_| settings.write().update_providers(|local| {
+                                local.selected_llm_provider = Some(id.clone());
+                            })
+                        },
+                        if selected_id.as_deref() == Some(provider.id.as_str()) {
+                            i { class: "fa-solid fa-circle-dot text-xl text-primary" }
+                        } else {
+                            i { class: "fa-regular fa-circle text-xl text-muted-foreground" }
+                        }
+                        div { class: "min-w-0",
+                            div { class: "flex items-center space-x-2",
+                                span { class: "font-medium text-foreground", "{provider.name}" }
+                                Badge { variant: BadgeVariant::Secondary, size: BadgeSize::Small,
+                                    {type_label(&provider.provider_type)}
+                                }
+                            }
+                            div { class: "text-sm text-muted-foreground mt-0.5 truncate",
+                                "{provider.config.model}"
+                                if let Some(key) = provider.config.api_key.as_deref().filter(|k| !k.is_empty()) {
+                                    {format!(" · key {}", mask_secret(key))}
+                                }
+                            }
+                        }
+                    }
+
+                    div { class: "flex items-center space-x-1",
+                        Button {
+                            variant: ButtonVariant::Ghost,
+                            size: ButtonSize::Small,
+                            onclick: {
+                                let provider = provider.clone();
+                                let templates = templates.clone();
+                                move |_| run_connection_test(&provider, &templates, toaster)
+                            },
+                            i { class: "fas fa-plug text-xs" }
+                        }
+                        Button {
+                            variant: ButtonVariant::Ghost,
+                            size: ButtonSize::Small,
+                            onclick: {
+                                let provider = provider.clone();
+                                move |_| {
+                                    draft.set(provider.clone());
+                                    editor_open.set(true);
+                                }
+                            },
+                            i { class: "fas fa-pen text-xs" }
+                        }
+                        Button {
+                            variant: ButtonVariant::Ghost,
+                            size: ButtonSize::Small,
+                            onclick: {
+                                let id = provider.id.clone();
+                                move |_| settings.write().update_providers(|local| {
+                                    local.duplicate_provider(&id);
+                                })
+                            },
+                            i { class: "fas fa-copy text-xs" }
+                        }
+                        Button {
+                            variant: ButtonVariant::Ghost,
+                            size: ButtonSize::Small,
+                            onclick: {
+                                let id = provider.id.clone();
+                                move |_| settings.write().update_providers(|local| local.remove_provider(&id))
+                            },
+                            i { class: "fas fa-trash text-xs" }
+                        }
+                    }
+                }
+            }
+
+            if local.llm_providers.is_empty() {
+                div { class: "px-4 py-2 text-sm text-muted-foreground", "No providers configured" }
+            }
+
+            button {
+                class: "w-full py-4 px-4 flex items-center space-x-4 hover:bg-muted transition-colors text-left rounded-lg",
+                onclick: move |_| {
+                    draft.set(LlmProviderConfig::new(LlmProviderType::Ollama));
+                    editor_open.set(true);
+                },
+                i { class: "fa-solid fa-plus text-xl text-foreground" }
+                div { class: "font-medium text-muted-foreground", "Add Provider" }
+            }
+
+            // What happens when the selected provider fails
+            div { class: "px-4 pt-4 mt-2 border-t border-border space-y-3",
+                div {
+                    div { class: "text-sm font-medium", "Fallback Providers" }
+                    div { class: "text-xs text-muted-foreground",
+                        "Tried in order when the selected provider keeps failing"
+                    }
+                }
+                for (position, fallback) in local.fallback_providers.iter().filter_map(|id| local.llm_providers.iter().find(|p| &p.id == id)).enumerate() {
+                    div {
+                        key: "{fallback.id}",
+                        class: "flex items-center justify-between py-1",
+                        span { class: "text-sm", {format!("{}. {}", position + 1, fallback.name)} }
+                        div { class: "flex items-center space-x-1",
+                            Button {
+                                variant: ButtonVariant::Ghost,
+                                size: ButtonSize::Small,
+                                disabled: position == 0,
+                                onclick: {
+                                    let id = fallback.id.clone();
+                                    move |_| settings.write().update_providers(|local| {
+                                        if let Some(index) = local.fallback_providers.iter().position(|p| *p == id) {
+                                            local.fallback_providers.swap(index.saturating_sub(1), index);
+                                        }
+                                    })
+                                },
+                                i { class: "fas fa-arrow-up text-xs" }
+                            }
+                            Button {
+                                variant: ButtonVariant::Ghost,
+                                size: ButtonSize::Small,
+                                onclick: {
+                                    let id = fallback.id.clone();
+                                    move |_| settings.write().update_providers(|local| local.fallback_providers.retain(|p| *p != id))
+                                },
+                                i { class: "fas fa-xmark text-xs" }
+                            }
+                        }
+                    }
+                }
+                if !fallback_options.is_empty() {
+                    Select {
+                        value: String::new(),
+                        placeholder: "Add fallback...".to_string(),
+                        options: fallback_options,
+                        onchange: move |id: String| settings.write().update_providers(|local| local.fallback_providers.push(id)),
+                    }
+                }
+
+                div { class: "grid grid-cols-3 gap-3",
+                    Field { label: "Timeout (s)",
+                        Input {
+                            input_type: InputType::Number,
+                            value: policy.timeout_secs.to_string(),
+                            onchange: move |v: String| if let Ok(v) = v.trim().parse() {
+                                settings.write().update_providers(|local| local.retry_policy.timeout_secs = v)
+                            },
+                        }
+                    }
+                    Field { label: "Retries",
+                        Input {
+                            input_type: InputType::Number,
+                            value: policy.max_retries.to_string(),
+                            onchange: move |v: String| if let Ok(v) = v.trim().parse() {
+                                settings.write().update_providers(|local| local.retry_policy.max_retries = v)
+                            },
+                        }
+                    }
+                    Field { label: "First retry after (ms)",
+                        Input {
+                            input_type: InputType::Number,
+                            value: policy.initial_backoff_ms.to_string(),
+                            onchange: move |v: String| if let Ok(v) = v.trim().parse() {
+                                settings.write().update_providers(|local| local.retry_policy.initial_backoff_ms = v)
+                            },
+                        }
+                    }
+                }
+            }
+        }
+
+        ProviderEditor {
+            is_open: editor_open,
+            draft: draft,
+            on_save: move |provider: LlmProviderConfig| {
+                let name = provider.name.clone();
+                settings.write().update_providers(|local| {
+                    if local.llm_providers.is_empty() {
+                        local.selected_llm_provider = Some(provider.id.clone());
+                    }
+                    local.save_provider(provider);
+                });
+                editor_open.set(false);
+                toaster.success(format!("Saved '{name}'"));
+            },
+        }
+    }
+}
+
+/// Report the latency or the failure of a provider's connection as a toast
+fn run_connection_test(provider: &LlmProviderConfig, templates: &TemplateRegistry, toaster: ToastManager) {
+    let client = match connect_for_discovery(provider, templates) {
+        Ok(client) => client,
+        Err(e) => {
+            toaster.error(format!("Cannot connect: {e}"));
+            return;
+        }
+    };
+    let name = provider.name.clone();
+    let has_model = !provider.config.model.is_empty();
+    Platform::spawn(async move {
+        match test_connection(client.as_ref()).await {
+            Ok(test) if test.model_available || !has_model => {
+                toaster.success(format!("{name} responded in {} ms", test.latency_ms));
+            }
+            Ok(test) => {
+                toaster.info(format!(
+                    "{name} responded in {} ms, but does not list the configured model",
+                    test.latency_ms
+                ));
+            }
+            Err(LlmError::Unauthorized(_)) => {
+                toaster.error(format!("{name} rejected the API key"));
+            }
+            Err(e) => {
+                toaster.error(format!("{name} is unreachable: {e}"));
+            }
+        }
+    });
+}
+
+#[derive(Props, Clone, PartialEq)]
+pub struct ProviderEditorProps {
+    pub is_open: Signal<bool>,
+    /// Provider being edited
+    pub draft: Signal<LlmProviderConfig>,
+    /// Called with a provider that passed validation
+    pub on_save: EventHandler<LlmProviderConfig>,
+}
+
+#[component]
+pub fn ProviderEditor(mut props: ProviderEditorProps) -> Element {
+    let mut show_key = use_signal(|| false);
+    let mut error = use_signal(|| None::<String>);
+
+    if !(props.is_open)() {
+        return rsx! { div {} };
+    }
+
+    let draft = (props.draft)();
+    let mut edit = move |change: fn(&mut LlmProviderConfig, String), value: String| {
+        props.draft.with_mut(|p| change(p, value));
+        error.set(None);
+    };
+
+    rsx! {
+        Modal {
+            title: "LLM Provider".to_string(),
+            is_open: props.is_open,
+
+            div { class: "flex flex-col p-4 space-y-4",
+                Field { label: "Name",
+                    Input {
+                        value: draft.name.clone(),
+                        oninput: move |v| edit(|p, v| p.name = v, v),
+                    }
+                }
+
+                Field { label: "Type",
+                    Select {
+                        value: type_label(&draft.provider_type).to_string(),
+                        options: PROVIDER_TYPES.iter().map(|t| SelectOption::new(type_label(t), type_label(t))).collect::<Vec<_>>(),
+                        onchange: move |label: String| {
+                            let Some(provider_type) = PROVIDER_TYPES.into_iter().find(|t| type_label(t) == label) else { return };
+                            // Switching type starts from that type's defaults but keeps identity and key
+                            props.draft.with_mut(|p| {
+                                let defaults = LlmProviderConfig::new(provider_type);
+                                p.provider_type = defaults.provider_type;
+                                p.config.base_url = defaults.config.base_url;
+                                p.config.model = defaults.config.model;
+                            });
+                        },
+                    }
+                }
+
+                Field { label: "Base URL",
+                    Input {
+                        input_type: InputType::Url,
+                        value: draft.config.base_url.clone().unwrap_or_default(),
+                        placeholder: "Provider default".to_string(),
+                        oninput: move |v| edit(|p, v| p.config.base_url = Some(v).filter(|v| !v.trim().is_empty()), v),
+                    }
+                }
+
+                Field { label: "API Key",
+                    div { class: "flex items-center space-x-2",
+                        div { class: "flex-1",
+                            Input {
+                                input_type: if show_key() { InputType::Text } else { InputType::Password },
+                                value: draft.config.api_key.clone().unwrap_or_default(),
+                                placeholder: "Not set".to_string(),
+                                oninput: move |v| edit(|p, v| p.config.api_key = Some(v).filter(|v| !v.is_empty()), v),
+                            }
+                        }
+                        Button {
+                            variant: ButtonVariant::Ghost,
+                            size: ButtonSize::Small,
+                            onclick: move |_| show_key.set(!show_key()),
+                            if show_key() {
+                                i { class: "fas fa-eye-slash text-xs" }
+                            } else {
+                                i { class: "fas fa-eye text-xs" }
+                            }
+                        }
+                    }
+                }
+
+                Field { label: "Model",
+                    div { class: "space-y-2",
+                        Input {
+                            value: draft.config.model.clone(),
+                            oninput: move |v| edit(|p, v| p.config.model = v, v),
+                        }
+                        ModelPicker {
+                            provider: draft.clone(),
+                            on_select: move |model: String| edit(|p, v| p.config.model = v, model),
+                        }
+                    }
+                }
+
+                div { class: "grid grid-cols-3 gap-3",
+                    Field { label: "Max tokens",
+                        Input {
+                            input_type: InputType::Number,
+                            value: show(draft.config.max_tokens),
+                            placeholder: "Default".to_string(),
+                            onchange: move |v| edit(|p, v| p.config.max_tokens = parse(&v), v),
+                        }
+                    }
+                    Field { label: "Temperature",
+                        Input {
+                            input_type: InputType::Number,
+                            value: show(draft.config.temperature),
+                            placeholder: "Default".to_string(),
+                            onchange: move |v| edit(|p, v| p.config.temperature = parse(&v), v),
+                        }
+                    }
+                    Field { label: "Context size",
+                        Input {
+                            input_type: InputType::Number,
+                            value: show(draft.config.context_size),
+                            placeholder: draft.config.context_window().to_string(),
+                            onchange: move |v| edit(|p, v| p.config.context_size = parse(&v), v),
+                        }
+                    }
+                }
+
+                if let Some(message) = error() {
+                    div { class: "text-sm text-red-500", "{message}" }
+                }
+
+                div { class: "flex justify-end space-x-2",
+                    Button {
+                        variant: ButtonVariant::Ghost,
+                        onclick: move |_| props.is_open.set(false),
+                        "Cancel"
+                    }
+                    Button {
+                        variant: ButtonVariant::Primary,
+                        onclick: move |_| {
+                            let provider = (props.draft)();
+                            match provider.validate() {
+                                Ok(()) => props.on_save.call(provider),
+                                Err(e) => error.set(Some(e.to_string())),
+                            }
+                        },
+                        "Save"
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[component]
+fn Field(label: &'static str, children: Element) -> Element {
+    rsx! {
+        div { class: "space-y-1",
+            div { class: "text-sm font-medium", "{label}" }
+            {children}
+        }
+    }
+}
@@ -0,0 +1,98 @@
+//! Message editor modal with edit history and restoration
+
+use crate::{Button, ButtonSize, ButtonVariant, FadeMode, Modal, ScrollArea, Textarea};
+use dioxus::prelude::*;
+use hearth_core::models::MessageRevision;
+
+#[derive(Props, Clone, PartialEq)]
+pub struct MessageEditorProps {
+    pub is_open: Signal<bool>,
+    /// Content being edited
+    pub draft: Signal<String>,
+    /// Earlier versions of the message, newest first
+    pub revisions: Vec<MessageRevision>,
+    /// Called with the new content
+    pub on_save: EventHandler<String>,
+    /// Called with the id of the revision to bring back
+    pub on_restore: EventHandler<String>,
+}
+
+#[component]
+pub fn MessageEditor(mut props: MessageEditorProps) -> Element {
+    if !(props.is_open)() {
+        return rsx! { div {} };
+    }
+
+    rsx! {
+        Modal {
+            title: "Edit Message".to_string(),
+            is_open: props.is_open,
+
+            div { class: "flex flex-col p-4 space-y-4",
+                Textarea {
+                    value: (props.draft)(),
+                    rows: 6,
+                    oninput: move |value: String| props.draft.set(value),
+                }
+
+                div { class: "flex justify-end space-x-2",
+                    Button {
+                        variant: ButtonVariant::Ghost,
+                        onclick: move |_| props.is_open.set(false),
+                        "Cancel"
+                    }
+                    Button {
+                        variant: ButtonVariant::Primary,
+                        onclick: move |_| props.on_save.call((props.draft)()),
+                        "Save"
+                    }
+                }
+
+                // Edit history
+                div { class: "border-t border-border pt-4",
+                    div { class: "font-medium text-foreground mb-2", "Edit History" }
+
+                    if props.revisions.is_empty() {
+                        div { class: "text-sm text-muted-foreground",
+                            "This message has not been edited yet."
+                        }
+                    } else {
+                        ScrollArea {
+                            height: "max-h-64".to_string(),
+                            fade_mode: FadeMode::Both,
+                            fade_color: Some("from-card".to_string()),
+
+                            div { class: "space-y-2",
+                                for revision in props.revisions.iter() {
+                                    div {
+                                        key: "{revision.id}",
+                                        class: "p-3 rounded-lg border border-border flex items-start space-x-3",
+
+                                        div { class: "flex-1 min-w-0",
+                                            div { class: "text-xs text-muted-foreground mb-1",
+                                                {revision.edited_at.format("%Y-%m-%d %H:%M").to_string()}
+                                            }
+                                            div { class: "text-sm text-foreground line-clamp-3 whitespace-pre-wrap",
+                                                "{revision.content}"
+                                            }
+                                        }
+
+                                        Button {
+                                            variant: ButtonVariant::Ghost,
+                                            size: ButtonSize::Small,
+                                            onclick: {
+                                                let revision_id = revision.id.clone();
+                                                move |_| props.on_restore.call(revision_id.clone())
+                                            },
+                                            i { class: "fas fa-undo text-xs" }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -18,6 +18,35 @@ pub use universal_search::*;
 pub mod story_management_menu;
 pub use story_management_menu::*;
 
+pub mod branch_history;
+pub use branch_history::*;
+
+pub mod story_export;
+pub use story_export::*;
+
+pub mod character_definition;
+pub use character_definition::*;
+
+pub mod message_editor;
+pub use message_editor::*;
+
+pub mod instruct_templates;
+pub use instruct_templates::*;
+
+mod optional_value;
+
+pub mod generation_settings;
+pub use generation_settings::*;
+
+pub mod model_picker;
+pub use model_picker::*;
+
+pub mod llm_providers;
+pub use llm_providers::*;
+
+pub mod credentials;
+pub use credentials::*;
+
 pub mod navigation;
 pub use navigation::*;
 
@@ -0,0 +1,106 @@
+//! Model selection and connection testing for a configured provider
+
+use crate::{use_settings, use_toaster, Button, ButtonSize, ButtonVariant, Platform, Select, SelectOption};
+use dioxus::prelude::*;
+use hearth_core::llm::{connect_for_discovery, test_connection, LlmError, ModelInfo};
+use hearth_core::LlmProviderConfig;
+
+#[derive(Props, Clone, PartialEq)]
+pub struct ModelPickerProps {
+    pub provider: LlmProviderConfig,
+    /// Called with the id of the chosen model
+    pub on_select: EventHandler<String>,
+}
+
+/// Searchable list of the provider's models, loaded on request
+#[component]
+pub fn ModelPicker(props: ModelPickerProps) -> Element {
+    let settings = use_settings();
+    let toaster = use_toaster();
+    let mut models = use_signal(Vec::<ModelInfo>::new);
+    let mut is_loading = use_signal(|| false);
+    let mut status = use_signal(|| None::<String>);
+
+    let current = props.provider.config.model.clone();
+    let mut options: Vec<SelectOption> = models
+        .read()
+        .iter()
+        .map(|m| SelectOption::new(&m.id, m.label()))
+        .collect();
+    // Keep the configured model selectable even if the server does not list it
+    if !current.is_empty() && !options.iter().any(|o| o.value == current) {
+        options.insert(0, SelectOption::new(&current, &current));
+    }
+
+    let refresh = {
+        let provider = props.provider.clone();
+        move |_| {
+            let templates = settings.read().get().instruct_templates.clone();
+            let client = match connect_for_discovery(&provider, &templates) {
+                Ok(client) => client,
+                Err(e) => {
+                    toaster.error(format!("Cannot connect: {e}"));
+                    return;
+                }
+            };
+            let has_model = !provider.config.model.is_empty();
+            is_loading.set(true);
+            status.set(None);
+            Platform::spawn(async move {
+                match test_connection(client.as_ref()).await {
+                    Ok(test) => {
+                        let note = if test.model_available || !has_model {
+                            ""
+                        } else {
+                            ", configured model not found"
+                        };
+                        status.set(Some(format!(
+                            "Connected in {} ms, {} models{note}",
+                            test.latency_ms,
+                            test.models.len()
+                        )));
+                        models.set(test.models);
+                    }
+                    Err(LlmError::Unauthorized(_)) => {
+                        status.set(Some("Authentication failed, check the API key".to_string()));
+                    }
+                    Err(e) => {
+                        log::warn!("Connection test failed: {e}");
+                        status.set(Some(format!("Connection failed: {e}")));
+                    }
+                }
+                is_loading.set(false);
+            });
+        }
+    };
+
+    rsx! {
+        div { class: "space-y-2",
+            div { class: "flex items-center space-x-2",
+                div { class: "flex-1",
+                    Select {
+                        value: current.clone(),
+                        options: options,
+                        placeholder: "Select model...".to_string(),
+                        searchable: true,
+                        onchange: move |model: String| props.on_select.call(model),
+                    }
+                }
+                Button {
+                    variant: ButtonVariant::Outline,
+                    size: ButtonSize::Small,
+                    disabled: is_loading(),
+                    onclick: refresh,
+                    if is_loading() {
+                        i { class: "fas fa-spinner fa-spin text-xs" }
+                    } else {
+                        "Test & Load Models"
+                    }
+                }
+            }
+            if let Some(status) = status() {
+                div { class: "text-xs text-muted-foreground", "{status}" }
+            }
+        }
+    }
+}
@@ -0,0 +1,13 @@
+//! Text conversions for number settings that can be left empty
+
+use std::str::FromStr;
+
+/// Text for an optional setting, empty when it is unset
+pub(crate) fn show<T: ToString>(value: Option<T>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+/// Empty or invalid input clears the option
+pub(crate) fn parse<T: FromStr>(value: &str) -> Option<T> {
+    value.trim().parse().ok()
+}
@@ -0,0 +1,42 @@
+//! Story export modal for choosing the file format
+
+use crate::{Button, ButtonVariant, Modal};
+use dioxus::prelude::*;
+use hearth_core::ExportFormat;
+
+#[derive(Props, Clone, PartialEq)]
+pub struct StoryExportDialogProps {
+    pub is_open: Signal<bool>,
+    /// Called with the format the user picked
+    pub on_export: EventHandler<ExportFormat>,
+}
+
+#[component]
+pub fn StoryExportDialog(props: StoryExportDialogProps) -> Element {
+    if !(props.is_open)() {
+        return rsx! { div {} };
+    }
+
+    rsx! {
+        Modal {
+            title: "Export Story".to_string(),
+            is_open: props.is_open,
+
+            div { class: "p-4 space-y-2",
+                p { class: "text-sm text-muted-foreground mb-2",
+                    "The active branch is saved, without guidance notes."
+                }
+                for format in ExportFormat::ALL {
+                    Button {
+                        key: "{format.extension()}",
+                        variant: ButtonVariant::Outline,
+                        class: "w-full justify-between".to_string(),
+                        onclick: move |_| props.on_export.call(format),
+                        span { "{format.label()}" }
+                        span { class: "text-xs text-muted-foreground", ".{format.extension()}" }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -6,6 +6,7 @@ use dioxus::prelude::*;
 #[component]
 pub fn StoryManagementMenu(
     on_new_story: EventHandler<()>,
+    on_branch_history: EventHandler<()>,
     on_export: EventHandler<()>,
     on_settings: EventHandler<()>,
 ) -> Element {
@@ -40,9 +41,7 @@ pub fn StoryManagementMenu(
                             icon: "fas fa-code-branch",
                             title: "Branch History",
                             description: "View and manage story branches",
-                            on_click: move |_| {
-                                // TODO: Implement branch history
-                            },
+                            on_click: move |_| on_branch_history.call(()),
                         }
                         
                         // Separator
@@ -73,7 +72,7 @@ pub fn StoryManagementMenu(
                         MenuOption {
                             icon: "fas fa-download",
                             title: "Export Story",
-                            description: "Save story as JSON or text file",
+                            description: "Save story as JSON, Markdown, HTML or EPUB",
                             on_click: move |_| on_export.call(()),
                         }
                         
@@ -1,11 +1,63 @@
 //! Story message components for interactive storytelling interface
 
-use crate::{Avatar, AvatarVariant, MarkdownContent, Badge, BadgeVariant, Button, ButtonVariant, ButtonSize, StoryMessage, StoryRole};
+use crate::{Avatar, AvatarVariant, MarkdownContent, Badge, BadgeVariant, Button, ButtonVariant, ButtonSize, GestureDetector, GestureDirection, Platform, StoryMessage, StoryRole};
 use dioxus::prelude::*;
 
 #[component]
-pub fn StoryMessageComponent(message: StoryMessage) -> Element {
-    match message.role {
+pub fn StoryMessageComponent(
+    message: StoryMessage,
+    /// Continue the story from this message on a new branch
+    on_branch: Option<EventHandler<()>>,
+    /// Open the editor with this message's content and edit history
+    on_edit: Option<EventHandler<()>>,
+    /// Move between alternates: `Left` shows the next one, `Right` the previous one
+    on_swipe: Option<EventHandler<GestureDirection>>,
+    /// The content is still being generated and grows as tokens arrive
+    #[props(default)]
+    is_streaming: bool,
+    /// Cancel the generation, keeping the text received so far
+    on_stop: Option<EventHandler<()>>,
+) -> Element {
+    let is_mobile = Platform::current().is_mobile();
+    let alternate_count = message.alternate_count();
+    let selected_alternate = message.selected_alternate;
+    let swipeable = message.is_generated() && on_swipe.is_some();
+
+    // Position indicator, with buttons on desktop where there is no swipe gesture
+    let alternate_nav = match on_swipe {
+        Some(on_swipe) if swipeable && alternate_count > 1 => rsx! {
+            AlternateNav {
+                selected: selected_alternate,
+                count: alternate_count,
+                show_buttons: !is_mobile,
+                on_swipe,
+            }
+        },
+        _ => rsx! {},
+    };
+
+    // Spinner and stop button while the reply is still streaming in
+    let stream_controls = if is_streaming {
+        rsx! {
+            div { class: "flex items-center space-x-2 text-xs text-muted-foreground",
+                div { class: "w-3 h-3 border-2 border-muted-foreground border-t-transparent rounded-full animate-spin" }
+                if let Some(on_stop) = on_stop {
+                    Button {
+                        variant: ButtonVariant::Ghost,
+                        size: ButtonSize::Small,
+                        class: "h-6 px-2".to_string(),
+                        onclick: move |_| on_stop.call(()),
+                        i { class: "fas fa-stop text-xs mr-1" }
+                        "Stop"
+                    }
+                }
+            }
+        }
+    } else {
+        rsx! {}
+    };
+
+    let body = match message.role {
         StoryRole::User { name } => rsx! {
             div { class: "mb-4 flex justify-center",
                 div { class: "bg-user-message text-user-message-foreground p-4 rounded-lg border-r-4 border-user-message-foreground/20 max-w-2xl w-full relative overflow-visible",
@@ -50,7 +102,9 @@ pub fn StoryMessageComponent(message: StoryMessage) -> Element {
                                 size: ButtonSize::Small,
                                 class: "w-6 h-6 p-0".to_string(),
                                 onclick: move |_| {
-                                    // TODO: Implement edit action
+                                    if let Some(on_edit) = on_edit {
+                                        on_edit.call(());
+                                    }
                                 },
                                 i { class: "fas fa-edit text-xs" }
                             }
@@ -77,7 +131,9 @@ pub fn StoryMessageComponent(message: StoryMessage) -> Element {
                                 size: ButtonSize::Small,
                                 class: "w-6 h-6 p-0".to_string(),
                                 onclick: move |_| {
-                                    // TODO: Implement branch action
+                                    if let Some(on_branch) = on_branch {
+                                        on_branch.call(());
+                                    }
                                 },
                                 i { class: "fas fa-code-branch text-xs" }
                             }
@@ -97,6 +153,10 @@ pub fn StoryMessageComponent(message: StoryMessage) -> Element {
                             quote_class: Some("text-orange-500".to_string()),
                         }
                     }
+                    div { class: "flex justify-center",
+                        {alternate_nav}
+                        {stream_controls}
+                    }
                 }
             }
         },
@@ -138,7 +198,9 @@ pub fn StoryMessageComponent(message: StoryMessage) -> Element {
                                 size: ButtonSize::Small,
                                 class: "w-6 h-6 p-0".to_string(),
                                 onclick: move |_| {
-                                    // TODO: Implement branch action
+                                    if let Some(on_branch) = on_branch {
+                                        on_branch.call(());
+                                    }
                                 },
                                 i { class: "fas fa-code-branch text-xs" }
                             }
@@ -165,11 +227,15 @@ pub fn StoryMessageComponent(message: StoryMessage) -> Element {
                                 size: ButtonSize::Small,
                                 class: "w-6 h-6 p-0".to_string(),
                                 onclick: move |_| {
-                                    // TODO: Implement edit action
+                                    if let Some(on_edit) = on_edit {
+                                        on_edit.call(());
+                                    }
                                 },
                                 i { class: "fas fa-edit text-xs" }
                             }
                         }
+                        {alternate_nav}
+                        {stream_controls}
                         // Name badge on right (opposite side of avatar)
                         Badge {
                             variant: BadgeVariant::Secondary,
@@ -180,5 +246,53 @@ pub fn StoryMessageComponent(message: StoryMessage) -> Element {
                 }
             }
         },
+    };
+
+    match on_swipe {
+        // Horizontal swipes on mobile; vertical movement still scrolls the story
+        Some(on_swipe) if swipeable && is_mobile => rsx! {
+            GestureDetector {
+                class: "relative".to_string(),
+                horizontal_only: true,
+                on_gesture: move |direction| on_swipe.call(direction),
+                {body}
+            }
+        },
+        _ => body,
+    }
+}
+
+/// Alternate position indicator such as `2 / 3`, optionally with previous/next buttons
+#[component]
+fn AlternateNav(
+    selected: usize,
+    count: usize,
+    show_buttons: bool,
+    on_swipe: EventHandler<GestureDirection>,
+) -> Element {
+    rsx! {
+        div { class: "flex items-center space-x-1 text-xs text-muted-foreground",
+            if show_buttons {
+                Button {
+                    variant: ButtonVariant::Ghost,
+                    size: ButtonSize::Small,
+                    class: "w-6 h-6 p-0".to_string(),
+                    disabled: selected == 0,
+                    onclick: move |_| on_swipe.call(GestureDirection::Right),
+                    i { class: "fas fa-chevron-left text-xs" }
+                }
+            }
+            span { "{selected + 1} / {count}" }
+            if show_buttons {
+                Button {
+                    variant: ButtonVariant::Ghost,
+                    size: ButtonSize::Small,
+                    class: "w-6 h-6 p-0".to_string(),
+                    disabled: selected + 1 >= count,
+                    onclick: move |_| on_swipe.call(GestureDirection::Left),
+                    i { class: "fas fa-chevron-right text-xs" }
+                }
+            }
+        }
     }
-}
\ No newline at end of file
+}
@@ -6,7 +6,7 @@
 
 use crate::{
     Logo, Progress,
-    use_loading_message, use_loading_progress, use_loading_stage, LoadingStage,
+    use_loading_error, use_loading_message, use_loading_progress, use_loading_stage, LoadingStage,
 };
 use dioxus::prelude::*;
 
@@ -47,6 +47,7 @@ pub struct AppLoadingProps {
 /// - **Initializing**: Shows logo and "Starting Hearth..." message
 /// - **LoadingAssets**: Shows "Loading interface..." with progress
 /// - **LoadingSettings**: Shows "Loading your settings..." 
+/// - **LoadingDatabase**: Shows "Preparing your library..." while migrations run
 /// - **Ready**: Transitions to main app (component hidden)
 ///
 /// # Usage
@@ -78,6 +79,7 @@ pub fn AppLoading(props: AppLoadingProps) -> Element {
     let stage = use_loading_stage();
     let progress = use_loading_progress();
     let message = use_loading_message();
+    let error = use_loading_error();
     
     // Get dark mode context for conditional styling
     let dark_mode = use_context::<crate::DarkModeContext>();
@@ -156,7 +158,18 @@ pub fn AppLoading(props: AppLoadingProps) -> Element {
                             class: "h-2".to_string(),
                         }
                         
-                        if props.show_messages {
+                        if let Some(error) = error {
+                            p { 
+                                class: "text-center text-sm text-red-500",
+                                style: "
+                                    text-align: center; 
+                                    font-size: 0.875rem; 
+                                    color: #ef4444; 
+                                    margin: 0;
+                                ",
+                                "{error}"
+                            }
+                        } else if props.show_messages {
                             p { 
                                 class: "text-center text-sm text-muted-foreground animate-pulse",
                                 style: "
@@ -16,6 +16,8 @@ pub fn GestureDetector(
     on_gesture: EventHandler<GestureDirection>,
     #[props(default = "fixed inset-0 pointer-events-auto z-50".to_string())] class: String,
     #[props(default = false)] debug: bool,
+    /// Only capture left/right swipes so vertical scrolling keeps working
+    #[props(default = false)] horizontal_only: bool,
     children: Element,
 ) -> Element {
     let mut touch_start_x = use_signal(|| None::<f64>);
@@ -54,6 +56,11 @@ pub fn GestureDetector(
                 let dy = coords.y - start_y;
                 let distance = (dx * dx + dy * dy).sqrt();
                 
+                // Leave mostly vertical movement to the scroll container
+                if horizontal_only && !is_gesture() && dy.abs() >= dx.abs() {
+                    return;
+                }
+
                 // Early detection: determine if this is likely a gesture (any direction)
                 if !is_gesture() && distance >= early_detection_distance {
                     is_gesture.set(true);
@@ -103,6 +103,7 @@ pub fn MarkdownContent(props: MarkdownContentProps) -> Element {
         td_class: props.td_class.clone(),
         hr_class: props.hr_class.clone(),
         quote_class: props.quote_class.clone(),
+        sanitize: false,
     };
     
     // Convert markdown to HTML using core functionality
@@ -25,6 +25,9 @@ pub use sample::*;
 pub mod settings;
 pub use settings::*;
 
+pub mod backend;
+pub use backend::*;
+
 pub mod components;
 pub use components::*;
 
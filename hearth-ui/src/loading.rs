@@ -30,6 +30,9 @@ pub enum LoadingStage {
     /// Loading user settings and preferences
     LoadingSettings,
     
+    /// Opening the local database and applying pending migrations
+    LoadingDatabase,
+    
     /// App is fully loaded and ready to use
     Ready,
 }
@@ -41,6 +44,7 @@ impl LoadingStage {
             LoadingStage::Initializing => "Starting Hearth...",
             LoadingStage::LoadingAssets => "Loading interface...",
             LoadingStage::LoadingSettings => "Loading your settings...",
+            LoadingStage::LoadingDatabase => "Preparing your library...",
             LoadingStage::Ready => "Ready!",
         }
     }
@@ -50,7 +54,8 @@ impl LoadingStage {
         match self {
             LoadingStage::Initializing => 0.1,
             LoadingStage::LoadingAssets => 0.4,
-            LoadingStage::LoadingSettings => 0.8,
+            LoadingStage::LoadingSettings => 0.6,
+            LoadingStage::LoadingDatabase => 0.8,
             LoadingStage::Ready => 1.0,
         }
     }
@@ -66,6 +71,7 @@ impl LoadingStage {
             LoadingStage::Initializing => Duration::from_millis(100),
             LoadingStage::LoadingAssets => Duration::from_millis(200),
             LoadingStage::LoadingSettings => Duration::from_millis(150),
+            LoadingStage::LoadingDatabase => Duration::from_millis(100),
             LoadingStage::Ready => Duration::ZERO,
         }
     }
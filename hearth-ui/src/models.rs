@@ -132,21 +132,6 @@ impl<T> SidebarRoute<T> {
     }
 }
 
-// Story message models for interactive storytelling interface
-#[derive(Clone, PartialEq)]
-pub struct StoryMessage {
-    pub id: String,
-    pub role: StoryRole,
-    pub content: String,
-}
-
-#[derive(Clone, PartialEq)]
-pub enum StoryRole {
-    User { name: String },
-    Narrator,
-    Character { name: String },
-}
-
 // Character selection menu models
 #[derive(Clone, PartialEq)]
 pub struct CharacterOption {
@@ -1,28 +1,49 @@
 //! Unified responsive characters view
 
 use crate::{
-    sample_character_tags_sorted, sample_characters, PageHeader, Platform, Route, SearchContext, 
+    sorted_tag_counts, use_backend, PageHeader, Platform, Route, SearchContext, 
     UniversalSearch, UniversalSearchState, UniversalSearchQuery, ToastManager, ToastType, ToastConfig,
     Card, CardHeader, CardTitle, CardDescription, CardContent, Avatar, Badge, BadgeVariant,
-    Button, ButtonVariant, ScrollArea, ScrollOrientation, FadeMode,
+    Button, ButtonSize, ButtonVariant, Modal, ScrollArea, ScrollOrientation, FadeMode, CharacterDefinitionEditor,
 };
-use hearth_core::models::CharacterItem;
+use hearth_core::models::{Character, CharacterItem};
+use hearth_core::{card_file, save_with_dialog, CardFormat};
+#[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+use hearth_core::{import_card, pick_card_file};
+use hearth_core::SharedBackend;
 use std::time::Duration;
 use dioxus::prelude::*;
 
 #[component]
 pub fn CharactersView(navigate_to: EventHandler<Route>) -> Element {
-    let available_tags: Vec<String> = sample_character_tags_sorted()
-        .into_iter()
+    let backend = use_backend();
+    let mut characters = use_signal(Vec::<CharacterItem>::new);
+    let mut show_export = use_signal(|| false);
+    let mut editor_open = use_signal(|| false);
+    let mut editing = use_signal(|| Character::new("", "", ""));
+
+    // Load characters from the active backend (reloads when the backend changes)
+    use_effect(move || {
+        let Some(backend) = backend() else { return };
+        Platform::spawn(async move {
+            match backend.list_characters().await {
+                Ok(items) => characters.set(items),
+                Err(e) => log::error!("Failed to load characters: {e}"),
+            }
+        });
+    });
+
+    let tag_counts = sorted_tag_counts(&characters());
+    let available_tags: Vec<String> = tag_counts
+        .iter()
         .take(12)
         .map(|(tag, count)| format!("{tag} ({count})"))
         .collect();
 
-    let all_tags: Vec<String> = sample_character_tags_sorted()
-        .into_iter()
+    let all_tags: Vec<String> = tag_counts
+        .iter()
         .map(|(tag, count)| format!("{tag} ({count})"))
         .collect();
-    let mut characters = use_signal(sample_characters);
     let platform = Platform::current();
     
     // Universal search state
@@ -35,6 +56,79 @@ pub fn CharactersView(navigate_to: EventHandler<Route>) -> Element {
         div { class: "flex-1 flex flex-col min-h-0",
             // PageHeader inside the flex container
             PageHeader { title: "Characters".to_string(), back_button: None }
+
+            div { class: "flex justify-end space-x-2 px-4",
+                if platform == Platform::Desktop {
+                    Button {
+                        variant: ButtonVariant::Outline,
+                        onclick: move |_| {
+                            let Some(backend) = backend() else { return };
+                            Platform::spawn(async move {
+                                match import_character_card(backend).await {
+                                    Ok(Some(character)) => {
+                                        toast_manager.success(format!("Imported {}", character.name));
+                                        characters.write().push(character.to_item());
+                                    }
+                                    Ok(None) => {}
+                                    Err(e) => {
+                                        toast_manager.error(format!("Failed to import card: {e}"));
+                                    }
+                                }
+                            });
+                        },
+                        "Import Card"
+                    }
+                }
+                Button {
+                    variant: ButtonVariant::Outline,
+                    disabled: characters().is_empty(),
+                    onclick: move |_| show_export.set(true),
+                    "Export Card"
+                }
+            }
+
+            ExportCardDialog {
+                is_open: show_export,
+                characters: characters(),
+                on_export: move |(id, format): (String, CardFormat)| {
+                    show_export.set(false);
+                    let Some(backend) = backend() else { return };
+                    Platform::spawn(async move {
+                        match export_character_card(backend, &id, format).await {
+                            Ok(Some(location)) => {
+                                toast_manager.success(format!("Card saved to: {location}"));
+                            }
+                            Ok(None) => {}
+                            Err(e) => {
+                                toast_manager.error(format!("Failed to export card: {e}"));
+                            }
+                        }
+                    });
+                },
+            }
+
+            CharacterDefinitionEditor {
+                is_open: editor_open,
+                character: editing,
+                on_save: move |character: Character| {
+                    let Some(backend) = backend() else { return };
+                    Platform::spawn(async move {
+                        match backend.save_character_definition(&character).await {
+                            Ok(()) => {
+                                editor_open.set(false);
+                                let item = character.to_item();
+                                if let Some(existing) = characters.write().iter_mut().find(|c| c.id == item.id) {
+                                    *existing = item;
+                                }
+                                toast_manager.success(format!("Saved {}", character.name));
+                            }
+                            Err(e) => {
+                                toast_manager.error(format!("Failed to save character: {e}"));
+                            }
+                        }
+                    });
+                },
+            }
             
             // Universal Search/Filter Section
             UniversalSearch {
@@ -82,11 +176,31 @@ pub fn CharactersView(navigate_to: EventHandler<Route>) -> Element {
                             for character in &characters() {
                                 CharacterCard {
                                     character: character.clone(),
-                                    on_select: move |id| println!("Selected character: {id}"),
+                                    on_select: move |id: String| {
+                                        let Some(backend) = backend() else { return };
+                                        Platform::spawn(async move {
+                                            match backend.get_character_definition(&id).await {
+                                                Ok(Some(character)) => {
+                                                    editing.set(character);
+                                                    editor_open.set(true);
+                                                }
+                                                Ok(None) => log::warn!("Character {id} not found"),
+                                                Err(e) => log::error!("Failed to load character: {e}"),
+                                            }
+                                        });
+                                    },
                                     on_favorite: move |id| {
                                         let mut chars = characters();
                                         if let Some(char) = chars.iter_mut().find(|c| c.id == id) {
                                             char.is_favorite = !char.is_favorite;
+                                            if let Some(backend) = backend() {
+                                                let updated = char.clone();
+                                                Platform::spawn(async move {
+                                                    if let Err(e) = backend.update_character(&updated).await {
+                                                        log::error!("Failed to save character: {e}");
+                                                    }
+                                                });
+                                            }
                                         }
                                         characters.set(chars);
                                     },
@@ -100,6 +214,98 @@ pub fn CharactersView(navigate_to: EventHandler<Route>) -> Element {
     }
 }
 
+/// Import a card picked by the user, saving its scenario before the character
+#[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+async fn import_character_card(backend: SharedBackend) -> Result<Option<Character>, String> {
+    let Some(bytes) = pick_card_file().await else {
+        return Ok(None);
+    };
+    let card = import_card(&bytes).map_err(|e| e.to_string())?;
+    if let Some(scenario) = &card.scenario {
+        backend.create_scenario(scenario).await.map_err(|e| e.to_string())?;
+    }
+    backend.save_character_definition(&card.character).await.map_err(|e| e.to_string())?;
+    Ok(Some(card.character))
+}
+
+/// Save a character's card where the user chooses, `None` if they cancelled
+async fn export_character_card(
+    backend: SharedBackend,
+    id: &str,
+    format: CardFormat,
+) -> Result<Option<String>, String> {
+    let character = backend
+        .get_character_definition(id)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "character not found".to_string())?;
+    let scenario = match &character.default_scenario_id {
+        Some(scenario_id) => backend.get_scenario(scenario_id).await.map_err(|e| e.to_string())?,
+        None => None,
+    };
+    let file = card_file(&character, scenario.as_ref(), format).map_err(|e| e.to_string())?;
+    save_with_dialog(file).await.map_err(|e| e.to_string())
+}
+
+/// Card files can only be picked on desktop
+#[cfg(any(target_arch = "wasm32", target_os = "android"))]
+async fn import_character_card(_backend: SharedBackend) -> Result<Option<Character>, String> {
+    Ok(None)
+}
+
+/// Pick a character and the file type to export its card as
+#[component]
+fn ExportCardDialog(
+    is_open: Signal<bool>,
+    characters: Vec<CharacterItem>,
+    on_export: EventHandler<(String, CardFormat)>,
+) -> Element {
+    if !is_open() {
+        return rsx! { div {} };
+    }
+
+    rsx! {
+        Modal {
+            title: "Export Card".to_string(),
+            is_open,
+
+            ScrollArea {
+                height: "h-[50vh]".to_string(),
+                fade_mode: FadeMode::Both,
+                fade_color: Some("from-card".to_string()),
+                class: "p-4",
+
+                div { class: "space-y-2",
+                    for character in characters {
+                        div {
+                            key: "{character.id}",
+                            class: "p-3 rounded-lg border border-border flex items-center space-x-3",
+
+                            Avatar {
+                                name: character.name.clone(),
+                                avatar_url: character.avatar_url.clone(),
+                                size: "w-8 h-8".to_string(),
+                            }
+                            span { class: "flex-1 min-w-0 truncate font-medium", "{character.name}" }
+                            for format in CardFormat::ALL {
+                                Button {
+                                    variant: ButtonVariant::Ghost,
+                                    size: ButtonSize::Small,
+                                    onclick: {
+                                        let id = character.id.clone();
+                                        move |_| on_export.call((id.clone(), format))
+                                    },
+                                    "{format.label()}"
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
 #[component]
 fn CharacterCard(
     character: CharacterItem,
@@ -1,7 +1,7 @@
 //! Unified responsive scenarios view
 
 use crate::{
-    sample_scenario_tags_sorted, sample_scenarios, PageHeader, Platform, Route, SearchContext, 
+    sorted_tag_counts, use_backend, PageHeader, Platform, Route, SearchContext, 
     UniversalSearch, UniversalSearchState, UniversalSearchQuery, ToastManager, ToastType, ToastConfig,
     Card, CardHeader, CardTitle, CardDescription, CardContent, Avatar, Badge, BadgeVariant,
     Button, ButtonVariant, ScrollArea, ScrollOrientation, FadeMode,
@@ -12,17 +12,31 @@ use dioxus::prelude::*;
 
 #[component]
 pub fn ScenariosView(navigate_to: EventHandler<Route>) -> Element {
-    let available_tags: Vec<String> = sample_scenario_tags_sorted()
-        .into_iter()
+    let backend = use_backend();
+    let mut scenarios = use_signal(Vec::<ScenarioItem>::new);
+
+    // Load scenarios from the active backend (reloads when the backend changes)
+    use_effect(move || {
+        let Some(backend) = backend() else { return };
+        Platform::spawn(async move {
+            match backend.list_scenarios().await {
+                Ok(items) => scenarios.set(items),
+                Err(e) => log::error!("Failed to load scenarios: {e}"),
+            }
+        });
+    });
+
+    let tag_counts = sorted_tag_counts(&scenarios());
+    let available_tags: Vec<String> = tag_counts
+        .iter()
         .take(12)
         .map(|(tag, count)| format!("{tag} ({count})"))
         .collect();
 
-    let all_tags: Vec<String> = sample_scenario_tags_sorted()
-        .into_iter()
+    let all_tags: Vec<String> = tag_counts
+        .iter()
         .map(|(tag, count)| format!("{tag} ({count})"))
         .collect();
-    let mut scenarios = use_signal(sample_scenarios);
     let platform = Platform::current();
     
     // Universal search state
@@ -87,6 +101,14 @@ pub fn ScenariosView(navigate_to: EventHandler<Route>) -> Element {
                                         let mut scenarios_vec = scenarios();
                                         if let Some(scenario) = scenarios_vec.iter_mut().find(|s| s.id == id) {
                                             scenario.is_favorite = !scenario.is_favorite;
+                                            if let Some(backend) = backend() {
+                                                let updated = scenario.clone();
+                                                Platform::spawn(async move {
+                                                    if let Err(e) = backend.update_scenario(&updated).await {
+                                                        log::error!("Failed to save scenario: {e}");
+                                                    }
+                                                });
+                                            }
                                         }
                                         scenarios.set(scenarios_vec);
                                     },
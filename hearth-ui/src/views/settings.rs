@@ -1,8 +1,10 @@
 //! Unified responsive settings view
 
 use crate::{
-    use_settings, use_theme, use_backend_selection, use_remote_backends, 
-    DarkModeContext, DarkModeToggle, LoggingSection, PageHeader, Platform, Route,
+    use_settings, use_theme, use_backend_selection, use_remote_backends, use_backend, use_toaster,
+    reconnect_backend,
+    CredentialsSection, DarkModeContext, DarkModeToggle, InstructTemplatesSection, LlmProviderList,
+    LoggingSection, ModelPicker, PageHeader, Platform, Route, SamplerPresetsSection,
     SettingsItem, SettingsSection, Select, SelectOption,
 };
 use dioxus::prelude::*;
@@ -14,6 +16,9 @@ pub fn SettingsView(navigate_to: EventHandler<Route>) -> Element {
     let (theme, set_theme) = use_theme();
     let (selected_backend, set_selected_backend) = use_backend_selection();
     let (remote_backends, add_remote_backend, remove_remote_backend) = use_remote_backends();
+    let settings = use_settings();
+    let backend = use_backend();
+    let toaster = use_toaster();
     let platform = Platform::current();
 
     rsx! {
@@ -26,7 +31,13 @@ pub fn SettingsView(navigate_to: EventHandler<Route>) -> Element {
                     selected_backend: selected_backend.clone(),
                     remote_backends: remote_backends.clone(),
                     read_only: !platform.can_edit_backend_settings(),
-                    on_backend_select: move |backend_id| set_selected_backend(backend_id),
+                    on_backend_select: move |backend_id| {
+                        set_selected_backend(backend_id);
+                        // Switch views over to the newly selected backend
+                        if let Err(e) = reconnect_backend(backend, settings.peek().get()) {
+                            toaster.error(format!("Failed to connect to backend: {e}"));
+                        }
+                    },
                     on_add_remote: move |backend| add_remote_backend(backend),
                     on_remove_remote: move |id| remove_remote_backend(id),
                 }
@@ -56,6 +67,22 @@ pub fn SettingsView(navigate_to: EventHandler<Route>) -> Element {
                     }
                 }
 
+                // Model of the provider used for generation, and the providers to pick from
+                if platform.can_edit_backend_settings() {
+                    LanguageModelSection {}
+                }
+
+                // Encryption of API keys and auth tokens
+                CredentialsSection {}
+
+                // Global sampler preset
+                SamplerPresetsSection {}
+
+                // Prompt formats for local text completion
+                if platform.can_edit_backend_settings() {
+                    InstructTemplatesSection {}
+                }
+
                 // Logging section
                 LoggingSection {}
             }
@@ -63,6 +90,39 @@ pub fn SettingsView(navigate_to: EventHandler<Route>) -> Element {
     }
 }
 
+#[component]
+fn LanguageModelSection() -> Element {
+    let mut settings = use_settings();
+    let provider = settings
+        .read()
+        .get()
+        .local_backend
+        .as_ref()
+        .and_then(|local| local.selected_provider().cloned());
+
+    rsx! {
+        SettingsSection { title: "Language Model",
+            if let Some(provider) = provider {
+                div { class: "px-4 py-2 space-y-2",
+                    div { class: "text-sm font-medium", "{provider.name}" }
+                    ModelPicker {
+                        provider: provider.clone(),
+                        on_select: {
+                            let provider_id = provider.id.clone();
+                            move |model: String| settings.write().update_providers(|local| {
+                                if let Some(provider) = local.llm_providers.iter_mut().find(|p| p.id == provider_id) {
+                                    provider.config.model = model;
+                                }
+                            })
+                        },
+                    }
+                }
+            }
+            LlmProviderList {}
+        }
+    }
+}
+
 #[component]
 fn BackendSection(
     selected_backend: Option<BackendId>,
@@ -2,15 +2,42 @@
 
 use crate::{
     PageHeader, Platform, Route, SearchContext, UniversalSearch, UniversalSearchState, 
-    UniversalSearchQuery, ToastManager, ScrollArea, FadeMode,
-    StoryCardComponent, StoryTooltipState,
+    UniversalSearchQuery, ToastManager, ScrollArea, FadeMode, Button, ButtonVariant,
+    StoryCardComponent, StoryTooltipState, sorted_tag_counts, use_backend,
 };
-use hearth_core::sample::{sample_character_tags_sorted, sample_scenario_tags_sorted, sample_stories};
+use hearth_core::models::{CharacterItem, ScenarioItem, StoryItem};
+#[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+use hearth_core::{import_chat, pick_chat_file};
+use hearth_core::SharedBackend;
 use dioxus::prelude::*;
 
 #[component]
 pub fn StoriesView(navigate_to: EventHandler<Route>) -> Element {
-    let character_tags = sample_character_tags_sorted();
+    let backend = use_backend();
+    let mut stories = use_signal(Vec::<StoryItem>::new);
+    let mut characters = use_signal(Vec::<CharacterItem>::new);
+    let mut scenarios = use_signal(Vec::<ScenarioItem>::new);
+
+    // Load stories and the tag sources from the active backend
+    use_effect(move || {
+        let Some(backend) = backend() else { return };
+        Platform::spawn(async move {
+            match backend.list_stories().await {
+                Ok(items) => stories.set(items),
+                Err(e) => log::error!("Failed to load stories: {e}"),
+            }
+            match backend.list_characters().await {
+                Ok(items) => characters.set(items),
+                Err(e) => log::error!("Failed to load characters: {e}"),
+            }
+            match backend.list_scenarios().await {
+                Ok(items) => scenarios.set(items),
+                Err(e) => log::error!("Failed to load scenarios: {e}"),
+            }
+        });
+    });
+
+    let character_tags = sorted_tag_counts(&characters());
     let available_character_tags: Vec<String> = character_tags
         .iter()
         .take(12)
@@ -22,8 +49,8 @@ pub fn StoriesView(navigate_to: EventHandler<Route>) -> Element {
         .map(|(name, count)| format!("{} ({})", name, count))
         .collect();
 
-    // Extract scenario names from sample data for search tags
-    let scenario_tags = sample_scenario_tags_sorted();
+    // Scenario tags for search filters
+    let scenario_tags = sorted_tag_counts(&scenarios());
     let available_scenario_tags: Vec<String> = scenario_tags
         .iter()
         .take(12)
@@ -35,7 +62,6 @@ pub fn StoriesView(navigate_to: EventHandler<Route>) -> Element {
         .map(|(name, count)| format!("{} ({})", name, count))
         .collect();
 
-    let stories = use_signal(sample_stories);
     let platform = Platform::current();
     
     // Universal search state
@@ -44,8 +70,8 @@ pub fn StoriesView(navigate_to: EventHandler<Route>) -> Element {
     // Global tooltip state for all story cards
     let tooltip_state: StoryTooltipState = use_signal(|| None::<(String, usize)>);
     
-    // Toast manager for showing search query changes
-    let _toast_manager = use_context::<ToastManager>();
+    // Toast manager for import results
+    let toast_manager = use_context::<ToastManager>();
     
     // Helper function to convert formatted tag display names back to tag IDs
     let _display_to_id = move |formatted_name: &str| -> String {
@@ -87,6 +113,30 @@ pub fn StoriesView(navigate_to: EventHandler<Route>) -> Element {
         div { class: "flex-1 flex flex-col min-h-0",
             // PageHeader inside the flex container
             PageHeader { title: "Stories".to_string(), back_button: None }
+
+            if platform == Platform::Desktop {
+                div { class: "flex justify-end px-4",
+                    Button {
+                        variant: ButtonVariant::Outline,
+                        onclick: move |_| {
+                            let Some(backend) = backend() else { return };
+                            Platform::spawn(async move {
+                                match import_chat_file(backend).await {
+                                    Ok(Some(story)) => {
+                                        toast_manager.success(format!("Imported {} messages", story.message_count));
+                                        stories.write().insert(0, story);
+                                    }
+                                    Ok(None) => {}
+                                    Err(e) => {
+                                        toast_manager.error(format!("Failed to import chat: {e}"));
+                                    }
+                                }
+                            });
+                        },
+                        "Import Chat"
+                    }
+                }
+            }
             
             // Universal Search/Filter Section
             UniversalSearch {
@@ -148,3 +198,42 @@ pub fn StoriesView(navigate_to: EventHandler<Route>) -> Element {
     }
 }
 
+/// Import a SillyTavern or TavernAI chat picked by the user as a new story
+#[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+async fn import_chat_file(backend: SharedBackend) -> Result<Option<StoryItem>, String> {
+    let Some(text) = pick_chat_file().await else {
+        return Ok(None);
+    };
+    let mut chat = import_chat(&text).map_err(|e| e.to_string())?;
+    let characters = backend.list_characters().await.map_err(|e| e.to_string())?;
+    chat.link_characters(&characters);
+
+    // Speakers missing from the library are added to it, and removed again if the story can't be saved
+    let mut saved = Vec::new();
+    let mut result = Ok(());
+    for character in chat.unlinked_characters() {
+        result = backend.save_character_definition(&character).await;
+        if result.is_err() {
+            break;
+        }
+        saved.push(character);
+    }
+    if result.is_ok() {
+        result = backend.import_story(&chat.story, &chat.messages).await;
+    }
+    if let Err(e) = result {
+        for character in &saved {
+            if let Err(e) = backend.delete_character(&character.id).await {
+                log::warn!("Failed to remove imported character {}: {e}", character.name);
+            }
+        }
+        return Err(e.to_string());
+    }
+    Ok(Some(chat.story))
+}
+
+/// Chat files can only be picked on desktop
+#[cfg(any(target_arch = "wasm32", target_os = "android"))]
+async fn import_chat_file(_backend: SharedBackend) -> Result<Option<StoryItem>, String> {
+    Ok(None)
+}
@@ -1,9 +1,17 @@
 //! Story view - Interactive storytelling interface
 
-use crate::{PageHeader, Platform, Route, components::*, ScrollArea, ScrollControl, ScrollAction, FadeMode, GestureDetector, GestureDirection, ToastManager, MobileNavbarContext, StoryMessage, StoryRole, StoryMessageComponent, ExpandableInputArea, CharacterOption, StoryManagementMenu};
-use hearth_core::sample::sample_stories;
+use crate::{PageHeader, Platform, Route, components::*, ScrollArea, ScrollControl, ScrollAction, FadeMode, GestureDetector, GestureDirection, ToastManager, MobileNavbarContext, StoryMessage, StoryRole, StoryMessageComponent, ExpandableInputArea, CharacterOption, StoryManagementMenu, BranchHistory, StoryExportDialog, MessageEditor, GenerationSettingsPanel, use_backend, use_settings, use_toaster};
+use hearth_core::guidance::{guidance_for_next_generation, MessageGuidance, LOCAL_USER_ID};
+use hearth_core::llm::{connect_with_fallbacks, CompletionRequest, LlmError, LlmProvider, Sleep, StopSignal};
+use hearth_core::prompt::PromptBuilder;
+use hearth_core::tokens::counter_for_model;
+use hearth_core::message_tree::StoryBranch;
+use hearth_core::models::{Character, MessageRevision, ScenarioItem, StoryItem};
+use hearth_core::{save_with_dialog, ExportFormat, LocalBackendConfig, StoryDocument, SamplerSettings, SharedBackend, TemplateRegistry};
+use hearth_core::persona::{Persona, PersonaContext};
 use dioxus::prelude::*;
 use std::collections::HashMap;
+use std::rc::Rc;
 
 #[component]
 pub fn StoryView(story_id: String, navigate_to: EventHandler<Route>) -> Element {
@@ -16,17 +24,86 @@ pub fn StoryView(story_id: String, navigate_to: EventHandler<Route>) -> Element
     let mut character_goals = use_signal(HashMap::<String, String>::new);
     let scroll_controller = use_signal(|| None::<ScrollAction>);
     let platform = Platform::current();
+    let backend = use_backend();
+    let settings = use_settings();
+    let mut story_data = use_signal(|| None::<StoryItem>);
+    let mut active_persona = use_signal(|| None::<Persona>);
+    let mut story_characters = use_signal(Vec::<Character>::new);
+    let mut story_scenario = use_signal(|| None::<ScenarioItem>);
+    let mut branches = use_signal(Vec::<StoryBranch>::new);
+    let mut show_branch_history = use_signal(|| false);
+    let mut show_export = use_signal(|| false);
+    let mut editing_message_id = use_signal(|| None::<String>);
+    let mut edit_draft = use_signal(String::new);
+    let mut revisions = use_signal(Vec::<MessageRevision>::new);
+    let mut show_editor = use_signal(|| false);
+    let mut streaming_message_id = use_signal(|| None::<String>);
+    let mut stop_signal = use_signal(|| None::<StopSignal>);
+    let mut show_generation_settings = use_signal(|| false);
+    let mut sampler_draft = use_signal(SamplerSettings::default);
+    let toaster = use_toaster();
     
-    // Load story data to get user character info
-    let story_data = sample_stories().into_iter().find(|s| s.id == story_id);
-    let user_name = story_data
+    // Load the story and its messages from the active backend
+    use_effect({
+        let story_id = story_id.clone();
+        move || {
+            let Some(backend) = backend() else { return };
+            let story_id = story_id.clone();
+            Platform::spawn(async move {
+                match backend.get_story(&story_id).await {
+                    Ok(story) => story_data.set(story),
+                    Err(e) => log::error!("Failed to load story {story_id}: {e}"),
+                }
+
+                // Resolve the persona locked to this story, its characters or scenario
+                let story = story_data.peek().clone();
+                if let Some(story) = story {
+                    let scenarios = backend.list_scenarios().await.unwrap_or_default();
+                    let scenario = story.scenario_name.as_ref().and_then(|name| {
+                        scenarios.into_iter().find(|s| &s.name == name)
+                    });
+                    let scenario_id = scenario.as_ref().map(|s| s.id.clone());
+                    story_scenario.set(scenario);
+                    match backend.list_personas().await {
+                        Ok(personas) => {
+                            let persona = PersonaContext::for_story(&story)
+                                .with_scenario(scenario_id.as_deref())
+                                .resolve(&personas)
+                                .cloned();
+                            active_persona.set(persona);
+                        }
+                        Err(e) => log::error!("Failed to load personas: {e}"),
+                    }
+
+                    // Full definitions of the story's characters for the prompt
+                    let mut characters = Vec::new();
+                    for participant in &story.characters {
+                        match backend.get_character_definition(&participant.id).await {
+                            Ok(Some(character)) => characters.push(character),
+                            Ok(None) => log::warn!("Character {} of story {story_id} not found", participant.id),
+                            Err(e) => log::error!("Failed to load character {}: {e}", participant.id),
+                        }
+                    }
+                    story_characters.set(characters);
+                }
+                reload_thread(backend, &story_id, story_messages, branches).await;
+                // Auto-scroll to bottom when messages are loaded
+                ScrollControl::scroll_to_bottom(scroll_controller);
+            });
+        }
+    });
+
+    // User character info from the story data, falling back to the resolved persona
+    let story = story_data();
+    let user_name = story
         .as_ref()
         .and_then(|s| s.user_character.as_ref())
         .map(|uc| uc.name.clone())
+        .or_else(|| active_persona.read().as_ref().map(|p| p.name.clone()))
         .unwrap_or_else(|| "You".to_string());
     
     // Get the story title from the story data
-    let story_title = story_data
+    let story_title = story
         .as_ref()
         .map(|s| s.title.clone())
         .unwrap_or_else(|| story_id.clone());
@@ -114,53 +191,6 @@ pub fn StoryView(story_id: String, navigate_to: EventHandler<Route>) -> Element
         }
     };
     
-    // Add some sample messages on first render
-    use_effect({
-        let user_name = user_name.clone();
-        move || {
-            let sample_messages = vec![
-                StoryMessage {
-                    id: "1".to_string(),
-                    role: StoryRole::Narrator,
-                    content: "You find yourself standing at the edge of an ancient forest. The towering trees whisper secrets in the wind, and a narrow path winds deeper into the shadows.".to_string(),
-                },
-                StoryMessage {
-                    id: "2".to_string(),
-                    role: StoryRole::User { name: user_name.clone() },
-                    content: "*I step carefully onto the forest path, scanning the ground for tracks while keeping my hand near my weapon* This place feels alive... I need to stay alert.".to_string(),
-                },
-                StoryMessage {
-                    id: "3".to_string(),
-                    role: StoryRole::Character { name: "Forest Guide".to_string() },
-                    content: "*An elderly woman emerges from the bushes, her walking stick tapping against the ground as she approaches* Wait, traveler! That path leads to the Heart of the Wilds. Are you certain you're prepared for such a journey?".to_string(),
-                },
-                StoryMessage {
-                    id: "4".to_string(),
-                    role: StoryRole::User { name: user_name.clone() },
-                    content: "*I think to myself \"Should I trust this stranger?\" before responding carefully* What dangers should I be aware of? Do you have any advice for a traveler like myself?".to_string(),
-                },
-                StoryMessage {
-                    id: "5".to_string(),
-                    role: StoryRole::Character { name: "Forest Guide".to_string() },
-                    content: "*She leans heavily on her gnarled staff and points toward the dark path ahead* Many have ventured into those depths, young one. The forest itself is alive, and it does not welcome intruders. Trust the silver moonlight, and beware the whispering stones.".to_string(),
-                },
-                StoryMessage {
-                    id: "6".to_string(),
-                    role: StoryRole::Narrator,
-                    content: "As the old woman's words fade into the forest air, a sudden chill runs down your spine. The wind picks up, rustling the leaves overhead, and somewhere in the distance you hear the haunting call of an unknown creature.".to_string(),
-                },
-                StoryMessage {
-                    id: "7".to_string(),
-                    role: StoryRole::User { name: user_name.clone() },
-                    content: "*I remember what my mentor always said \"Knowledge is the best weapon\" and decide to heed her advice* Thank you for the warning. I'll be careful and watch for the silver moonlight.".to_string(),
-                },
-            ];
-            story_messages.set(sample_messages);
-            // Auto-scroll to bottom when messages are loaded
-            ScrollControl::scroll_to_bottom(scroll_controller);
-        }
-    });
-    
     // Auto-scroll to bottom when new messages are added
     use_effect({
         let story_messages = story_messages();
@@ -193,11 +223,20 @@ pub fn StoryView(story_id: String, navigate_to: EventHandler<Route>) -> Element
                         on_new_story: move |_| {
                             // TODO: Implement new story
                         },
+                        on_branch_history: move |_| {
+                            show_story_menu.set(false);
+                            show_branch_history.set(true);
+                        },
                         on_export: move |_| {
-                            // TODO: Implement export
+                            show_story_menu.set(false);
+                            show_export.set(true);
                         },
                         on_settings: move |_| {
-                            // TODO: Implement settings
+                            show_story_menu.set(false);
+                            let presets = settings.read().get().sampler_presets.clone();
+                            let story_sampler = story_data.peek().as_ref().and_then(|s| s.sampler.clone());
+                            sampler_draft.set(presets.for_story(story_sampler.as_ref()));
+                            show_generation_settings.set(true);
                         },
                     }
                 }),
@@ -224,9 +263,71 @@ pub fn StoryView(story_id: String, navigate_to: EventHandler<Route>) -> Element
                             }
                         ),
                         for message in story_messages().iter() {
-                            StoryMessageComponent { message: message.clone() }
+                            StoryMessageComponent {
+                                message: message.clone(),
+                                on_branch: {
+                                    let story_id = story_id.clone();
+                                    let message_id = message.id.clone();
+                                    move |_| {
+                                        let Some(backend) = backend() else { return };
+                                        let story_id = story_id.clone();
+                                        let message_id = message_id.clone();
+                                        Platform::spawn(async move {
+                                            match backend.fork_at(&story_id, &message_id).await {
+                                                Ok(()) => {
+                                                    toaster.info("Your next message will start a new branch");
+                                                    reload_thread(backend, &story_id, story_messages, branches).await;
+                                                }
+                                                Err(e) => {
+                                                    toaster.error(format!("Failed to create branch: {e}"));
+                                                }
+                                            }
+                                        });
+                                    }
+                                },
+                                on_edit: {
+                                    let message_id = message.id.clone();
+                                    let content = message.content.clone();
+                                    move |_| {
+                                        editing_message_id.set(Some(message_id.clone()));
+                                        edit_draft.set(content.clone());
+                                        revisions.set(Vec::new());
+                                        show_editor.set(true);
+
+                                        let Some(backend) = backend() else { return };
+                                        let message_id = message_id.clone();
+                                        Platform::spawn(async move {
+                                            match backend.list_revisions(&message_id).await {
+                                                Ok(list) => revisions.set(list),
+                                                Err(e) => log::error!("Failed to load revisions: {e}"),
+                                            }
+                                        });
+                                    }
+                                },
+                                on_swipe: {
+                                    let message_id = message.id.clone();
+                                    move |direction: GestureDirection| {
+                                        let Some(message) = swipe_alternate(story_messages, &message_id, direction) else {
+                                            return;
+                                        };
+                                        if let Some(backend) = backend() {
+                                            Platform::spawn(async move {
+                                                if let Err(e) = backend.update_message(&message).await {
+                                                    log::error!("Failed to save selected alternate: {e}");
+                                                }
+                                            });
+                                        }
+                                    }
+                                },
+                                is_streaming: streaming_message_id().as_deref() == Some(message.id.as_str()),
+                                on_stop: move |_| {
+                                    if let Some(stop) = stop_signal.peek().as_ref() {
+                                        stop.stop();
+                                    }
+                                },
+                            }
                         }
-                        if is_typing() {
+                        if is_typing() && streaming_message_id().is_none() {
                             div { class: "flex items-center space-x-2 text-muted-foreground",
                                 div { class: "w-4 h-4 border-2 border-muted-foreground border-t-transparent rounded-full animate-spin" }
                                 span { "Story is continuing..." }
@@ -250,35 +351,157 @@ pub fn StoryView(story_id: String, navigate_to: EventHandler<Route>) -> Element
                         on_input_change: move |val| current_message.set(val),
                         on_send: {
                             let user_name = user_name.clone();
+                            let story_id = story_id.clone();
+                            let character_options = character_options.clone();
                             move |_| {
                                 if !current_message().trim().is_empty() {
-                                    let user_msg = StoryMessage {
-                                        id: format!("msg_{}", story_messages().len()),
-                                        role: StoryRole::User { name: user_name.clone() },
-                                        content: current_message(),
-                                    };
-                                    story_messages.with_mut(|msgs| msgs.push(user_msg));
+                                    let user_msg = StoryMessage::new(
+                                        uuid::Uuid::new_v4().to_string(),
+                                        StoryRole::User { name: user_name.clone() },
+                                        current_message(),
+                                    );
+
+                                    // Goals become hidden guidance for the reply to this message only
+                                    let guidance: Vec<MessageGuidance> = character_goals()
+                                        .iter()
+                                        .filter(|(_, goal)| !goal.trim().is_empty())
+                                        .map(|(character_id, goal)| {
+                                            let character = character_options
+                                                .iter()
+                                                .find(|c| &c.id == character_id && !c.is_narrator)
+                                                .map(|c| c.name.clone());
+                                            MessageGuidance::new(user_msg.id.clone(), LOCAL_USER_ID, character, goal.trim())
+                                        })
+                                        .collect();
+                                    character_goals.set(HashMap::new());
+                                    story_messages.with_mut(|msgs| msgs.push(user_msg.clone()));
                                     current_message.set(String::new());
                                     is_typing.set(true);
                                     
                                     // Auto-scroll after user message
                                     ScrollControl::scroll_to_bottom(scroll_controller);
                                     
-                                    // Simulate immediate story response for now
-                                    let story_msg = StoryMessage {
-                                        id: format!("msg_{}", story_messages().len()),
-                                        role: StoryRole::Narrator,
-                                        content: "The story continues with your choice, weaving new possibilities into the narrative thread...".to_string(),
+                                    // Stream the reply into a placeholder that can be stopped at any time
+                                    let path = story_messages();
+                                    let reply_id = uuid::Uuid::new_v4().to_string();
+                                    story_messages.with_mut(|msgs| {
+                                        msgs.push(StoryMessage::new(reply_id.clone(), StoryRole::Narrator, ""));
+                                    });
+                                    let stop = StopSignal::new();
+                                    stop_signal.set(Some(stop.clone()));
+                                    streaming_message_id.set(Some(reply_id.clone()));
+
+                                    let local = settings.read().get().local_backend.clone().unwrap_or_default();
+                                    let provider = local.selected_provider().cloned();
+                                    let templates = settings.read().get().instruct_templates.clone();
+
+                                    // Assemble the prompt from the story as it is now
+                                    let prompt_config = settings.read().get().prompt.clone();
+                                    let mut prompt = {
+                                        let characters = story_characters.read();
+                                        let persona = active_persona.read();
+                                        let scenario = story_scenario.read();
+                                        PromptBuilder::new(&prompt_config)
+                                            .characters(&characters)
+                                            .persona(persona.as_ref())
+                                            .scenario(scenario.as_ref())
+                                            .history(&path)
+                                            .guidance(guidance_for_next_generation(&path, &guidance))
+                                            .build()
                                     };
-                                    story_messages.with_mut(|msgs| msgs.push(story_msg));
-                                    is_typing.set(false);
-                                    
-                                    // Auto-scroll after story response
-                                    ScrollControl::scroll_to_bottom(scroll_controller);
+                                    // Fitted to the smallest context in the failover chain, so any provider can take it
+                                    if let Some(tightest) = local.tightest_provider() {
+                                        let counter = counter_for_model(&tightest.config.model);
+                                        let report = prompt.fit(counter.as_ref(), tightest.config.prompt_budget());
+                                        if report.is_trimmed() {
+                                            log::info!(
+                                                "Trimmed prompt to {}/{} tokens: {}",
+                                                report.prompt_tokens,
+                                                report.budget,
+                                                report.summary()
+                                            );
+                                            toaster.info(format!("Left out to fit the context window: {}", report.summary()));
+                                        }
+                                        if !report.fits() {
+                                            log::warn!("Prompt is still {} tokens over budget", report.prompt_tokens - report.budget);
+                                        }
+                                    }
+                                    let sampler = settings
+                                        .read()
+                                        .get()
+                                        .sampler_presets
+                                        .for_story(story_data.peek().as_ref().and_then(|s| s.sampler.as_ref()));
+                                    let request = prompt.to_request().with_sampler(sampler);
+
+                                    // Persist the user's message, then generate the reply to it
+                                    let backend = backend();
+                                    let story_id = story_id.clone();
+                                    Platform::spawn(async move {
+                                        if let Some(backend) = &backend {
+                                            if let Err(e) = backend.add_message(&story_id, &user_msg).await {
+                                                log::error!("Failed to save message: {e}");
+                                            }
+                                            for guidance in &guidance {
+                                                if let Err(e) = backend.add_guidance(guidance).await {
+                                                    log::error!("Failed to save guidance: {e}");
+                                                }
+                                            }
+                                        }
+
+                                        let result = generate_reply(
+                                            &local,
+                                            &templates,
+                                            &request,
+                                            &reply_id,
+                                            story_messages,
+                                            &stop,
+                                        )
+                                        .await;
+                                        streaming_message_id.set(None);
+                                        stop_signal.set(None);
+                                        is_typing.set(false);
+
+                                        // Whatever arrived before a stop or failure is kept
+                                        let story_msg = match result {
+                                            Ok(story_msg) => {
+                                                let metadata = story_msg.selected_metadata();
+                                                if metadata.and_then(|m| m.provider_id.as_ref()) != provider.as_ref().map(|p| &p.id) {
+                                                    let name = metadata.and_then(|m| m.provider.clone()).unwrap_or_default();
+                                                    toaster.info(format!("Answered by fallback provider {name}"));
+                                                }
+                                                Some(story_msg)
+                                            }
+                                            Err(e) => {
+                                                toaster.error(format!("Generation failed: {e}"));
+                                                story_messages.peek().iter().find(|m| m.id == reply_id).cloned()
+                                            }
+                                        };
+                                        match story_msg.filter(|m| !m.content.trim().is_empty()) {
+                                            Some(story_msg) => {
+                                                replace_message(story_messages, story_msg.clone());
+                                                if let Some(backend) = &backend {
+                                                    if let Err(e) = backend.add_message(&story_id, &story_msg).await {
+                                                        log::error!("Failed to save message: {e}");
+                                                    }
+                                                }
+                                            }
+                                            None => story_messages.with_mut(|msgs| msgs.retain(|m| m.id != reply_id)),
+                                        }
+
+                                        // Auto-scroll after story response
+                                        ScrollControl::scroll_to_bottom(scroll_controller);
+
+                                        if let Some(backend) = &backend {
+                                            match backend.list_branches(&story_id).await {
+                                                Ok(list) => branches.set(list),
+                                                Err(e) => log::error!("Failed to load branches: {e}"),
+                                            }
+                                        }
+                                    });
                                 }
                             }
                         },
-                        send_disabled: current_message().trim().is_empty(),
+                        send_disabled: current_message().trim().is_empty() || streaming_message_id().is_some(),
                         // Character selection props
                         is_expanded: show_character_menu(),
                         characters: character_options.clone(),
@@ -307,6 +530,208 @@ pub fn StoryView(story_id: String, navigate_to: EventHandler<Route>) -> Element
                     }
                 }
             }
+
+            GenerationSettingsPanel {
+                is_open: show_generation_settings,
+                draft: sampler_draft,
+                on_save: move |sampler: Option<SamplerSettings>| {
+                    let Some(mut story) = story_data.peek().clone() else { return };
+                    story.sampler = sampler;
+                    story_data.set(Some(story.clone()));
+                    show_generation_settings.set(false);
+                    let Some(backend) = backend() else { return };
+                    Platform::spawn(async move {
+                        match backend.update_story(&story).await {
+                            Ok(()) => {
+                                toaster.success("Generation settings saved");
+                            }
+                            Err(e) => {
+                                toaster.error(format!("Failed to save generation settings: {e}"));
+                            }
+                        }
+                    });
+                },
+            }
+
+            MessageEditor {
+                is_open: show_editor,
+                draft: edit_draft,
+                revisions: revisions(),
+                on_save: move |content: String| {
+                    let (Some(backend), Some(message_id)) = (backend(), editing_message_id()) else { return };
+                    Platform::spawn(async move {
+                        match backend.edit_message(&message_id, &content).await {
+                            Ok(message) => {
+                                replace_message(story_messages, message);
+                                show_editor.set(false);
+                            }
+                            Err(e) => {
+                                toaster.error(format!("Failed to save edit: {e}"));
+                            }
+                        }
+                    });
+                },
+                on_restore: move |revision_id: String| {
+                    let (Some(backend), Some(message_id)) = (backend(), editing_message_id()) else { return };
+                    Platform::spawn(async move {
+                        match backend.restore_revision(&message_id, &revision_id).await {
+                            Ok(message) => {
+                                edit_draft.set(message.content.clone());
+                                replace_message(story_messages, message);
+                                toaster.success("Revision restored");
+                                match backend.list_revisions(&message_id).await {
+                                    Ok(list) => revisions.set(list),
+                                    Err(e) => log::error!("Failed to load revisions: {e}"),
+                                }
+                            }
+                            Err(e) => {
+                                toaster.error(format!("Failed to restore revision: {e}"));
+                            }
+                        }
+                    });
+                },
+            }
+
+            StoryExportDialog {
+                is_open: show_export,
+                on_export: move |format: ExportFormat| {
+                    let Some(story) = story_data.peek().clone() else { return };
+                    show_export.set(false);
+                    let document = StoryDocument::new(story, story_messages.peek().clone());
+                    Platform::spawn(async move {
+                        let file = match document.save_file(format) {
+                            Ok(file) => file,
+                            Err(e) => {
+                                toaster.error(format!("Failed to export story: {e}"));
+                                return;
+                            }
+                        };
+                        match save_with_dialog(file).await {
+                            Ok(Some(location)) => {
+                                toaster.success(format!("Story saved to: {location}"));
+                            }
+                            Ok(None) => {}
+                            Err(e) => {
+                                toaster.error(format!("Failed to save story: {e}"));
+                            }
+                        }
+                    });
+                },
+            }
+
+            BranchHistory {
+                is_open: show_branch_history,
+                branches: branches(),
+                on_switch: {
+                    let story_id = story_id.clone();
+                    move |leaf_id: String| {
+                        let Some(backend) = backend() else { return };
+                        let story_id = story_id.clone();
+                        Platform::spawn(async move {
+                            match backend.switch_branch(&story_id, &leaf_id).await {
+                                Ok(()) => {
+                                    reload_thread(backend, &story_id, story_messages, branches).await;
+                                    show_branch_history.set(false);
+                                    ScrollControl::scroll_to_bottom(scroll_controller);
+                                }
+                                Err(e) => {
+                                    toaster.error(format!("Failed to switch branch: {e}"));
+                                }
+                            }
+                        });
+                    }
+                },
+                on_prune: {
+                    let story_id = story_id.clone();
+                    move |leaf_id: String| {
+                        let Some(backend) = backend() else { return };
+                        let story_id = story_id.clone();
+                        Platform::spawn(async move {
+                            match backend.prune_branch(&story_id, &leaf_id).await {
+                                Ok(()) => {
+                                    toaster.success("Branch deleted");
+                                    reload_thread(backend, &story_id, story_messages, branches).await;
+                                }
+                                Err(e) => {
+                                    toaster.error(format!("Failed to delete branch: {e}"));
+                                }
+                            }
+                        });
+                    }
+                },
+            }
         }
     }
 }
+
+/// Stream the narrator's reply into the message `reply_id`, falling back to other providers on failure
+async fn generate_reply(
+    local: &LocalBackendConfig,
+    templates: &TemplateRegistry,
+    request: &CompletionRequest,
+    reply_id: &str,
+    mut story_messages: Signal<Vec<StoryMessage>>,
+    stop: &StopSignal,
+) -> Result<StoryMessage, LlmError> {
+    let sleep: Sleep = Rc::new(|duration| Box::pin(Platform::sleep(duration)));
+    let provider = connect_with_fallbacks(local, templates, sleep)?;
+    let mut on_token = |token: &str| {
+        story_messages.with_mut(|messages| {
+            if let Some(message) = messages.iter_mut().find(|m| m.id == reply_id) {
+                message.content.push_str(token);
+            }
+        });
+    };
+    let completion = provider.stream(request, &mut on_token, stop).await?;
+    Ok(StoryMessage::generated(
+        reply_id,
+        StoryRole::Narrator,
+        completion.content.trim(),
+        completion.metadata,
+    ))
+}
+
+/// Reload the visible thread and the branch list after the message tree changed
+async fn reload_thread(
+    backend: SharedBackend,
+    story_id: &str,
+    mut story_messages: Signal<Vec<StoryMessage>>,
+    mut branches: Signal<Vec<StoryBranch>>,
+) {
+    match backend.list_messages(story_id).await {
+        Ok(messages) => story_messages.set(messages),
+        Err(e) => log::error!("Failed to load messages for story {story_id}: {e}"),
+    }
+    match backend.list_branches(story_id).await {
+        Ok(list) => branches.set(list),
+        Err(e) => log::error!("Failed to load branches for story {story_id}: {e}"),
+    }
+}
+
+/// Select the next (`Left`) or previous (`Right`) alternate of a message
+///
+/// Returns the updated message if the selection changed.
+fn swipe_alternate(
+    mut story_messages: Signal<Vec<StoryMessage>>,
+    message_id: &str,
+    direction: GestureDirection,
+) -> Option<StoryMessage> {
+    story_messages.with_mut(|messages| {
+        let message = messages.iter_mut().find(|m| m.id == message_id)?;
+        let changed = match direction {
+            GestureDirection::Left => message.next_alternate(),
+            GestureDirection::Right => message.previous_alternate(),
+            _ => false,
+        };
+        changed.then(|| message.clone())
+    })
+}
+
+/// Swap in an updated copy of a message shown in the story
+fn replace_message(mut story_messages: Signal<Vec<StoryMessage>>, message: StoryMessage) {
+    story_messages.with_mut(|messages| {
+        if let Some(existing) = messages.iter_mut().find(|m| m.id == message.id) {
+            *existing = message;
+        }
+    });
+}